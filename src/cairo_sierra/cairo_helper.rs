@@ -44,7 +44,7 @@ pub struct CompilerConfig<'c> {
     pub add_statements_functions: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TextPosition {
     /// Line index, 0 based.
     pub line: usize,
@@ -52,13 +52,13 @@ pub struct TextPosition {
     pub col: usize,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CairoLocation {
     pub file_name: String,
     pub start: TextPosition,
     pub end: TextPosition,
 }
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CairoInfo {
     pub fn_name: String,
     pub cairo_locations: Option<Vec<CairoLocation>>,
@@ -128,32 +128,31 @@ pub fn compile_prepared_db_program(
     main_crate_ids: Vec<CrateId>,
     compiler_config: CompilerConfig<'_>,
 ) -> Result<FullProgram> {
-    match compile_prepared_db(db, main_crate_ids, compiler_config) {
-        Ok(sierra_program_with_debug) => {
-            let statement_locations = sierra_program_with_debug.debug_info.statements_locations;
-            let statements_functions_map =
-                statement_locations.get_statements_functions_map_for_tests(db);
+    let sierra_program_with_debug = compile_prepared_db(db, main_crate_ids, compiler_config)?;
+    full_program(db, sierra_program_with_debug)
+}
 
-            let diagnostic_locations = get_diagnostic_locations(db, statement_locations);
+/// Maps the statements of a compiled program to the Cairo code they were generated from.
+pub fn full_program(
+    db: &RootDatabase,
+    sierra_program_with_debug: SierraProgramWithDebug,
+) -> Result<FullProgram> {
+    let statement_locations = sierra_program_with_debug.debug_info.statements_locations;
+    let statements_functions_map = statement_locations.get_statements_functions_map_for_tests(db);
 
-            let sierra_cairo_info_mapping = generate_sierra_to_cairo_statement_info(
-                db,
-                sierra_program_with_debug.program.statements.len() as usize,
-                statements_functions_map,
-                diagnostic_locations,
-            );
+    let diagnostic_locations = get_diagnostic_locations(db, statement_locations);
 
-            match sierra_cairo_info_mapping {
-                Ok(mapping) => Ok(FullProgram {
-                    contract: mapping.contract_code,
-                    program: sierra_program_with_debug.program,
-                    sierra_cairo_info_mapping: mapping.sierra_cairo_statement_info,
-                }),
-                Err(e) => Err(e.into()),
-            }
-        }
-        Err(e) => Err(e),
-    }
+    let mapping = generate_sierra_to_cairo_statement_info(
+        db,
+        sierra_program_with_debug.program.statements.len(),
+        statements_functions_map,
+        diagnostic_locations,
+    )?;
+    Ok(FullProgram {
+        contract: mapping.contract_code,
+        program: sierra_program_with_debug.program,
+        sierra_cairo_info_mapping: mapping.sierra_cairo_statement_info,
+    })
 }
 
 pub fn get_diagnostic_locations(
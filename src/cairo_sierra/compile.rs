@@ -32,12 +32,15 @@ use cairo_lang_starknet::contract::{
 use cairo_lang_starknet::plugin::consts::{CONSTRUCTOR_MODULE, EXTERNAL_MODULE, L1_HANDLER_MODULE};
 
 use super::cairo_helper::SierraCairoInfoMapping;
+use super::storage::{storage_layout, StorageVariable};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FullProgram {
     pub contract: String,
     pub sierra_contract_class: ContractClass,
     pub sierra_cairo_info_mapping: SierraCairoInfoMapping,
+    #[serde(default)]
+    pub storage_layout: Vec<StorageVariable>,
 }
 
 /// Runs Starknet contracts compiler.
@@ -148,6 +151,7 @@ fn compile_contract_with_prepared_and_checked_db(
         contract: contract_code,
         sierra_contract_class,
         sierra_cairo_info_mapping: sierra_cairo_statement_info,
+        storage_layout: storage_layout(db, contract)?,
     })
 }
 
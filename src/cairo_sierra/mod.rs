@@ -4,3 +4,4 @@ pub mod cairo_contract;
 pub mod cairo_contract_helper;
 pub mod cairo_helper;
 pub mod compile;
+pub mod storage;
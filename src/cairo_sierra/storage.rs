@@ -0,0 +1,334 @@
+use anyhow::{Context, Result};
+use cairo_lang_defs::ids::{ModuleId, NamedLanguageElementId, StructId};
+use cairo_lang_diagnostics::ToOption;
+use cairo_lang_semantic::db::SemanticGroup;
+use cairo_lang_semantic::{ConcreteTypeId, TypeLongId};
+use cairo_lang_starknet::contract::ContractDeclaration;
+use cairo_lang_starknet_classes::keccak::starknet_keccak;
+use cairo_lang_syntax::attribute::structured::AttributeStructurize;
+use cairo_lang_syntax::node::db::SyntaxGroup;
+use cairo_lang_syntax::node::helpers::QueryAttrs;
+use cairo_lang_syntax::node::{Terminal, TypedStablePtr};
+use cairo_lang_utils::LookupIntern;
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use starknet_types_core::felt::Felt as Felt252;
+use starknet_types_core::hash::{Pedersen, StarkHash};
+
+use crate::trace::abi_decoder::split_tuple;
+
+/// The name of the struct holding the storage variables of a contract or a component.
+const STORAGE_STRUCT_NAME: &str = "Storage";
+
+/// The attribute embedding the storage of a component in the storage of its contract.
+const SUBSTORAGE_ATTR: &str = "substorage";
+
+/// A member of the `Storage` struct of a contract.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageVariable {
+    pub name: String,
+    /// The full Cairo type of the variable, e.g. `LegacyMap::<ContractAddress, u256>`.
+    #[serde(rename = "type")]
+    pub ty: String,
+    /// The address of the variable, or of its first felt: the Starknet keccak of its name, hashed
+    /// with the address of its `#[substorage(v1)]` member if any.
+    pub base_address: Felt252,
+    /// The path of the `#[substorage]` members declaring the variable, e.g. `ownable`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub component: Option<String>,
+    /// How the keys of a map variable are hashed into the address of an entry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub map: Option<StorageMapLayout>,
+}
+
+/// The layout of a `LegacyMap` or `Map` storage variable, possibly nested.
+///
+/// The address of an entry is `pedersen(...pedersen(base_address, key_felts[0])..., key_felts[n])`
+/// modulo `2**251 - 256`, and the value is stored from there.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageMapLayout {
+    /// The key type of every nesting level of the map.
+    pub key_types: Vec<String>,
+    /// The type of the values, once all the keys are given.
+    pub value_type: String,
+    /// The serialized felts of the keys, in hashing order, e.g. `key0.low` for a `u256` key.
+    pub key_felts: Vec<String>,
+}
+
+impl StorageVariable {
+    pub fn new(name: String, ty: String, component: Option<String>) -> Self {
+        let base_address: BigUint = starknet_keccak(name.as_bytes());
+        let map = StorageMapLayout::from_type(&ty);
+        Self {
+            base_address: Felt252::from_bytes_be_slice(&base_address.to_bytes_be()),
+            name,
+            ty,
+            component,
+            map,
+        }
+    }
+}
+
+impl StorageMapLayout {
+    /// The layout of a map type, `None` if the type is not a map.
+    pub fn from_type(ty: &str) -> Option<Self> {
+        let mut key_types = vec![];
+        let mut value_type = ty;
+        while let Some((key_type, inner_type)) = map_types(value_type) {
+            key_types.push(key_type.to_string());
+            value_type = inner_type;
+        }
+        if key_types.is_empty() {
+            return None;
+        }
+        let mut key_felts = vec![];
+        for (level, key_type) in key_types.iter().enumerate() {
+            serialized_felts(key_type, format!("key{}", level), &mut key_felts);
+        }
+        Some(Self {
+            key_types,
+            value_type: value_type.to_string(),
+            key_felts,
+        })
+    }
+}
+
+/// The key and value types of a `LegacyMap` or `Map` type.
+fn map_types(ty: &str) -> Option<(&str, &str)> {
+    let (path, args) = ty.split_once("::<")?;
+    if !matches!(path.rsplit("::").next(), Some("LegacyMap" | "Map")) {
+        return None;
+    }
+    match split_tuple(args.strip_suffix('>')?)[..] {
+        [key_type, value_type] => Some((key_type, value_type)),
+        _ => None,
+    }
+}
+
+/// Names the felts a key of the given type is serialized to before hashing.
+fn serialized_felts(ty: &str, name: String, felts: &mut Vec<String>) {
+    if ty == "core::integer::u256" {
+        felts.push(format!("{}.low", name));
+        felts.push(format!("{}.high", name));
+    } else if let Some(members) = ty.strip_prefix('(').and_then(|ty| ty.strip_suffix(')')) {
+        for (index, member) in split_tuple(members).into_iter().enumerate() {
+            serialized_felts(member, format!("{}.{}", name, index), felts);
+        }
+    } else {
+        felts.push(name);
+    }
+}
+
+/// Returns the storage layout of a contract, in declaration order.
+///
+/// The variables of components embedded with `#[substorage(v0)]` share the namespace of the
+/// contract: their base address is the Starknet keccak of their own name, without prefix. The
+/// variables of components embedded with `#[substorage(v1)]` are prefixed by their member: their
+/// base address is `pedersen(sn_keccak(member), sn_keccak(name))`.
+pub fn storage_layout(
+    db: &dyn SemanticGroup,
+    contract: &ContractDeclaration,
+) -> Result<Vec<StorageVariable>> {
+    let module_id = ModuleId::Submodule(contract.submodule_id);
+    let storage_struct = db
+        .module_structs_ids(module_id)
+        .to_option()
+        .with_context(|| "Failed to list the contract structs.")?
+        .iter()
+        .find(|struct_id| struct_id.name(db.upcast()) == STORAGE_STRUCT_NAME)
+        .copied()
+        .with_context(|| "The contract has no Storage struct.")?;
+
+    let mut variables = vec![];
+    collect_storage_variables(db, storage_struct, None, None, &mut variables)?;
+    Ok(variables)
+}
+
+/// Collects the variables of a `Storage` struct, `prefix` being the address of the
+/// `#[substorage(v1)]` member holding it, if any.
+fn collect_storage_variables(
+    db: &dyn SemanticGroup,
+    storage_struct: StructId,
+    component: Option<&str>,
+    prefix: Option<Felt252>,
+    variables: &mut Vec<StorageVariable>,
+) -> Result<()> {
+    let members = db
+        .struct_members(storage_struct)
+        .to_option()
+        .with_context(|| "Failed to read the Storage struct members.")?;
+    for (name, member) in members.iter() {
+        if let TypeLongId::Concrete(ConcreteTypeId::Struct(concrete_struct)) =
+            member.ty.lookup_intern(db)
+        {
+            let struct_id = concrete_struct.struct_id(db);
+            if struct_id.name(db.upcast()) == STORAGE_STRUCT_NAME {
+                let component = match component {
+                    Some(component) => format!("{}.{}", component, name),
+                    None => name.to_string(),
+                };
+                let prefix = match substorage_version(db, storage_struct, name)?.as_deref() {
+                    // The storage structs generated by the compiler before Cairo 2.7 drop the
+                    // attributes of their members, and only accept `v0` components.
+                    None | Some("v0") => prefix,
+                    Some("v1") => Some(prefixed_address(prefix, name)),
+                    Some(version) => anyhow::bail!(
+                        "Unsupported `#[{}({})]` on the storage member `{}`.",
+                        SUBSTORAGE_ATTR,
+                        version,
+                        component
+                    ),
+                };
+                collect_storage_variables(db, struct_id, Some(&component), prefix, variables)?;
+                continue;
+            }
+        }
+        let mut variable = StorageVariable::new(
+            name.to_string(),
+            member.ty.format(db),
+            component.map(str::to_string),
+        );
+        if prefix.is_some() {
+            variable.base_address = prefixed_address(prefix, name);
+        }
+        variables.push(variable);
+    }
+    Ok(())
+}
+
+/// The argument of the `#[substorage]` attribute of a member of a `Storage` struct, if any.
+fn substorage_version(
+    db: &dyn SemanticGroup,
+    storage_struct: StructId,
+    member_name: &str,
+) -> Result<Option<String>> {
+    let syntax_db: &dyn SyntaxGroup = db.upcast();
+    let struct_ast = storage_struct.stable_ptr(db.upcast()).lookup(syntax_db);
+    let member = struct_ast
+        .members(syntax_db)
+        .elements(syntax_db)
+        .into_iter()
+        .find(|member| member.name(syntax_db).text(syntax_db) == member_name)
+        .with_context(|| format!("The storage member `{}` is not declared.", member_name))?;
+    Ok(member.find_attr(syntax_db, SUBSTORAGE_ATTR).map(|attr| {
+        let attr = attr.structurize(syntax_db);
+        attr.args
+            .iter()
+            .map(|arg| arg.text(syntax_db))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }))
+}
+
+/// The address of a storage member: the Starknet keccak of its name, hashed with the address of
+/// the storage node holding it, if any.
+fn prefixed_address(prefix: Option<Felt252>, name: &str) -> Felt252 {
+    let address: BigUint = starknet_keccak(name.as_bytes());
+    let address = Felt252::from_bytes_be_slice(&address.to_bytes_be());
+    match prefix {
+        Some(prefix) => Pedersen::hash(&prefix, &address),
+        None => address,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::compile_contract::compile_contract;
+
+    #[test]
+    fn test_map_layout() {
+        let layout = StorageMapLayout::from_type(
+            "core::starknet::storage::map::Map::<(core::felt252, core::integer::u256), core::starknet::storage::map::Map::<core::felt252, core::bool>>",
+        )
+        .unwrap();
+        assert_eq!(
+            layout.key_types,
+            vec!["(core::felt252, core::integer::u256)", "core::felt252"]
+        );
+        assert_eq!(layout.value_type, "core::bool");
+        assert_eq!(
+            layout.key_felts,
+            vec!["key0.0", "key0.1.low", "key0.1.high", "key1"]
+        );
+        assert!(StorageMapLayout::from_type("core::integer::u256").is_none());
+    }
+
+    #[test]
+    fn test_substorage_layout() {
+        let code = r#"
+            #[starknet::component]
+            mod owned {
+                #[storage]
+                struct Storage {
+                    owner: felt252,
+                }
+
+                #[event]
+                #[derive(Drop, starknet::Event)]
+                enum Event {}
+            }
+
+            #[starknet::component]
+            mod counted {
+                #[storage]
+                struct Storage {
+                    count: felt252,
+                }
+
+                #[event]
+                #[derive(Drop, starknet::Event)]
+                enum Event {}
+            }
+
+            #[starknet::contract]
+            mod contract {
+                component!(path: super::owned, storage: owned, event: OwnedEvent);
+                component!(path: super::counted, storage: counted, event: CountedEvent);
+
+                #[storage]
+                struct Storage {
+                    value: felt252,
+                    #[substorage(v0)]
+                    owned: super::owned::Storage,
+                    #[substorage(v1)]
+                    counted: super::counted::Storage,
+                }
+
+                #[event]
+                #[derive(Drop, starknet::Event)]
+                enum Event {
+                    OwnedEvent: super::owned::Event,
+                    CountedEvent: super::counted::Event,
+                }
+            }
+        "#;
+        let compilation_result = compile_contract(code, "contract").unwrap();
+        let layout = compilation_result.cairo_sierra.storage_layout;
+        let variables: Vec<_> = layout
+            .iter()
+            .map(|variable| {
+                (
+                    variable.name.as_str(),
+                    variable.component.as_deref(),
+                    variable.base_address,
+                )
+            })
+            .collect();
+        assert_eq!(
+            variables,
+            vec![
+                ("value", None, prefixed_address(None, "value")),
+                ("owner", Some("owned"), prefixed_address(None, "owner")),
+                (
+                    "count",
+                    Some("counted"),
+                    Pedersen::hash(
+                        &prefixed_address(None, "counted"),
+                        &prefixed_address(None, "count")
+                    )
+                ),
+            ]
+        );
+    }
+}
@@ -21,6 +21,17 @@ pub struct ContractClassIgnoreAbi {
 pub fn conpile_contract_sierra_to_casm(
     file_path: String,
 ) -> anyhow::Result<(SierraContractCompile)> {
+    let contract_class: ContractClassIgnoreAbi = serde_json::from_str(
+        &fs::read_to_string(file_path).with_context(|| "Failed to read file.")?,
+    )
+    .with_context(|| "Deserialization failed.")?;
+    compile_contract_class_to_casm(contract_class)
+}
+
+/// Compiles a Sierra contract class, e.g. fetched from a node, to CASM.
+pub fn compile_contract_class_to_casm(
+    contract_class: ContractClassIgnoreAbi,
+) -> anyhow::Result<SierraContractCompile> {
     let list_selector = ListSelector::DefaultList;
     let ContractClassIgnoreAbi {
         sierra_program,
@@ -28,10 +39,7 @@ pub fn conpile_contract_sierra_to_casm(
         contract_class_version,
         entry_points_by_type,
         _abi,
-    } = serde_json::from_str(
-        &fs::read_to_string(file_path).with_context(|| "Failed to read file.")?,
-    )
-    .with_context(|| "Deserialization failed.")?;
+    } = contract_class;
     let contract_class = ContractClass {
         sierra_program,
         sierra_program_debug_info,
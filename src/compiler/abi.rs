@@ -0,0 +1,180 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::compile_contract::ContractCompilationResult;
+
+/// The ABI of a contract, in the JSON format of Sierra contract classes.
+pub type Abi = Vec<AbiEntry>;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AbiEntry {
+    Function(AbiFunction),
+    Constructor(AbiConstructor),
+    L1Handler(AbiFunction),
+    Event(AbiEvent),
+    Struct(AbiStruct),
+    Enum(AbiEnum),
+    Interface(AbiInterface),
+    Impl(AbiImpl),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbiFunction {
+    pub name: String,
+    pub inputs: Vec<AbiInput>,
+    pub outputs: Vec<AbiOutput>,
+    pub state_mutability: StateMutability,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StateMutability {
+    External,
+    View,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbiConstructor {
+    pub name: String,
+    pub inputs: Vec<AbiInput>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbiInput {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbiOutput {
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbiInterface {
+    pub name: String,
+    pub items: Vec<AbiEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbiImpl {
+    pub name: String,
+    pub interface_name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbiStruct {
+    pub name: String,
+    pub members: Vec<AbiMember>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbiEnum {
+    pub name: String,
+    pub variants: Vec<AbiMember>,
+}
+
+/// A member of a struct or a variant of an enum.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbiMember {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbiEvent {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: AbiEventKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AbiEventKind {
+    Struct { members: Vec<AbiEventField> },
+    Enum { variants: Vec<AbiEventField> },
+}
+
+/// A member of a struct event or a variant of an enum event.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbiEventField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub kind: AbiEventFieldKind,
+}
+
+/// How an event field is serialized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AbiEventFieldKind {
+    /// Serialized into the keys (`#[key]`).
+    Key,
+    /// Serialized into the data.
+    Data,
+    /// A nested event, prefixed with the selector of the variant name.
+    Nested,
+    /// A nested event serialized as is (`#[flat]`).
+    Flat,
+}
+
+/// Returns the ABI of a compiled contract.
+pub fn contract_abi(compilation_result: &ContractCompilationResult) -> Result<Abi> {
+    let abi = compilation_result
+        .cairo_sierra
+        .sierra_contract_class
+        .abi
+        .as_ref()
+        .with_context(|| "The contract class has no ABI.")?;
+    serde_json::from_value(serde_json::to_value(abi)?).with_context(|| "Failed to parse ABI.")
+}
+
+/// Returns the external functions and L1 handlers of an ABI, including those of its interfaces.
+pub fn abi_functions(abi: &[AbiEntry]) -> Vec<&AbiFunction> {
+    abi.iter()
+        .flat_map(|entry| match entry {
+            AbiEntry::Function(function) | AbiEntry::L1Handler(function) => vec![function],
+            AbiEntry::Interface(interface) => abi_functions(&interface.items),
+            _ => vec![],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_abi() {
+        let abi: Abi = serde_json::from_str(
+            r#"[
+                {
+                    "type": "function",
+                    "name": "transfer",
+                    "inputs": [{ "name": "to", "type": "core::starknet::contract_address::ContractAddress" }],
+                    "outputs": [{ "type": "core::bool" }],
+                    "state_mutability": "external"
+                },
+                {
+                    "type": "event",
+                    "name": "erc20::ERC20::Event",
+                    "kind": "enum",
+                    "variants": [{ "name": "Transfer", "type": "erc20::ERC20::Transfer", "kind": "nested" }]
+                }
+            ]"#,
+        )
+        .unwrap();
+        assert!(matches!(abi[0], AbiEntry::Function(_)));
+        let AbiEntry::Event(event) = &abi[1] else {
+            panic!("Expected an event.");
+        };
+        let AbiEventKind::Enum { variants } = &event.kind else {
+            panic!("Expected an enum event.");
+        };
+        assert_eq!(variants[0].kind, AbiEventFieldKind::Nested);
+    }
+}
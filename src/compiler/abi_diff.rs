@@ -0,0 +1,323 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use starknet_types_core::felt::Felt as Felt252;
+
+use super::abi::{
+    abi_functions, contract_abi, Abi, AbiEntry, AbiEvent, AbiFunction, AbiInput, AbiOutput,
+    StateMutability,
+};
+use super::compile_contract::ContractCompilationResult;
+use crate::casm_sierra::cairo_contract_helper::CasmContractEntryPoint;
+use crate::trace::abi_decoder::selector_from_name;
+use crate::trace::execution::EntryPointType;
+use crate::trace::sierra_trace::SierraDebugInfo;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// An entrypoint added, removed, or whose signature changed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EntrypointDiff {
+    pub kind: DiffKind,
+    pub entry_point_type: EntryPointType,
+    pub selector: Felt252,
+    /// The function name, if the ABI declares the selector.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_signature: Option<String>,
+}
+
+/// An event added, removed, or whose members or variants changed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventDiff {
+    pub kind: DiffKind,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FunctionSizeDiff {
+    pub name: String,
+    /// The number of bytecode words of the function, `None` if it does not exist in the version.
+    pub old_size: Option<usize>,
+    pub new_size: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BytecodeDiff {
+    pub old_size: usize,
+    pub new_size: usize,
+    /// The Sierra functions whose bytecode size changed.
+    pub functions: Vec<FunctionSizeDiff>,
+}
+
+/// The external impact of going from one version of a contract to another.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClassDiff {
+    pub entrypoints: Vec<EntrypointDiff>,
+    pub events: Vec<EventDiff>,
+    pub bytecode: BytecodeDiff,
+}
+
+/// Diffs the entrypoints, events and bytecode sizes of two compiled versions of a contract.
+pub fn diff_classes(
+    old: &ContractCompilationResult,
+    new: &ContractCompilationResult,
+) -> Result<ClassDiff> {
+    let old_abi = contract_abi(old)?;
+    let new_abi = contract_abi(new)?;
+    Ok(ClassDiff {
+        entrypoints: diff_entrypoints(&entrypoints(old, &old_abi), &entrypoints(new, &new_abi)),
+        events: diff_events(&old_abi, &new_abi),
+        bytecode: diff_bytecode(old, new)?,
+    })
+}
+
+/// An entrypoint of a class, with the signature declared by the ABI.
+struct Entrypoint {
+    entry_point_type: EntryPointType,
+    name: Option<String>,
+    signature: Option<String>,
+}
+
+fn entrypoints(
+    compilation_result: &ContractCompilationResult,
+    abi: &Abi,
+) -> BTreeMap<Felt252, Entrypoint> {
+    let functions: HashMap<Felt252, &AbiFunction> = abi_functions(abi)
+        .into_iter()
+        .map(|function| (selector_from_name(&function.name), function))
+        .collect();
+    let constructor = abi.iter().find_map(|entry| match entry {
+        AbiEntry::Constructor(constructor) => Some(constructor),
+        _ => None,
+    });
+
+    let entry_points = &compilation_result
+        .casm_sierra
+        .casm_contract_class
+        .entry_points_by_type;
+    let by_type: [(EntryPointType, &Vec<CasmContractEntryPoint>); 3] = [
+        (EntryPointType::External, &entry_points.external),
+        (EntryPointType::L1Handler, &entry_points.l1_handler),
+        (EntryPointType::Constructor, &entry_points.constructor),
+    ];
+    let mut entrypoints = BTreeMap::new();
+    for (entry_point_type, entry_points) in by_type {
+        for entry_point in entry_points {
+            let selector = Felt252::from_bytes_be_slice(&entry_point.selector.to_bytes_be());
+            let (name, signature) = match entry_point_type {
+                EntryPointType::Constructor => (
+                    constructor.map(|constructor| constructor.name.clone()),
+                    constructor.map(|constructor| {
+                        format_signature(&constructor.name, &constructor.inputs, &[], None)
+                    }),
+                ),
+                _ => (
+                    functions
+                        .get(&selector)
+                        .map(|function| function.name.clone()),
+                    functions.get(&selector).map(|function| {
+                        format_signature(
+                            &function.name,
+                            &function.inputs,
+                            &function.outputs,
+                            Some(function.state_mutability),
+                        )
+                    }),
+                ),
+            };
+            entrypoints.insert(
+                selector,
+                Entrypoint {
+                    entry_point_type,
+                    name,
+                    signature,
+                },
+            );
+        }
+    }
+    entrypoints
+}
+
+/// Formats a function signature, e.g. `view fn balance_of(account: ContractAddress) -> u256`.
+fn format_signature(
+    name: &str,
+    inputs: &[AbiInput],
+    outputs: &[AbiOutput],
+    state_mutability: Option<StateMutability>,
+) -> String {
+    let inputs: Vec<String> = inputs
+        .iter()
+        .map(|input| format!("{}: {}", input.name, input.ty))
+        .collect();
+    let mut signature = format!("fn {}({})", name, inputs.join(", "));
+    if !outputs.is_empty() {
+        let outputs: Vec<&str> = outputs.iter().map(|output| output.ty.as_str()).collect();
+        signature.push_str(&format!(" -> {}", outputs.join(", ")));
+    }
+    match state_mutability {
+        Some(StateMutability::View) => format!("view {}", signature),
+        Some(StateMutability::External) => format!("external {}", signature),
+        None => signature,
+    }
+}
+
+fn diff_entrypoints(
+    old: &BTreeMap<Felt252, Entrypoint>,
+    new: &BTreeMap<Felt252, Entrypoint>,
+) -> Vec<EntrypointDiff> {
+    let selectors: BTreeSet<&Felt252> = old.keys().chain(new.keys()).collect();
+    selectors
+        .into_iter()
+        .filter_map(|selector| {
+            let (old, new) = (old.get(selector), new.get(selector));
+            let (kind, entrypoint) = match (old, new) {
+                (None, Some(new)) => (DiffKind::Added, new),
+                (Some(old), None) => (DiffKind::Removed, old),
+                (Some(old), Some(new))
+                    if old.signature != new.signature
+                        || old.entry_point_type != new.entry_point_type =>
+                {
+                    (DiffKind::Changed, new)
+                }
+                _ => return None,
+            };
+            Some(EntrypointDiff {
+                kind,
+                entry_point_type: entrypoint.entry_point_type,
+                selector: *selector,
+                name: entrypoint.name.clone(),
+                old_signature: old.and_then(|old| old.signature.clone()),
+                new_signature: new.and_then(|new| new.signature.clone()),
+            })
+        })
+        .collect()
+}
+
+fn diff_events(old_abi: &Abi, new_abi: &Abi) -> Vec<EventDiff> {
+    let events = |abi: &Abi| -> BTreeMap<String, AbiEvent> {
+        abi.iter()
+            .filter_map(|entry| match entry {
+                AbiEntry::Event(event) => Some((event.name.clone(), event.clone())),
+                _ => None,
+            })
+            .collect()
+    };
+    let (old, new) = (events(old_abi), events(new_abi));
+    let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let kind = match (old.get(name), new.get(name)) {
+                (None, Some(_)) => DiffKind::Added,
+                (Some(_), None) => DiffKind::Removed,
+                (Some(old), Some(new)) if old != new => DiffKind::Changed,
+                _ => return None,
+            };
+            Some(EventDiff {
+                kind,
+                name: name.clone(),
+            })
+        })
+        .collect()
+}
+
+fn diff_bytecode(
+    old: &ContractCompilationResult,
+    new: &ContractCompilationResult,
+) -> Result<BytecodeDiff> {
+    let old_size = old.casm_sierra.casm_contract_class.bytecode.len();
+    let new_size = new.casm_sierra.casm_contract_class.bytecode.len();
+    let old_functions = SierraDebugInfo::from_contract(old)?.bytecode_size_by_function(old_size);
+    let new_functions = SierraDebugInfo::from_contract(new)?.bytecode_size_by_function(new_size);
+
+    let names: BTreeSet<&String> = old_functions.keys().chain(new_functions.keys()).collect();
+    let functions = names
+        .into_iter()
+        .map(|name| FunctionSizeDiff {
+            name: name.clone(),
+            old_size: old_functions.get(name).copied(),
+            new_size: new_functions.get(name).copied(),
+        })
+        .filter(|function| function.old_size != function.new_size)
+        .collect();
+    Ok(BytecodeDiff {
+        old_size,
+        new_size,
+        functions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::compile_contract::compile_contract;
+
+    const CODE: &str = r#"#[starknet::interface]
+    pub trait ICounter<TContractState> {
+        fn get(self: @TContractState) -> felt252;
+        fn increase(ref self: TContractState, amount: felt252);
+    }
+
+    #[starknet::contract]
+    mod Counter {
+        #[storage]
+        struct Storage {
+            counter: felt252,
+        }
+
+        #[abi(embed_v0)]
+        impl CounterImpl of super::ICounter<ContractState> {
+            fn get(self: @ContractState) -> felt252 {
+                self.counter.read()
+            }
+
+            fn increase(ref self: ContractState, amount: felt252) {
+                self.counter.write(self.counter.read() + amount);
+            }
+        }
+    }
+    "#;
+
+    #[test]
+    fn test_diff_classes() {
+        let old = compile_contract(CODE, "counter").unwrap();
+        let new_code = CODE
+            .replace(
+                "fn increase(ref self: TContractState, amount: felt252);",
+                "fn increase(ref self: TContractState, amount: felt252, times: felt252);",
+            )
+            .replace(
+                "fn increase(ref self: ContractState, amount: felt252) {\n                self.counter.write(self.counter.read() + amount);",
+                "fn increase(ref self: ContractState, amount: felt252, times: felt252) {\n                self.counter.write(self.counter.read() + amount * times);",
+            );
+        let new = compile_contract(&new_code, "counter").unwrap();
+
+        let diff = diff_classes(&old, &new).unwrap();
+
+        assert_eq!(diff.entrypoints.len(), 1);
+        let entrypoint = &diff.entrypoints[0];
+        assert_eq!(entrypoint.kind, DiffKind::Changed);
+        assert_eq!(entrypoint.selector, selector_from_name("increase"));
+        assert_eq!(
+            entrypoint.new_signature.as_deref(),
+            Some("external fn increase(amount: core::felt252, times: core::felt252)")
+        );
+        assert!(diff.events.is_empty());
+        assert!(diff
+            .bytecode
+            .functions
+            .iter()
+            .any(|function| function.name.contains("increase")));
+    }
+}
@@ -14,6 +14,23 @@ pub struct ContractCompilationResult {
     pub casm_sierra: SierraContractCompile,
 }
 
+/// A contract given by its source code or by the result of compiling it.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ContractSource {
+    Code { code: String, file_name: String },
+    Compiled(Box<ContractCompilationResult>),
+}
+
+impl ContractSource {
+    pub fn compile(self) -> Result<ContractCompilationResult> {
+        match self {
+            ContractSource::Code { code, file_name } => compile_contract(&code, &file_name),
+            ContractSource::Compiled(compilation_result) => Ok(*compilation_result),
+        }
+    }
+}
+
 pub fn compile_contract(code: &str, file_name: &str) -> Result<ContractCompilationResult> {
     // Create a temporary directory
     let dir = tempdir()?;
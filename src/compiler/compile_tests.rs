@@ -0,0 +1,136 @@
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use cairo_lang_compiler::db::RootDatabase;
+use cairo_lang_compiler::diagnostics::DiagnosticsReporter;
+use cairo_lang_compiler::project::setup_project;
+use cairo_lang_defs::db::DefsGroup;
+use cairo_lang_defs::ids::{FunctionWithBodyId, ModuleItemId};
+use cairo_lang_diagnostics::ToOption;
+use cairo_lang_filesystem::cfg::{Cfg, CfgSet};
+use cairo_lang_filesystem::ids::CrateId;
+use cairo_lang_lowering::ids::ConcreteFunctionWithBodyId;
+use cairo_lang_semantic::db::SemanticGroup;
+use cairo_lang_sierra_generator::db::SierraGenGroup;
+use cairo_lang_sierra_generator::replace_ids::replace_sierra_ids_in_program;
+use cairo_lang_starknet::starknet_plugin_suite;
+use cairo_lang_test_plugin::test_config::try_extract_test_config;
+use cairo_lang_test_plugin::{
+    compile_test_prepared_db, test_plugin_suite, TestCompilation, TestsCompilationConfig,
+};
+use cairo_lang_utils::Upcast;
+use tempfile::{tempdir, NamedTempFile};
+
+use crate::cairo_sierra::cairo_helper::{full_program, FullProgram};
+
+/// Compiled tests.
+pub struct TestsCompilationResult {
+    pub test_compilation: TestCompilation,
+    /// The test functions alone, with the Cairo code of their statements. Its statements are not
+    /// those of `test_compilation`, whose program also holds the contracts the tests use.
+    pub debug_program: FullProgram,
+}
+
+/// Compiles the `#[test]` functions of the given code, including those in `#[cfg(test)]`
+/// modules.
+pub fn compile_tests(code: &str, file_name: &str) -> Result<TestsCompilationResult> {
+    // Create a temporary directory
+    let dir = tempdir()?;
+
+    let cairo_file_path = dir.path().join(format!("{}.cairo", file_name));
+
+    // Create and write to the file
+    let mut cairo_temp_file = NamedTempFile::new_in(dir.path())?;
+    cairo_temp_file.write_all(code.as_bytes())?;
+    cairo_temp_file.persist(&cairo_file_path)?;
+
+    compile_tests_at_path(&cairo_file_path)
+}
+
+/// Compiles the tests of the Cairo project at the given path.
+pub fn compile_tests_at_path(path: &Path) -> Result<TestsCompilationResult> {
+    let mut db = RootDatabase::builder()
+        .detect_corelib()
+        .with_cfg(CfgSet::from_iter([Cfg::name("test")]))
+        .with_plugin_suite(test_plugin_suite())
+        .with_plugin_suite(starknet_plugin_suite())
+        .build()?;
+
+    let main_crate_ids = setup_project(&mut db, path)?;
+    DiagnosticsReporter::stderr().allow_warnings().ensure(&db)?;
+
+    let test_compilation = compile_test_prepared_db(
+        &db,
+        TestsCompilationConfig {
+            starknet: true,
+            add_statements_functions: false,
+        },
+        main_crate_ids.clone(),
+        main_crate_ids.clone(),
+    )?;
+
+    let mut sierra_program_with_debug = Arc::unwrap_or_clone(
+        db.get_sierra_program_for_functions(test_functions(&db, main_crate_ids))
+            .to_option()
+            .context("Compilation failed without any diagnostics")?,
+    );
+    sierra_program_with_debug.program =
+        replace_sierra_ids_in_program(&db, &sierra_program_with_debug.program);
+
+    Ok(TestsCompilationResult {
+        test_compilation,
+        debug_program: full_program(&db, sierra_program_with_debug)?,
+    })
+}
+
+/// The `#[test]` functions of the given crates.
+fn test_functions(db: &RootDatabase, crate_ids: Vec<CrateId>) -> Vec<ConcreteFunctionWithBodyId> {
+    let mut functions = vec![];
+    for crate_id in crate_ids {
+        for module_id in db.crate_modules(crate_id).iter() {
+            let Ok(module_items) = db.module_items(*module_id) else {
+                continue;
+            };
+            functions.extend(module_items.iter().filter_map(|item| {
+                let ModuleItemId::FreeFunction(func_id) = item else {
+                    return None;
+                };
+                let attrs = db
+                    .function_with_body_attributes(FunctionWithBodyId::Free(*func_id))
+                    .ok()?;
+                try_extract_test_config(db.upcast(), attrs).ok()??;
+                ConcreteFunctionWithBodyId::from_no_generics_free(db, *func_id)
+            }));
+        }
+    }
+    functions
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_compile_tests() {
+        let code = r#"fn add(a: felt252, b: felt252) -> felt252 {
+            a + b
+        }
+
+        #[cfg(test)]
+        mod tests {
+            #[test]
+            fn test_add() {
+                assert(super::add(1, 2) == 3, 'wrong sum');
+            }
+        }
+        "#;
+        let result = super::compile_tests(code, "add").unwrap();
+        assert_eq!(result.test_compilation.metadata.named_tests.len(), 1);
+        assert!(result
+            .debug_program
+            .program
+            .funcs
+            .iter()
+            .any(|func| func.id.to_string().ends_with("::test_add")));
+    }
+}
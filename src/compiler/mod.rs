@@ -1,3 +1,7 @@
+pub mod abi;
+pub mod abi_diff;
 pub mod compile;
 pub mod compile_contract;
+pub mod compile_tests;
 pub mod helper;
+pub mod upgrade;
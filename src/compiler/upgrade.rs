@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::abi::{abi_functions, contract_abi, Abi, AbiFunction};
+use super::compile_contract::ContractCompilationResult;
+use crate::cairo_sierra::storage::StorageVariable;
+
+/// Whether a change keeps the storage and the callers of a contract working after an upgrade.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeSeverity {
+    Safe,
+    Breaking,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageChangeKind {
+    /// A new variable, starting out zeroed.
+    Added,
+    /// A variable no longer declared: its values stay in storage, unreachable.
+    Removed,
+    /// A variable replaced by one of the same type under another name: its values stay at the
+    /// address of the old name.
+    Renamed,
+    /// A variable whose values are read with another type.
+    TypeChanged,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StorageChange {
+    pub kind: StorageChangeKind,
+    pub severity: ChangeSeverity,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_name: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub ty: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_type: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntrypointChangeKind {
+    Added,
+    Removed,
+    /// The inputs, outputs or state mutability of the entrypoint changed.
+    SignatureChanged,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EntrypointChange {
+    pub kind: EntrypointChangeKind,
+    pub severity: ChangeSeverity,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpgradeReport {
+    /// Whether no change is breaking.
+    pub compatible: bool,
+    pub storage_changes: Vec<StorageChange>,
+    pub entrypoint_changes: Vec<EntrypointChange>,
+}
+
+/// Checks that a contract can be upgraded from one compiled version to another with `replace_class`.
+pub fn check_contract_upgrade(
+    old: &ContractCompilationResult,
+    new: &ContractCompilationResult,
+) -> Result<UpgradeReport> {
+    Ok(check_upgrade(
+        &old.cairo_sierra.storage_layout,
+        &contract_abi(old)?,
+        &new.cairo_sierra.storage_layout,
+        &contract_abi(new)?,
+    ))
+}
+
+/// Compares the storage layouts and ABIs of two versions of a contract.
+///
+/// Storage addresses derive from variable names, so reordering variables is safe, while renaming
+/// or retyping them loses or corrupts their values.
+pub fn check_upgrade(
+    old_layout: &[StorageVariable],
+    old_abi: &Abi,
+    new_layout: &[StorageVariable],
+    new_abi: &Abi,
+) -> UpgradeReport {
+    let storage_changes = storage_changes(old_layout, new_layout);
+    let entrypoint_changes = entrypoint_changes(old_abi, new_abi);
+    let compatible = storage_changes
+        .iter()
+        .map(|change| change.severity)
+        .chain(entrypoint_changes.iter().map(|change| change.severity))
+        .all(|severity| severity == ChangeSeverity::Safe);
+    UpgradeReport {
+        compatible,
+        storage_changes,
+        entrypoint_changes,
+    }
+}
+
+fn storage_changes(
+    old_layout: &[StorageVariable],
+    new_layout: &[StorageVariable],
+) -> Vec<StorageChange> {
+    let old_types: HashMap<&str, &str> = old_layout
+        .iter()
+        .map(|variable| (variable.name.as_str(), variable.ty.as_str()))
+        .collect();
+    let new_names: Vec<&str> = new_layout
+        .iter()
+        .map(|variable| variable.name.as_str())
+        .collect();
+
+    let mut changes = vec![];
+    let mut removed: Vec<&StorageVariable> = old_layout
+        .iter()
+        .filter(|variable| !new_names.contains(&variable.name.as_str()))
+        .collect();
+    for variable in new_layout {
+        match old_types.get(variable.name.as_str()) {
+            Some(old_type) if *old_type != variable.ty => changes.push(StorageChange {
+                kind: StorageChangeKind::TypeChanged,
+                severity: ChangeSeverity::Breaking,
+                name: variable.name.clone(),
+                previous_name: None,
+                ty: Some(variable.ty.clone()),
+                previous_type: Some(old_type.to_string()),
+            }),
+            Some(_) => {}
+            None => {
+                // A removed variable of the same type is taken to be renamed into this one.
+                let renamed = removed
+                    .iter()
+                    .position(|old| old.ty == variable.ty)
+                    .map(|index| removed.remove(index));
+                changes.push(StorageChange {
+                    kind: match renamed {
+                        Some(_) => StorageChangeKind::Renamed,
+                        None => StorageChangeKind::Added,
+                    },
+                    severity: match renamed {
+                        Some(_) => ChangeSeverity::Breaking,
+                        None => ChangeSeverity::Safe,
+                    },
+                    name: variable.name.clone(),
+                    previous_name: renamed.map(|old| old.name.clone()),
+                    ty: Some(variable.ty.clone()),
+                    previous_type: None,
+                });
+            }
+        }
+    }
+    changes.extend(removed.into_iter().map(|variable| StorageChange {
+        kind: StorageChangeKind::Removed,
+        severity: ChangeSeverity::Safe,
+        name: variable.name.clone(),
+        previous_name: None,
+        ty: None,
+        previous_type: Some(variable.ty.clone()),
+    }));
+    changes
+}
+
+fn entrypoint_changes(old_abi: &Abi, new_abi: &Abi) -> Vec<EntrypointChange> {
+    let old_functions: HashMap<&str, &AbiFunction> = abi_functions(old_abi)
+        .into_iter()
+        .map(|function| (function.name.as_str(), function))
+        .collect();
+    let new_functions = abi_functions(new_abi);
+
+    let mut changes = vec![];
+    for function in &new_functions {
+        let kind = match old_functions.get(function.name.as_str()) {
+            None => EntrypointChangeKind::Added,
+            Some(old) if *old != *function => EntrypointChangeKind::SignatureChanged,
+            Some(_) => continue,
+        };
+        changes.push(EntrypointChange {
+            kind,
+            severity: match kind {
+                EntrypointChangeKind::Added => ChangeSeverity::Safe,
+                _ => ChangeSeverity::Breaking,
+            },
+            name: function.name.clone(),
+        });
+    }
+    for function in abi_functions(old_abi) {
+        if !new_functions.iter().any(|new| new.name == function.name) {
+            changes.push(EntrypointChange {
+                kind: EntrypointChangeKind::Removed,
+                severity: ChangeSeverity::Breaking,
+                name: function.name.clone(),
+            });
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable(name: &str, ty: &str) -> StorageVariable {
+        StorageVariable::new(name.to_string(), ty.to_string(), None)
+    }
+
+    #[test]
+    fn test_check_upgrade() {
+        let old_abi: Abi = serde_json::from_str(
+            r#"[
+                { "type": "function", "name": "get", "inputs": [], "outputs": [{ "type": "core::felt252" }], "state_mutability": "view" },
+                { "type": "function", "name": "set", "inputs": [{ "name": "value", "type": "core::felt252" }], "outputs": [], "state_mutability": "external" }
+            ]"#,
+        )
+        .unwrap();
+        let new_abi: Abi = serde_json::from_str(
+            r#"[
+                { "type": "function", "name": "get", "inputs": [], "outputs": [{ "type": "core::felt252" }], "state_mutability": "view" },
+                { "type": "function", "name": "reset", "inputs": [], "outputs": [], "state_mutability": "external" }
+            ]"#,
+        )
+        .unwrap();
+        let old_layout = vec![
+            variable("owner", "core::starknet::contract_address::ContractAddress"),
+            variable("value", "core::felt252"),
+            variable("count", "core::integer::u32"),
+        ];
+        let new_layout = vec![
+            variable("count", "core::integer::u64"),
+            variable("admin", "core::starknet::contract_address::ContractAddress"),
+            variable("value", "core::felt252"),
+            variable("paused", "core::bool"),
+        ];
+
+        let report = check_upgrade(&old_layout, &old_abi, &new_layout, &new_abi);
+
+        assert!(!report.compatible);
+        let storage_changes: Vec<_> = report
+            .storage_changes
+            .iter()
+            .map(|change| (change.kind, change.name.as_str()))
+            .collect();
+        assert_eq!(
+            storage_changes,
+            vec![
+                (StorageChangeKind::TypeChanged, "count"),
+                (StorageChangeKind::Renamed, "admin"),
+                (StorageChangeKind::Added, "paused"),
+            ]
+        );
+        assert_eq!(
+            report.storage_changes[1].previous_name.as_deref(),
+            Some("owner")
+        );
+        let entrypoint_changes: Vec<_> = report
+            .entrypoint_changes
+            .iter()
+            .map(|change| (change.kind, change.severity, change.name.as_str()))
+            .collect();
+        assert_eq!(
+            entrypoint_changes,
+            vec![
+                (EntrypointChangeKind::Added, ChangeSeverity::Safe, "reset"),
+                (
+                    EntrypointChangeKind::Removed,
+                    ChangeSeverity::Breaking,
+                    "set"
+                ),
+            ]
+        );
+    }
+}
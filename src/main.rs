@@ -1,8 +1,12 @@
 pub mod cairo_sierra;
 pub mod casm_sierra;
 pub mod compiler;
+pub mod rpc;
+pub mod state;
 pub mod trace;
 
+use std::sync::Mutex;
+
 use actix_cors::Cors;
 use actix_web::{http, web, App, HttpResponse, HttpServer, Responder};
 use cairo_lang_starknet_classes_2_point_6::casm_contract_class::CasmContractClass;
@@ -11,6 +15,9 @@ use starknet_types_core::felt::Felt;
 
 use serde::Deserialize;
 
+/// The maximal size of a state dump loaded with `/state/load`, in bytes.
+const MAX_STATE_DUMP_SIZE: usize = 256 * 1024 * 1024;
+
 #[derive(Deserialize)]
 struct CompileInput {
     code: String,
@@ -22,6 +29,124 @@ struct TraceInput {
     args: Vec<String>,
     casm_contract_class: String,
     entrypoint_offset: usize,
+    #[serde(default)]
+    compilation_result: Option<compiler::compile_contract::ContractCompilationResult>,
+    #[serde(default)]
+    export_memory: bool,
+    #[serde(default)]
+    config: trace::config::RunConfig,
+}
+
+#[derive(Deserialize)]
+struct ProofInput {
+    args: Vec<String>,
+    casm_contract_class: String,
+    entrypoint_offset: usize,
+    #[serde(default)]
+    config: trace::config::RunConfig,
+}
+
+#[derive(Deserialize)]
+struct RunInput {
+    compilation_result: compiler::compile::CompilationResult,
+    #[serde(default)]
+    function: Option<String>,
+    #[serde(default)]
+    args: Vec<trace::run::RunArg>,
+    #[serde(default)]
+    config: trace::config::RunConfig,
+}
+
+#[derive(Deserialize)]
+struct DeclareInput {
+    casm_contract_class: String,
+    #[serde(default)]
+    compilation_result: Option<compiler::compile_contract::ContractCompilationResult>,
+}
+
+#[derive(Deserialize)]
+struct DeployInput {
+    class_hash: String,
+    contract_address: String,
+}
+
+#[derive(Deserialize)]
+struct ForkInput {
+    rpc_url: String,
+    block: state::fork::ForkBlock,
+}
+
+#[derive(Deserialize)]
+struct RevertInput {
+    snapshot_id: usize,
+}
+
+#[derive(Deserialize)]
+struct DumpInput {
+    #[serde(default)]
+    format: state::dump::DumpFormat,
+}
+
+#[derive(Deserialize)]
+struct InvokeInput {
+    sender_address: Felt,
+    /// The calls the account executes, passed to `__execute__` as a multicall array.
+    calls: Vec<rpc::types::FunctionCall>,
+    #[serde(default)]
+    signature: Vec<Felt>,
+    /// Defaults to the current nonce of the account.
+    #[serde(default)]
+    nonce: Option<Felt>,
+    #[serde(default)]
+    max_fee: Option<Felt>,
+    /// Signs the transaction with this key instead of using `signature`.
+    #[serde(default)]
+    private_key: Option<Felt>,
+    #[serde(default)]
+    config: trace::config::RunConfig,
+}
+
+#[derive(Deserialize)]
+struct TransactionHashInput {
+    transaction: rpc::types::BroadcastedTransaction,
+    /// Defaults to the chain of the default execution context.
+    #[serde(default)]
+    chain_id: Option<Felt>,
+    /// Signs the transaction hash with this key if set.
+    #[serde(default)]
+    private_key: Option<Felt>,
+}
+
+#[derive(Deserialize)]
+struct ContractAddressInput {
+    class_hash: Felt,
+    #[serde(default)]
+    salt: Felt,
+    #[serde(default)]
+    constructor_calldata: Vec<Felt>,
+    /// The contract deploying, zero for `deploy_from_zero` and deploy account transactions.
+    #[serde(default)]
+    deployer_address: Felt,
+}
+
+#[derive(Deserialize)]
+struct CallInput {
+    contract_address: String,
+    /// The entrypoint name or selector.
+    entry_point: String,
+    #[serde(default)]
+    calldata: Vec<String>,
+    #[serde(default)]
+    caller_address: Option<String>,
+    #[serde(default)]
+    config: trace::config::RunConfig,
+}
+
+/// Two versions of a contract to compare.
+#[derive(Deserialize)]
+struct CompareInput {
+    old: compiler::compile_contract::ContractSource,
+    new: compiler::compile_contract::ContractSource,
 }
 
 // This function will handle POST requests to "/compile"
@@ -42,6 +167,32 @@ async fn compile_contract_code(input: web::Json<CompileInput>) -> impl Responder
     }
 }
 
+// This function will handle POST requests to "/upgrade_check"
+async fn upgrade_check(input: web::Json<CompareInput>) -> impl Responder {
+    let input = input.into_inner();
+    let result = input.old.compile().and_then(|old| {
+        let new = input.new.compile()?;
+        compiler::upgrade::check_contract_upgrade(&old, &new)
+    });
+    match result {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+// This function will handle POST requests to "/class_diff"
+async fn class_diff(input: web::Json<CompareInput>) -> impl Responder {
+    let input = input.into_inner();
+    let result = input.old.compile().and_then(|old| {
+        let new = input.new.compile()?;
+        compiler::abi_diff::diff_classes(&old, &new)
+    });
+    match result {
+        Ok(diff) => HttpResponse::Ok().json(diff),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
 // This function will handle POST requests to "/trace_error"
 async fn trace_error(input: web::Json<TraceInput>) -> impl Responder {
     let casm_contract_class = serde_json::from_str::<CasmContractClass>(&input.casm_contract_class)
@@ -55,10 +206,18 @@ async fn trace_error(input: web::Json<TraceInput>) -> impl Responder {
 
     let relocated_args: Vec<MaybeRelocatable> = args.iter().map(MaybeRelocatable::from).collect();
 
+    let input = input.into_inner();
+    let class = match state::DeclaredClass::new(casm_contract_class, input.compilation_result) {
+        Ok(class) => class,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+
     let result = trace::cairo_runner::trace_error(
-        casm_contract_class,
+        &class,
         input.entrypoint_offset,
         &relocated_args,
+        input.export_memory,
+        &input.config,
     );
 
     match result {
@@ -67,9 +226,380 @@ async fn trace_error(input: web::Json<TraceInput>) -> impl Responder {
     }
 }
 
+// This function will handle POST requests to "/proof_inputs"
+async fn proof_inputs(input: web::Json<ProofInput>) -> impl Responder {
+    let casm_contract_class =
+        match serde_json::from_str::<CasmContractClass>(&input.casm_contract_class) {
+            Ok(casm_contract_class) => casm_contract_class,
+            Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+        };
+    let args: Vec<MaybeRelocatable> = input
+        .args
+        .iter()
+        .map(|arg| MaybeRelocatable::from(Felt::from_hex_unchecked(arg)))
+        .collect();
+
+    let result = trace::proof::generate_proof_inputs(
+        casm_contract_class,
+        input.entrypoint_offset,
+        &args,
+        &input.config,
+    );
+
+    match result {
+        Ok(proof_inputs) => HttpResponse::Ok().json(proof_inputs),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+// This function will handle POST requests to "/run"
+async fn run_program(input: web::Json<RunInput>) -> impl Responder {
+    let result = trace::run::run_program(
+        &input.compilation_result,
+        input.function.as_deref(),
+        &input.args,
+        &input.config,
+    );
+
+    match result {
+        Ok(execution_result) => HttpResponse::Ok().json(execution_result),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+// This function will handle POST requests to "/test"
+async fn run_tests(input: web::Json<CompileInput>) -> impl Responder {
+    let result = trace::test_runner::run_tests(&input.code, &input.file_name);
+    match result {
+        Ok(summary) => HttpResponse::Ok().json(summary),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+// This function will handle POST requests to "/state/declare"
+async fn declare_class(
+    state: web::Data<Mutex<state::State>>,
+    input: web::Json<DeclareInput>,
+) -> impl Responder {
+    let casm_contract_class =
+        match serde_json::from_str::<CasmContractClass>(&input.casm_contract_class) {
+            Ok(casm_contract_class) => casm_contract_class,
+            Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+        };
+    let class =
+        match state::DeclaredClass::new(casm_contract_class, input.into_inner().compilation_result)
+        {
+            Ok(class) => class,
+            Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+        };
+
+    let compiled_class_hash = class.compiled_class_hash;
+    let class_hash = state.lock().unwrap().declare_class(class);
+    HttpResponse::Ok().json(serde_json::json!({
+        "class_hash": class_hash,
+        "compiled_class_hash": compiled_class_hash,
+    }))
+}
+
+// This function will handle POST requests to "/state/deploy"
+async fn deploy_contract(
+    state: web::Data<Mutex<state::State>>,
+    input: web::Json<DeployInput>,
+) -> impl Responder {
+    let (class_hash, contract_address) = match (
+        trace::run::parse_felt(&input.class_hash),
+        trace::run::parse_felt(&input.contract_address),
+    ) {
+        (Ok(class_hash), Ok(contract_address)) => (class_hash, contract_address),
+        (Err(e), _) | (_, Err(e)) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+
+    let result = state
+        .lock()
+        .unwrap()
+        .deploy_contract(contract_address, class_hash);
+    match result {
+        Ok(()) => {
+            HttpResponse::Ok().json(serde_json::json!({ "contract_address": contract_address }))
+        }
+        Err(e) => HttpResponse::BadRequest().body(e.to_string()),
+    }
+}
+
+// This function will handle POST requests to "/state/genesis"
+async fn genesis_state(
+    state: web::Data<Mutex<state::State>>,
+    input: web::Json<state::genesis::GenesisConfig>,
+) -> impl Responder {
+    let mut genesis = state::State::default();
+    match input.predeploy(&mut genesis) {
+        Ok(accounts) => {
+            *state.lock().unwrap() = genesis;
+            HttpResponse::Ok().json(accounts)
+        }
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+// This function will handle POST requests to "/state/fork"
+async fn fork_state(
+    state: web::Data<Mutex<state::State>>,
+    input: web::Json<ForkInput>,
+) -> impl Responder {
+    let input = input.into_inner();
+    let fork = state::fork::ForkStateReader::new(&input.rpc_url, input.block);
+    *state.lock().unwrap() = state::State::forked(fork);
+    HttpResponse::Ok().finish()
+}
+
+// This function will handle POST requests to "/state/block"
+async fn set_block(
+    state: web::Data<Mutex<state::State>>,
+    input: web::Json<state::BlockInfo>,
+) -> impl Responder {
+    state.lock().unwrap().block = input.into_inner();
+    HttpResponse::Ok().finish()
+}
+
+// This function will handle POST requests to "/state/snapshot"
+async fn snapshot_state(
+    state: web::Data<Mutex<state::State>>,
+    snapshots: web::Data<Mutex<state::snapshot::Snapshots>>,
+) -> impl Responder {
+    let state = state.lock().unwrap();
+    let snapshot_id = snapshots.lock().unwrap().take(&state);
+    HttpResponse::Ok().json(serde_json::json!({ "snapshot_id": snapshot_id }))
+}
+
+// This function will handle POST requests to "/state/revert"
+async fn revert_state(
+    state: web::Data<Mutex<state::State>>,
+    snapshots: web::Data<Mutex<state::snapshot::Snapshots>>,
+    input: web::Json<RevertInput>,
+) -> impl Responder {
+    let mut state = state.lock().unwrap();
+    match snapshots.lock().unwrap().revert(input.snapshot_id) {
+        Ok(snapshot) => {
+            *state = snapshot;
+            HttpResponse::Ok().finish()
+        }
+        Err(e) => HttpResponse::BadRequest().body(e.to_string()),
+    }
+}
+
+// This function will handle POST requests to "/state/dump"
+async fn dump_state(
+    state: web::Data<Mutex<state::State>>,
+    input: web::Json<DumpInput>,
+) -> impl Responder {
+    let state = state.lock().unwrap();
+    let content_type = match input.format {
+        state::dump::DumpFormat::Json => "application/json",
+        state::dump::DumpFormat::Binary => "application/gzip",
+    };
+    match state::dump::dump(&state, input.format) {
+        Ok(dump) => HttpResponse::Ok().content_type(content_type).body(dump),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+// This function will handle POST requests to "/state/load"
+async fn load_state(state: web::Data<Mutex<state::State>>, body: web::Bytes) -> impl Responder {
+    match state::dump::load(&body) {
+        Ok(loaded) => {
+            *state.lock().unwrap() = loaded;
+            HttpResponse::Ok().finish()
+        }
+        Err(e) => HttpResponse::BadRequest().body(e.to_string()),
+    }
+}
+
+/// Parses the body of a request executed against the state, also as JSON to tell the fields of its
+/// `config` that are set from those left to the block of the state.
+fn parse_state_request<T: serde::de::DeserializeOwned>(
+    body: &[u8],
+) -> anyhow::Result<(T, serde_json::Value)> {
+    Ok((serde_json::from_slice(body)?, serde_json::from_slice(body)?))
+}
+
+fn parse_call(input: &CallInput) -> anyhow::Result<trace::execution::CallEntryPoint> {
+    Ok(trace::execution::CallEntryPoint {
+        call_type: trace::execution::CallType::Call,
+        caller_address: match &input.caller_address {
+            Some(caller_address) => trace::run::parse_felt(caller_address)?,
+            None => input
+                .config
+                .execution_context
+                .caller_address
+                .unwrap_or_default(),
+        },
+        contract_address: trace::run::parse_felt(&input.contract_address)?,
+        class_hash: None,
+        entry_point_type: trace::execution::EntryPointType::External,
+        entry_point_selector: trace::execution::parse_entry_point_selector(&input.entry_point)?,
+        calldata: input
+            .calldata
+            .iter()
+            .map(|value| trace::run::parse_felt(value))
+            .collect::<anyhow::Result<_>>()?,
+        initial_gas: input.config.initial_gas,
+    })
+}
+
+// This function will handle POST requests to "/rpc"
+async fn json_rpc(
+    state: web::Data<Mutex<state::State>>,
+    body: web::Json<serde_json::Value>,
+) -> impl Responder {
+    let mut state = state.lock().unwrap();
+    let config = state.block.run_config();
+    let response = rpc::handle(&mut state, &config, body.into_inner());
+    HttpResponse::Ok().json(response)
+}
+
+// This function will handle POST requests to "/replay"
+async fn replay_transaction(input: web::Json<rpc::replay::Replay>) -> impl Responder {
+    match rpc::replay::replay(input.into_inner()) {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+// This function will handle POST requests to "/state/invoke"
+async fn invoke_transaction(
+    state: web::Data<Mutex<state::State>>,
+    body: web::Bytes,
+) -> impl Responder {
+    let (mut input, request) = match parse_state_request::<InvokeInput>(&body) {
+        Ok(input) => input,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    let mut state = state.lock().unwrap();
+    state
+        .block
+        .fill_config(&mut input.config, &request["config"]);
+    let nonce = match input.nonce {
+        Some(nonce) => nonce,
+        None => match state.get_nonce_at(input.sender_address) {
+            Ok(nonce) => nonce,
+            Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        },
+    };
+    let mut transaction = rpc::types::BroadcastedInvokeTransaction {
+        sender_address: input.sender_address,
+        calldata: rpc::invoke::multicall_calldata(&input.calls),
+        signature: input.signature,
+        nonce,
+        version: Felt::ONE,
+        max_fee: input.max_fee,
+        ..Default::default()
+    };
+    if let Some(private_key) = input.private_key {
+        let chain_id = input.config.execution_context.chain_id;
+        let signature = rpc::transaction_hash::invoke_transaction_hash(&transaction, chain_id)
+            .and_then(|hash| rpc::transaction_hash::sign(private_key, hash));
+        match signature {
+            Ok(signature) => transaction.signature = signature,
+            Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+        }
+    }
+    match rpc::invoke::execute_invoke(&mut state, &input.config, &transaction) {
+        // The receipt, with the state diff, the full resources report and the fee estimate of the
+        // transaction.
+        Ok(execution) => {
+            let mut response = serde_json::to_value(execution.receipt()).unwrap_or_default();
+            response["state_diff"] = serde_json::json!(execution.state_diff);
+            response["data_availability_fee"] =
+                serde_json::json!(execution.data_availability_fee(&input.config));
+            response["resources"] = serde_json::json!(execution.resources);
+            response["fee_estimate"] = serde_json::json!(execution.fee_estimate(&input.config));
+            HttpResponse::Ok().json(response)
+        }
+        Err(e) => HttpResponse::InternalServerError().body(e),
+    }
+}
+
+// This function will handle POST requests to "/transaction/hash"
+async fn transaction_hash(input: web::Json<TransactionHashInput>) -> impl Responder {
+    let chain_id = input
+        .chain_id
+        .unwrap_or(trace::config::ExecutionContext::default().chain_id);
+    let transaction_hash =
+        match rpc::transaction_hash::transaction_hash(&input.transaction, chain_id) {
+            Ok(transaction_hash) => transaction_hash,
+            Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+        };
+    let Some(private_key) = input.private_key else {
+        return HttpResponse::Ok()
+            .json(serde_json::json!({ "transaction_hash": transaction_hash }));
+    };
+    match rpc::transaction_hash::sign(private_key, transaction_hash) {
+        Ok(signature) => HttpResponse::Ok().json(serde_json::json!({
+            "transaction_hash": transaction_hash,
+            "signature": signature,
+            "public_key": rpc::transaction_hash::public_key(private_key),
+        })),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+// This function will handle POST requests to "/contract_address"
+async fn contract_address(input: web::Json<ContractAddressInput>) -> impl Responder {
+    let contract_address = rpc::transaction_hash::calculate_contract_address(
+        input.salt,
+        input.class_hash,
+        &input.constructor_calldata,
+        input.deployer_address,
+    );
+    HttpResponse::Ok().json(serde_json::json!({ "contract_address": contract_address }))
+}
+
+// This function will handle POST requests to "/call"
+async fn call_contract(state: web::Data<Mutex<state::State>>, body: web::Bytes) -> impl Responder {
+    let (mut input, request) = match parse_state_request::<CallInput>(&body) {
+        Ok(input) => input,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    let mut state = state.lock().unwrap();
+    state
+        .block
+        .fill_config(&mut input.config, &request["config"]);
+    let call = match parse_call(&input) {
+        Ok(call) => call,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+
+    let result = trace::execution::execute_call(&mut state, &mut 0, &call, &input.config);
+    match result {
+        Ok(call_info) => HttpResponse::Ok().json(call_info),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    HttpServer::new(|| {
+    let mut genesis = state::State::default();
+    // Starts from a state dump, or predeploys the accounts and fee tokens of a genesis config file,
+    // if one is given.
+    if let Ok(path) = std::env::var("STATE_FILE") {
+        genesis = state::dump::load(&std::fs::read(path)?)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+    } else if let Ok(path) = std::env::var("GENESIS_CONFIG") {
+        let accounts = state::genesis::GenesisConfig::load(std::path::Path::new(&path))
+            .and_then(|config| config.predeploy(&mut genesis))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+        for account in accounts {
+            println!(
+                "Account {} (private key {})",
+                account.address.to_hex_string(),
+                account.private_key.to_hex_string()
+            );
+        }
+    }
+    let state = web::Data::new(Mutex::new(genesis));
+    let snapshots = web::Data::new(Mutex::new(state::snapshot::Snapshots::default()));
+
+    HttpServer::new(move || {
         let cors = Cors::default()
             .allowed_origin("http://localhost:3000")
             .allowed_methods(vec!["GET", "POST"])
@@ -79,9 +609,33 @@ async fn main() -> std::io::Result<()> {
 
         App::new()
             .wrap(cors)
+            .app_data(state.clone())
+            .app_data(snapshots.clone())
+            // Raw bodies are state dumps, which hold whole classes.
+            .app_data(web::PayloadConfig::new(MAX_STATE_DUMP_SIZE))
             .route("/compile", web::post().to(compile_code))
             .route("/compile_contract", web::post().to(compile_contract_code))
+            .route("/upgrade_check", web::post().to(upgrade_check))
+            .route("/class_diff", web::post().to(class_diff))
             .route("/trace_error", web::post().to(trace_error))
+            .route("/proof_inputs", web::post().to(proof_inputs))
+            .route("/run", web::post().to(run_program))
+            .route("/test", web::post().to(run_tests))
+            .route("/state/declare", web::post().to(declare_class))
+            .route("/state/deploy", web::post().to(deploy_contract))
+            .route("/state/genesis", web::post().to(genesis_state))
+            .route("/state/fork", web::post().to(fork_state))
+            .route("/state/block", web::post().to(set_block))
+            .route("/state/snapshot", web::post().to(snapshot_state))
+            .route("/state/revert", web::post().to(revert_state))
+            .route("/state/dump", web::post().to(dump_state))
+            .route("/state/load", web::post().to(load_state))
+            .route("/state/invoke", web::post().to(invoke_transaction))
+            .route("/transaction/hash", web::post().to(transaction_hash))
+            .route("/contract_address", web::post().to(contract_address))
+            .route("/call", web::post().to(call_contract))
+            .route("/rpc", web::post().to(json_rpc))
+            .route("/replay", web::post().to(replay_transaction))
     })
     .bind("127.0.0.1:8080")?
     .run()
@@ -0,0 +1,113 @@
+use starknet_types_core::felt::Felt as Felt252;
+
+use super::invoke::{
+    charge_fee, check_nonce, execute_or_reject, fee_token, tx_info_config, validate,
+    TransactionExecution, TransactionFields, TransactionType,
+};
+use super::transaction_hash::declare_transaction_hash;
+use super::types::BroadcastedDeclareTransaction;
+use crate::state::diff::state_diff;
+use crate::state::fork::compile_class;
+use crate::state::State;
+use crate::trace::config::RunConfig;
+use crate::trace::resources::{StateChangesCount, TransactionResources};
+
+/// Executes a declare transaction like the sequencer: checks the nonce, validates the transaction
+/// with the `__validate_declare__` entrypoint of the account, compiles and declares the class and
+/// charges the fee.
+///
+/// Declare transactions cannot be reverted: any failure rejects the transaction and leaves the
+/// state unchanged.
+pub fn execute_declare(
+    state: &mut State,
+    config: &RunConfig,
+    transaction: &BroadcastedDeclareTransaction,
+) -> Result<TransactionExecution, String> {
+    execute_or_reject(state, |state, initial_state| {
+        run_declare(state, initial_state, config, transaction)
+    })
+}
+
+fn run_declare(
+    state: &mut State,
+    initial_state: &State,
+    config: &RunConfig,
+    transaction: &BroadcastedDeclareTransaction,
+) -> Result<TransactionExecution, String> {
+    let config = tx_info_config(
+        config,
+        TransactionFields {
+            transaction_hash: declare_transaction_hash(
+                transaction,
+                config.execution_context.chain_id,
+            )
+            .map_err(|e| e.to_string())?,
+            account_address: transaction.sender_address,
+            nonce: transaction.nonce,
+            version: transaction.version,
+            signature: &transaction.signature,
+            max_fee: transaction.max_fee,
+            resources: &transaction.resources,
+            account_deployment_data: &transaction.account_deployment_data,
+        },
+    );
+    let sender_address = transaction.sender_address;
+    let nonce = check_nonce(state, sender_address, transaction.nonce)?;
+    let class = serde_json::to_value(&transaction.contract_class)
+        .map_err(anyhow::Error::from)
+        .and_then(compile_class)
+        .map_err(|e| format!("Failed to compile the declared class: {}", e))?;
+    if class.compiled_class_hash != transaction.compiled_class_hash {
+        return Err(format!(
+            "Mismatching compiled class hash: expected {}, got {}.",
+            class.compiled_class_hash.to_hex_string(),
+            transaction.compiled_class_hash.to_hex_string()
+        ));
+    }
+    if state.get_class(&class.class_hash).is_ok() {
+        return Err(format!(
+            "Class {} is already declared.",
+            class.class_hash.to_hex_string()
+        ));
+    }
+    let mut n_emitted_events = 0;
+
+    let validate_call_info = validate(
+        state,
+        &mut n_emitted_events,
+        "__validate_declare__",
+        vec![class.class_hash],
+        &config,
+    )?;
+    state.declare_class(class);
+    state.nonces.insert(sender_address, nonce + Felt252::ONE);
+
+    let (fee_unit, fee_token_address) = fee_token(&config);
+    let resources = TransactionResources::new(
+        TransactionType::Declare,
+        &validate_call_info.iter().collect::<Vec<_>>(),
+        &[],
+        0,
+        transaction.signature.len(),
+        StateChangesCount::new(
+            &state_diff(initial_state, state).map_err(|e| e.to_string())?,
+            Some((fee_token_address, sender_address)),
+        ),
+        &config,
+    );
+    let (fee_transfer_call_info, actual_fee) =
+        charge_fee(state, &config, &mut n_emitted_events, &resources)?;
+
+    Ok(TransactionExecution {
+        transaction_type: TransactionType::Declare,
+        transaction_hash: config.execution_context.transaction_hash,
+        validate_call_info,
+        execute_call_info: None,
+        fee_transfer_call_info,
+        revert_reason: None,
+        state_diff: state_diff(initial_state, state).map_err(|e| e.to_string())?,
+        resources,
+        actual_fee,
+        fee_unit,
+    })
+}
@@ -0,0 +1,133 @@
+use starknet_types_core::felt::Felt as Felt252;
+
+use super::invoke::{
+    charge_fee, check_nonce, execute_or_reject, fee_token, tx_info_config, validate,
+    TransactionExecution, TransactionFields, TransactionType,
+};
+use super::revert_reason;
+use super::transaction_hash::{calculate_contract_address, deploy_account_transaction_hash};
+use super::types::BroadcastedDeployAccountTransaction;
+use crate::state::diff::state_diff;
+use crate::state::State;
+use crate::trace::abi_decoder::selector_from_name;
+use crate::trace::cairo_runner::ExecutionStatus;
+use crate::trace::config::RunConfig;
+use crate::trace::execution::{execute_call, CallEntryPoint, CallType, EntryPointType};
+use crate::trace::resources::{StateChangesCount, TransactionResources};
+
+/// Executes a deploy account transaction like the sequencer: deploys the account and runs its
+/// constructor, validates the transaction with the `__validate_deploy__` entrypoint of the new
+/// account and charges the fee.
+///
+/// Deploy account transactions cannot be reverted: any failure, including a failing constructor,
+/// rejects the transaction and leaves the state unchanged.
+pub fn execute_deploy_account(
+    state: &mut State,
+    config: &RunConfig,
+    transaction: &BroadcastedDeployAccountTransaction,
+) -> Result<TransactionExecution, String> {
+    execute_or_reject(state, |state, initial_state| {
+        run_deploy_account(state, initial_state, config, transaction)
+    })
+}
+
+fn run_deploy_account(
+    state: &mut State,
+    initial_state: &State,
+    config: &RunConfig,
+    transaction: &BroadcastedDeployAccountTransaction,
+) -> Result<TransactionExecution, String> {
+    let contract_address = calculate_contract_address(
+        transaction.contract_address_salt,
+        transaction.class_hash,
+        &transaction.constructor_calldata,
+        Felt252::ZERO,
+    );
+    let config = tx_info_config(
+        config,
+        TransactionFields {
+            transaction_hash: deploy_account_transaction_hash(
+                transaction,
+                config.execution_context.chain_id,
+            )
+            .map_err(|e| e.to_string())?,
+            account_address: contract_address,
+            nonce: transaction.nonce,
+            version: transaction.version,
+            signature: &transaction.signature,
+            max_fee: transaction.max_fee,
+            resources: &transaction.resources,
+            account_deployment_data: &[],
+        },
+    );
+    let nonce = check_nonce(state, contract_address, transaction.nonce)?;
+    let mut n_emitted_events = 0;
+
+    state
+        .deploy_contract(contract_address, transaction.class_hash)
+        .map_err(|e| e.to_string())?;
+    let constructor_call = CallEntryPoint {
+        call_type: CallType::Call,
+        caller_address: Felt252::ZERO,
+        contract_address,
+        class_hash: Some(transaction.class_hash),
+        entry_point_type: EntryPointType::Constructor,
+        entry_point_selector: selector_from_name("constructor"),
+        calldata: transaction.constructor_calldata.clone(),
+        initial_gas: config.initial_gas,
+    };
+    let constructor_call_info =
+        execute_call(state, &mut n_emitted_events, &constructor_call, &config)
+            .map_err(|e| format!("Deployment failed: {}", e))?;
+    if constructor_call_info.status != ExecutionStatus::Succeeded {
+        return Err(format!(
+            "Deployment failed: {}.",
+            revert_reason(&constructor_call_info)
+        ));
+    }
+
+    // The account validates its own deployment, with the class hash, the salt and the
+    // constructor calldata.
+    let mut validate_calldata = vec![transaction.class_hash, transaction.contract_address_salt];
+    validate_calldata.extend(&transaction.constructor_calldata);
+    let validate_call_info = validate(
+        state,
+        &mut n_emitted_events,
+        "__validate_deploy__",
+        validate_calldata,
+        &config,
+    )?;
+    state.nonces.insert(contract_address, nonce + Felt252::ONE);
+
+    let (fee_unit, fee_token_address) = fee_token(&config);
+    let resources = TransactionResources::new(
+        TransactionType::DeployAccount,
+        &[Some(&constructor_call_info), validate_call_info.as_ref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>(),
+        &[],
+        transaction.constructor_calldata.len(),
+        transaction.signature.len(),
+        StateChangesCount::new(
+            &state_diff(initial_state, state).map_err(|e| e.to_string())?,
+            Some((fee_token_address, contract_address)),
+        ),
+        &config,
+    );
+    let (fee_transfer_call_info, actual_fee) =
+        charge_fee(state, &config, &mut n_emitted_events, &resources)?;
+
+    Ok(TransactionExecution {
+        transaction_type: TransactionType::DeployAccount,
+        transaction_hash: config.execution_context.transaction_hash,
+        validate_call_info,
+        execute_call_info: Some(constructor_call_info),
+        fee_transfer_call_info,
+        revert_reason: None,
+        state_diff: state_diff(initial_state, state).map_err(|e| e.to_string())?,
+        resources,
+        actual_fee,
+        fee_unit,
+    })
+}
@@ -0,0 +1,735 @@
+use starknet_types_core::felt::Felt as Felt252;
+
+use super::revert_reason;
+use super::transaction_hash::{invoke_transaction_hash, is_v3};
+use super::types::{
+    BroadcastedInvokeTransaction, Event, FeeEstimate, FeePayment, FunctionCall, ResourceFields,
+    StateDiff, TransactionExecutionResources, TransactionExecutionStatus, TransactionReceipt,
+};
+use crate::state::diff::state_diff;
+use crate::state::State;
+use crate::trace::abi_decoder::selector_from_name;
+use crate::trace::cairo_runner::ExecutionStatus;
+use crate::trace::config::{ExecutionMode, ResourceBounds, RunConfig, VALIDATE_MAX_STEPS};
+use crate::trace::execution::{execute_call, CallEntryPoint, CallInfo, CallType, EntryPointType};
+use crate::trace::resources::{StateChangesCount, TransactionResources};
+
+/// The return data of a successful `__validate__`, `'VALID'` as a short string.
+const VALID: &[u8] = b"VALID";
+
+/// The revert reason of a transaction whose execution costs more than its `max_fee`.
+const INSUFFICIENT_MAX_FEE_ERROR: &str = "Insufficient max fee";
+
+/// The type of an account transaction, which sets the shape of its trace and the resources the
+/// OS spends on it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionType {
+    Invoke,
+    Declare,
+    DeployAccount,
+}
+
+/// An executed account transaction.
+pub struct TransactionExecution {
+    pub transaction_type: TransactionType,
+    pub transaction_hash: Felt252,
+    /// The validation of the transaction, `None` if it was skipped.
+    pub validate_call_info: Option<CallInfo>,
+    /// The `__execute__` call of an invoke transaction or the constructor call of a deploy
+    /// account transaction, `None` for declare transactions.
+    pub execute_call_info: Option<CallInfo>,
+    /// The transfer of the fee to the sequencer, `None` if the transaction has no fee bound.
+    pub fee_transfer_call_info: Option<CallInfo>,
+    /// Why the execution was reverted, `None` if it succeeded.
+    pub revert_reason: Option<String>,
+    /// The changes of the transaction to the state, including its nonce and fee.
+    pub state_diff: StateDiff,
+    /// The resources of the validation and the execution of the transaction.
+    pub resources: TransactionResources,
+    pub actual_fee: u128,
+    /// `WEI` for fees paid in ETH, `FRI` for fees paid in STRK.
+    pub fee_unit: &'static str,
+}
+
+impl TransactionExecution {
+    pub fn execution_status(&self) -> TransactionExecutionStatus {
+        match self.revert_reason {
+            Some(_) => TransactionExecutionStatus::Reverted,
+            None => TransactionExecutionStatus::Succeeded,
+        }
+    }
+
+    /// The part of the fee paying for the publication of the state diff.
+    pub fn data_availability_fee(&self, config: &RunConfig) -> FeePayment {
+        FeePayment {
+            amount: self
+                .resources
+                .data_availability_fee(config, self.fee_unit)
+                .into(),
+            unit: self.fee_unit,
+        }
+    }
+
+    pub fn fee_estimate(&self, config: &RunConfig) -> FeeEstimate {
+        let (gas_price, data_gas_price, overall_fee) = self.resources.fee(config, self.fee_unit);
+        FeeEstimate {
+            gas_consumed: self.resources.gas.l1_gas.into(),
+            gas_price: gas_price.into(),
+            data_gas_consumed: self.resources.gas.l1_data_gas.into(),
+            data_gas_price: data_gas_price.into(),
+            overall_fee: overall_fee.into(),
+            unit: self.fee_unit,
+        }
+    }
+
+    /// The events of the transaction in emission order, without those of a reverted execution.
+    pub fn events(&self) -> Vec<Event> {
+        // The constructor of a deployed account runs before its validation.
+        let mut call_infos = vec![];
+        if self.revert_reason.is_none() {
+            call_infos.extend(&self.execute_call_info);
+        }
+        call_infos.extend(&self.validate_call_info);
+        call_infos.extend(&self.fee_transfer_call_info);
+
+        let mut events = vec![];
+        for call_info in call_infos {
+            collect_events(call_info, &mut events);
+        }
+        events.sort_by_key(|(order, _)| *order);
+        events.into_iter().map(|(_, event)| event).collect()
+    }
+
+    pub fn receipt(&self) -> TransactionReceipt {
+        TransactionReceipt {
+            transaction_hash: self.transaction_hash,
+            actual_fee: FeePayment {
+                amount: self.actual_fee.into(),
+                unit: self.fee_unit,
+            },
+            execution_status: self.execution_status(),
+            revert_reason: self.revert_reason.clone(),
+            finality_status: "ACCEPTED_ON_L2",
+            events: self.events(),
+            execution_resources: TransactionExecutionResources::from(&self.resources),
+        }
+    }
+}
+
+fn collect_events(call_info: &CallInfo, events: &mut Vec<(usize, Event)>) {
+    events.extend(
+        call_info
+            .events
+            .iter()
+            .map(|event| (event.order, Event::from(event))),
+    );
+    for inner_call in &call_info.inner_calls {
+        collect_events(inner_call, events);
+    }
+}
+
+/// Encodes calls as the multicall array taken by the `__execute__` of Cairo 1 accounts: the
+/// number of calls, then the address, selector and length-prefixed calldata of each call.
+pub fn multicall_calldata(calls: &[FunctionCall]) -> Vec<Felt252> {
+    let mut calldata = vec![Felt252::from(calls.len())];
+    for call in calls {
+        calldata.extend([
+            call.contract_address,
+            call.entry_point_selector,
+            Felt252::from(call.calldata.len()),
+        ]);
+        calldata.extend(&call.calldata);
+    }
+    calldata
+}
+
+/// Executes an invoke transaction like the sequencer: checks the nonce, validates the transaction
+/// with the `__validate__` entrypoint of the account, runs its `__execute__` entrypoint and
+/// charges the fee.
+///
+/// A transaction failing before its execution is rejected and leaves the state unchanged. A
+/// transaction whose execution fails is reverted: only its nonce and fee are committed.
+pub fn execute_invoke(
+    state: &mut State,
+    config: &RunConfig,
+    transaction: &BroadcastedInvokeTransaction,
+) -> Result<TransactionExecution, String> {
+    execute_or_reject(state, |state, initial_state| {
+        run_invoke(state, initial_state, config, transaction)
+    })
+}
+
+/// Runs a transaction against the state, given with its initial value, and restores the state if
+/// the transaction is rejected.
+pub fn execute_or_reject(
+    state: &mut State,
+    run: impl FnOnce(&mut State, &State) -> Result<TransactionExecution, String>,
+) -> Result<TransactionExecution, String> {
+    let snapshot = state.clone();
+    let result = run(state, &snapshot);
+    if result.is_err() {
+        *state = snapshot;
+    }
+    result
+}
+
+fn run_invoke(
+    state: &mut State,
+    initial_state: &State,
+    config: &RunConfig,
+    transaction: &BroadcastedInvokeTransaction,
+) -> Result<TransactionExecution, String> {
+    let config = transaction_config(config, transaction)?;
+    let sender_address = transaction.sender_address;
+    let nonce = check_nonce(state, sender_address, transaction.nonce)?;
+    let mut n_emitted_events = 0;
+
+    let validate_call_info = validate(
+        state,
+        &mut n_emitted_events,
+        "__validate__",
+        transaction.calldata.clone(),
+        &config,
+    )?;
+    state.nonces.insert(sender_address, nonce + Felt252::ONE);
+
+    let pre_execution_state = state.clone();
+    let execute_call_info = execute_call(
+        state,
+        &mut n_emitted_events,
+        &self_call(
+            sender_address,
+            "__execute__",
+            transaction.calldata.clone(),
+            &config,
+        ),
+        &config,
+    )
+    .map_err(|e| e.to_string())?;
+    let mut revert_reason = match execute_call_info.status {
+        ExecutionStatus::Succeeded => None,
+        _ => Some(revert_reason(&execute_call_info)),
+    };
+
+    let (fee_unit, fee_token_address) = fee_token(&config);
+    // The resources of a reverted execution are charged, but not its events, messages and state
+    // changes.
+    let transaction_resources = |state: &State, reverted: bool| -> Result<_, String> {
+        let mut call_infos: Vec<&CallInfo> = validate_call_info.iter().collect();
+        let mut reverted_call_infos = vec![];
+        if reverted {
+            reverted_call_infos.push(&execute_call_info);
+        } else {
+            call_infos.push(&execute_call_info);
+        }
+        let state_diff = state_diff(initial_state, state).map_err(|e| e.to_string())?;
+        Ok(TransactionResources::new(
+            TransactionType::Invoke,
+            &call_infos,
+            &reverted_call_infos,
+            transaction.calldata.len(),
+            transaction.signature.len(),
+            StateChangesCount::new(&state_diff, Some((fee_token_address, sender_address))),
+            &config,
+        ))
+    };
+    let mut resources = transaction_resources(&*state, revert_reason.is_some())?;
+    let (_, _, mut actual_fee) = resources.fee(&config, fee_unit);
+
+    // Transactions without a fee bound are not charged.
+    let fee_bound = config.execution_context.fee_bound;
+    let fee_transfer_call_info = if fee_bound == 0 || config.skip_fee_charge {
+        None
+    } else {
+        if actual_fee > fee_bound {
+            // The execution is reverted, and the whole bound is charged.
+            if revert_reason.is_none() {
+                *state = pre_execution_state;
+                revert_reason = Some(INSUFFICIENT_MAX_FEE_ERROR.to_string());
+                resources = transaction_resources(&*state, true)?;
+            }
+            actual_fee = fee_bound;
+        }
+        Some(transfer_fee(
+            state,
+            &config,
+            &mut n_emitted_events,
+            fee_token_address,
+            actual_fee,
+        )?)
+    };
+
+    Ok(TransactionExecution {
+        transaction_type: TransactionType::Invoke,
+        transaction_hash: config.execution_context.transaction_hash,
+        validate_call_info,
+        execute_call_info: Some(execute_call_info),
+        fee_transfer_call_info,
+        revert_reason,
+        state_diff: state_diff(initial_state, state).map_err(|e| e.to_string())?,
+        resources,
+        actual_fee,
+        fee_unit,
+    })
+}
+
+/// A call of an account to one of its own entrypoints.
+fn self_call(
+    contract_address: Felt252,
+    entry_point: &str,
+    calldata: Vec<Felt252>,
+    config: &RunConfig,
+) -> CallEntryPoint {
+    CallEntryPoint {
+        call_type: CallType::Call,
+        caller_address: Felt252::ZERO,
+        contract_address,
+        class_hash: None,
+        entry_point_type: EntryPointType::External,
+        entry_point_selector: selector_from_name(entry_point),
+        calldata,
+        initial_gas: config.initial_gas,
+    }
+}
+
+/// Checks that the nonce of a transaction is the current nonce of its account, returning it.
+pub fn check_nonce(
+    state: &State,
+    account_address: Felt252,
+    transaction_nonce: Felt252,
+) -> Result<Felt252, String> {
+    let nonce = state
+        .get_nonce_at(account_address)
+        .map_err(|e| e.to_string())?;
+    if nonce != transaction_nonce {
+        return Err(format!(
+            "Invalid transaction nonce of account {}: expected {}, got {}.",
+            account_address.to_hex_string(),
+            nonce.to_hex_string(),
+            transaction_nonce.to_hex_string()
+        ));
+    }
+    Ok(nonce)
+}
+
+/// Runs a validation entrypoint of the account of the transaction, with the validation step limit,
+/// and checks that it returns `'VALID'`. Returns `None` if the config skips validation.
+pub fn validate(
+    state: &mut State,
+    n_emitted_events: &mut usize,
+    entry_point: &str,
+    calldata: Vec<Felt252>,
+    config: &RunConfig,
+) -> Result<Option<CallInfo>, String> {
+    if config.skip_validate {
+        return Ok(None);
+    }
+    let validate_config = RunConfig {
+        execution_mode: ExecutionMode::Validate,
+        max_steps: Some(config.max_steps.map_or(VALIDATE_MAX_STEPS, |max_steps| {
+            max_steps.min(VALIDATE_MAX_STEPS)
+        })),
+        ..config.clone()
+    };
+    let call = self_call(
+        config.execution_context.account_contract_address,
+        entry_point,
+        calldata,
+        &validate_config,
+    );
+    let call_info = execute_call(state, n_emitted_events, &call, &validate_config)
+        .map_err(|e| format!("Validation failed: {}", e))?;
+    if call_info.status != ExecutionStatus::Succeeded {
+        return Err(format!("Validation failed: {}.", revert_reason(&call_info)));
+    }
+    if call_info.retdata != [Felt252::from_bytes_be_slice(VALID)] {
+        return Err(format!(
+            "Invalid `{}` return data: expected 'VALID'.",
+            entry_point
+        ));
+    }
+    Ok(Some(call_info))
+}
+
+/// The unit of the fee of the transaction of `config` and the token it is paid with: v3
+/// transactions pay their fee in STRK.
+pub fn fee_token(config: &RunConfig) -> (&'static str, Felt252) {
+    let context = &config.execution_context;
+    if is_v3(context.version) {
+        ("FRI", context.strk_fee_token_address)
+    } else {
+        ("WEI", context.eth_fee_token_address)
+    }
+}
+
+/// Charges the fee of a transaction that cannot be reverted, like declare and deploy account
+/// transactions: a fee above the bound of the transaction rejects it.
+///
+/// Returns the fee transfer, `None` if the transaction has no fee bound or the config skips fee
+/// charging, and the fee.
+pub fn charge_fee(
+    state: &mut State,
+    config: &RunConfig,
+    n_emitted_events: &mut usize,
+    resources: &TransactionResources,
+) -> Result<(Option<CallInfo>, u128), String> {
+    let (fee_unit, fee_token_address) = fee_token(config);
+    let (_, _, actual_fee) = resources.fee(config, fee_unit);
+    let fee_bound = config.execution_context.fee_bound;
+    if fee_bound == 0 || config.skip_fee_charge {
+        return Ok((None, actual_fee));
+    }
+    if actual_fee > fee_bound {
+        return Err(format!(
+            "{}: the fee is {}, the bound is {}.",
+            INSUFFICIENT_MAX_FEE_ERROR, actual_fee, fee_bound
+        ));
+    }
+    let fee_transfer_call_info = transfer_fee(
+        state,
+        config,
+        n_emitted_events,
+        fee_token_address,
+        actual_fee,
+    )?;
+    Ok((Some(fee_transfer_call_info), actual_fee))
+}
+
+/// Transfers the fee from the account to the sequencer with the `transfer` entrypoint of the fee
+/// token.
+fn transfer_fee(
+    state: &mut State,
+    config: &RunConfig,
+    n_emitted_events: &mut usize,
+    fee_token_address: Felt252,
+    fee: u128,
+) -> Result<CallInfo, String> {
+    let context = &config.execution_context;
+    let call = CallEntryPoint {
+        call_type: CallType::Call,
+        caller_address: context.account_contract_address,
+        contract_address: fee_token_address,
+        class_hash: None,
+        entry_point_type: EntryPointType::External,
+        entry_point_selector: selector_from_name("transfer"),
+        // The recipient and the amount as a u256.
+        calldata: vec![context.sequencer_address, fee.into(), Felt252::ZERO],
+        initial_gas: config.initial_gas,
+    };
+    let call_info = execute_call(state, n_emitted_events, &call, config)
+        .map_err(|e| format!("Fee transfer failed: {}", e))?;
+    if call_info.status != ExecutionStatus::Succeeded {
+        return Err(format!(
+            "Fee transfer failed: {}.",
+            revert_reason(&call_info)
+        ));
+    }
+    Ok(call_info)
+}
+
+/// The fields of an account transaction returned by `get_tx_info`.
+pub struct TransactionFields<'a> {
+    pub transaction_hash: Felt252,
+    /// The account sending the transaction, or deployed by it.
+    pub account_address: Felt252,
+    pub nonce: Felt252,
+    pub version: Felt252,
+    pub signature: &'a [Felt252],
+    /// The fee bound of transactions before v3.
+    pub max_fee: Option<Felt252>,
+    pub resources: &'a ResourceFields,
+    pub account_deployment_data: &'a [Felt252],
+}
+
+/// The run config of an invoke transaction.
+fn transaction_config(
+    config: &RunConfig,
+    transaction: &BroadcastedInvokeTransaction,
+) -> Result<RunConfig, String> {
+    Ok(tx_info_config(
+        config,
+        TransactionFields {
+            transaction_hash: invoke_transaction_hash(
+                transaction,
+                config.execution_context.chain_id,
+            )
+            .map_err(|e| e.to_string())?,
+            account_address: transaction.sender_address,
+            nonce: transaction.nonce,
+            version: transaction.version,
+            signature: &transaction.signature,
+            max_fee: transaction.max_fee,
+            resources: &transaction.resources,
+            account_deployment_data: &transaction.account_deployment_data,
+        },
+    ))
+}
+
+/// The run config of a transaction: its fields are returned by `get_tx_info`.
+pub fn tx_info_config(config: &RunConfig, transaction: TransactionFields) -> RunConfig {
+    let mut config = config.clone();
+    let context = &mut config.execution_context;
+    context.transaction_hash = transaction.transaction_hash;
+    context.account_contract_address = transaction.account_address;
+    context.nonce = transaction.nonce;
+    context.version = transaction.version;
+    context.signature = transaction.signature.to_vec();
+    if let Some(max_fee) = transaction.max_fee.filter(|_| !is_v3(transaction.version)) {
+        context.max_fee = saturating_u128(max_fee);
+        context.fee_bound = context.max_fee;
+    }
+    if is_v3(transaction.version) {
+        let resources = transaction.resources;
+        let bounds = &resources.resource_bounds;
+        // v3 transactions have no `max_fee`: their resource bounds cap the fee.
+        context.max_fee = 0;
+        context.fee_bound = saturating_u128(bounds.max_fee());
+        context.resource_bounds = [
+            ("L1_GAS", Some(&bounds.l1_gas)),
+            ("L2_GAS", Some(&bounds.l2_gas)),
+            ("L1_DATA", bounds.l1_data_gas.as_ref()),
+        ]
+        .into_iter()
+        .filter_map(|(resource, bounds)| {
+            bounds.map(|bounds| ResourceBounds {
+                resource: resource.to_string(),
+                max_amount: saturating_u128(bounds.max_amount)
+                    .try_into()
+                    .unwrap_or(u64::MAX),
+                max_price_per_unit: saturating_u128(bounds.max_price_per_unit),
+            })
+        })
+        .collect();
+        context.tip = saturating_u128(resources.tip);
+        context.paymaster_data = resources.paymaster_data.clone();
+        context.nonce_data_availability_mode = resources.nonce_data_availability_mode.value();
+        context.fee_data_availability_mode = resources.fee_data_availability_mode.value();
+        context.account_deployment_data = transaction.account_deployment_data.to_vec();
+    }
+    config
+}
+
+fn saturating_u128(value: Felt252) -> u128 {
+    value.to_biguint().try_into().unwrap_or(u128::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::types::{
+        self, DataAvailabilityMode, NonceUpdate, ResourceBoundsMapping, ResourceFields,
+    };
+    use crate::trace::resources::fee_balance_key;
+
+    const ACCOUNT: &str = r#"#[starknet::contract]
+    mod Account {
+        use starknet::SyscallResultTrait;
+        use starknet::account::Call;
+        use starknet::syscalls::call_contract_syscall;
+
+        #[storage]
+        struct Storage {}
+
+        #[external(v0)]
+        fn __validate__(self: @ContractState, calls: Array<Call>) -> felt252 {
+            'VALID'
+        }
+
+        #[external(v0)]
+        fn __execute__(ref self: ContractState, mut calls: Array<Call>) -> Array<Span<felt252>> {
+            let mut results = array![];
+            loop {
+                match calls.pop_front() {
+                    Option::Some(call) => {
+                        results.append(
+                            call_contract_syscall(call.to, call.selector, call.calldata)
+                                .unwrap_syscall()
+                        );
+                    },
+                    Option::None => { break; },
+                };
+            };
+            results
+        }
+    }
+    "#;
+
+    const COUNTER: &str = r#"#[starknet::contract]
+    mod Counter {
+        #[storage]
+        struct Storage {
+            counter: felt252,
+        }
+
+        #[external(v0)]
+        fn increase(ref self: ContractState, amount: felt252) {
+            assert(amount != 0, 'Zero amount');
+            self.counter.write(self.counter.read() + amount);
+        }
+    }
+    "#;
+
+    const TOKEN: &str = r#"#[starknet::contract]
+    mod Token {
+        use starknet::{ContractAddress, get_caller_address};
+
+        #[storage]
+        struct Storage {
+            ERC20_balances: LegacyMap<ContractAddress, u256>,
+        }
+
+        #[external(v0)]
+        fn transfer(ref self: ContractState, recipient: ContractAddress, amount: u256) -> bool {
+            let sender = get_caller_address();
+            let balance = self.ERC20_balances.read(sender);
+            assert(balance >= amount, 'Insufficient balance');
+            self.ERC20_balances.write(sender, balance - amount);
+            self.ERC20_balances.write(recipient, self.ERC20_balances.read(recipient) + amount);
+            true
+        }
+    }
+    "#;
+
+    fn deploy(state: &mut State, code: &str, contract_address: Felt252) {
+        state.deploy_code(code, contract_address);
+    }
+
+    fn invoke(nonce: u64, amount: u64) -> BroadcastedInvokeTransaction {
+        BroadcastedInvokeTransaction {
+            sender_address: Felt252::from(0x100),
+            calldata: multicall_calldata(&[FunctionCall {
+                contract_address: Felt252::from(0x200),
+                entry_point_selector: selector_from_name("increase"),
+                calldata: vec![Felt252::from(amount)],
+            }]),
+            signature: vec![],
+            nonce: Felt252::from(nonce),
+            version: Felt252::ONE,
+            max_fee: Some(Felt252::from(10_u128.pow(16))),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_execute_invoke() {
+        let config = RunConfig::default();
+        let account = Felt252::from(0x100);
+        let counter = Felt252::from(0x200);
+        let fee_token = config.execution_context.eth_fee_token_address;
+        let mut state = State::default();
+        deploy(&mut state, ACCOUNT, account);
+        deploy(&mut state, COUNTER, counter);
+        deploy(&mut state, TOKEN, fee_token);
+        let balance_key = fee_balance_key(account);
+        let initial_balance = Felt252::from(10_u128.pow(18));
+        state.set_storage_at(fee_token, balance_key, initial_balance);
+
+        let execution = execute_invoke(&mut state, &config, &invoke(0, 5)).unwrap();
+        assert_eq!(
+            execution.execution_status(),
+            TransactionExecutionStatus::Succeeded
+        );
+        assert!(execution.actual_fee > 0);
+        let resources = &execution.resources;
+        assert_eq!(
+            resources.computation.syscall_counter.get("CallContract"),
+            Some(&1)
+        );
+        // The counter and the fee balance are updated, and the nonce of the account.
+        assert_eq!(resources.state_changes.n_storage_updates, 2);
+        assert_eq!(resources.state_changes.n_modified_contracts, 3);
+        assert_eq!(resources.gas.l1_data_gas, resources.da_gas.l1_data_gas);
+        assert!(resources.gas.l1_data_gas > 0);
+        assert_eq!(
+            execution.state_diff.nonces,
+            vec![NonceUpdate {
+                contract_address: account,
+                nonce: Felt252::ONE,
+            }]
+        );
+        // The counter, and the fee token balances of the account and the sequencer.
+        assert_eq!(execution.state_diff.storage_diffs.len(), 2);
+        assert_eq!(
+            state
+                .get_storage_at(counter, selector_from_name("counter"))
+                .unwrap(),
+            Felt252::from(5)
+        );
+        assert_eq!(state.get_nonce_at(account).unwrap(), Felt252::ONE);
+        assert_eq!(
+            state.get_storage_at(fee_token, balance_key).unwrap(),
+            initial_balance - Felt252::from(execution.actual_fee)
+        );
+
+        // A replayed nonce is rejected without changing the state.
+        let error = execute_invoke(&mut state, &config, &invoke(0, 5))
+            .err()
+            .unwrap();
+        assert!(error.contains("Invalid transaction nonce"), "{}", error);
+        assert_eq!(state.get_nonce_at(account).unwrap(), Felt252::ONE);
+
+        // A failing execution is reverted, but the nonce is bumped and the fee is charged.
+        let execution = execute_invoke(&mut state, &config, &invoke(1, 0)).unwrap();
+        let receipt = execution.receipt();
+        assert_eq!(
+            receipt.execution_status,
+            TransactionExecutionStatus::Reverted
+        );
+        assert!(receipt.revert_reason.unwrap().contains("Zero amount"));
+        assert_eq!(
+            state
+                .get_storage_at(counter, selector_from_name("counter"))
+                .unwrap(),
+            Felt252::from(5)
+        );
+        assert_eq!(state.get_nonce_at(account).unwrap(), Felt252::TWO);
+        assert!(execution.fee_transfer_call_info.is_some());
+    }
+
+    #[test]
+    fn test_v3_transaction_config() {
+        let transaction = BroadcastedInvokeTransaction {
+            sender_address: Felt252::from(0x100),
+            version: Felt252::THREE,
+            resources: ResourceFields {
+                resource_bounds: ResourceBoundsMapping {
+                    l1_gas: types::ResourceBounds {
+                        max_amount: Felt252::from(100),
+                        max_price_per_unit: Felt252::from(10),
+                    },
+                    ..Default::default()
+                },
+                tip: Felt252::from(7),
+                paymaster_data: vec![Felt252::ONE],
+                fee_data_availability_mode: DataAvailabilityMode::L2,
+                ..Default::default()
+            },
+            account_deployment_data: vec![Felt252::TWO],
+            ..Default::default()
+        };
+        let config = transaction_config(&RunConfig::default(), &transaction).unwrap();
+        let context = config.execution_context;
+        assert_eq!(context.max_fee, 0);
+        assert_eq!(context.fee_bound, 1000);
+        assert_eq!(
+            context.resource_bounds,
+            vec![
+                ResourceBounds {
+                    resource: "L1_GAS".to_string(),
+                    max_amount: 100,
+                    max_price_per_unit: 10,
+                },
+                ResourceBounds {
+                    resource: "L2_GAS".to_string(),
+                    max_amount: 0,
+                    max_price_per_unit: 0,
+                },
+            ]
+        );
+        assert_eq!(context.tip, 7);
+        assert_eq!(context.paymaster_data, vec![Felt252::ONE]);
+        assert_eq!(context.nonce_data_availability_mode, 0);
+        assert_eq!(context.fee_data_availability_mode, 1);
+        assert_eq!(context.account_deployment_data, vec![Felt252::TWO]);
+    }
+}
@@ -0,0 +1,476 @@
+pub mod declare;
+pub mod deploy_account;
+pub mod invoke;
+pub mod replay;
+pub mod transaction_hash;
+pub mod types;
+
+use cairo_lang_starknet_classes::contract_class::ContractClass;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use starknet_types_core::felt::Felt as Felt252;
+
+use crate::state::State;
+use crate::trace::cairo_runner::ExecutionStatus;
+use crate::trace::config::RunConfig;
+use crate::trace::execution::{execute_call, CallEntryPoint, CallInfo, CallType, EntryPointType};
+use crate::trace::short_string::as_short_string;
+use declare::execute_declare;
+use deploy_account::execute_deploy_account;
+use invoke::{execute_invoke, TransactionExecution, TransactionType};
+use types::{
+    BroadcastedTransaction, ExecuteInvocation, FeeEstimate, FunctionCall, FunctionInvocation,
+    RpcError, RpcRequest, RpcResponse, SimulatedTransaction, SimulationFlag,
+    TransactionExecutionResources, TransactionTrace,
+};
+
+// Error codes of the Starknet JSON-RPC specification.
+const CONTRACT_NOT_FOUND: i64 = 20;
+const BLOCK_NOT_FOUND: i64 = 24;
+const CLASS_HASH_NOT_FOUND: i64 = 28;
+const CONTRACT_ERROR: i64 = 40;
+const TRANSACTION_EXECUTION_ERROR: i64 = 41;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+#[derive(Deserialize)]
+struct CallParams {
+    request: FunctionCall,
+    block_id: Value,
+}
+
+#[derive(Deserialize)]
+struct GetStorageAtParams {
+    contract_address: Felt252,
+    key: Felt252,
+    block_id: Value,
+}
+
+#[derive(Deserialize)]
+struct GetNonceParams {
+    block_id: Value,
+    contract_address: Felt252,
+}
+
+#[derive(Deserialize)]
+struct GetClassParams {
+    block_id: Value,
+    class_hash: Felt252,
+}
+
+#[derive(Deserialize)]
+struct EstimateFeeParams {
+    request: Vec<BroadcastedTransaction>,
+    #[serde(default)]
+    simulation_flags: Vec<SimulationFlag>,
+    block_id: Value,
+}
+
+#[derive(Deserialize)]
+struct SimulateTransactionsParams {
+    block_id: Value,
+    transactions: Vec<BroadcastedTransaction>,
+    #[serde(default)]
+    simulation_flags: Vec<SimulationFlag>,
+}
+
+/// Handles a JSON-RPC request or batch of requests against the state.
+pub fn handle(state: &mut State, config: &RunConfig, body: Value) -> Value {
+    match body {
+        Value::Array(requests) => Value::Array(
+            requests
+                .into_iter()
+                .map(|request| handle_request(state, config, request))
+                .collect(),
+        ),
+        request => handle_request(state, config, request),
+    }
+}
+
+fn handle_request(state: &mut State, config: &RunConfig, request: Value) -> Value {
+    let response = match serde_json::from_value::<RpcRequest>(request) {
+        Ok(request) => {
+            let result = dispatch(state, config, &request.method, request.params);
+            RpcResponse {
+                jsonrpc: "2.0",
+                id: request.id,
+                result: result.as_ref().ok().cloned(),
+                error: result.err(),
+            }
+        }
+        Err(e) => RpcResponse {
+            jsonrpc: "2.0",
+            id: Value::Null,
+            result: None,
+            error: Some(RpcError::new(INVALID_REQUEST, e.to_string())),
+        },
+    };
+    serde_json::to_value(response).unwrap_or_default()
+}
+
+fn dispatch(
+    state: &mut State,
+    config: &RunConfig,
+    method: &str,
+    params: Value,
+) -> Result<Value, RpcError> {
+    let result = match method {
+        "starknet_call" => {
+            let params: CallParams = parse_params(params, &["request", "block_id"])?;
+            check_block_id(&params.block_id)?;
+            to_value(call(state, config, params.request)?)
+        }
+        "starknet_getStorageAt" => {
+            let params: GetStorageAtParams =
+                parse_params(params, &["contract_address", "key", "block_id"])?;
+            check_block_id(&params.block_id)?;
+            check_contract_exists(state, &params.contract_address)?;
+            let value = state
+                .get_storage_at(params.contract_address, params.key)
+                .map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))?;
+            to_value(value)
+        }
+        "starknet_getNonce" => {
+            let params: GetNonceParams = parse_params(params, &["block_id", "contract_address"])?;
+            check_block_id(&params.block_id)?;
+            check_contract_exists(state, &params.contract_address)?;
+            let nonce = state
+                .get_nonce_at(params.contract_address)
+                .map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))?;
+            to_value(nonce)
+        }
+        "starknet_getClass" => {
+            let params: GetClassParams = parse_params(params, &["block_id", "class_hash"])?;
+            check_block_id(&params.block_id)?;
+            get_class(state, &params.class_hash)?
+        }
+        "starknet_estimateFee" => {
+            let params: EstimateFeeParams =
+                parse_params(params, &["request", "simulation_flags", "block_id"])?;
+            check_block_id(&params.block_id)?;
+            let config = simulation_config(config, &params.simulation_flags);
+            to_value(estimate_fee(state, &config, params.request)?)
+        }
+        "starknet_simulateTransactions" => {
+            let params: SimulateTransactionsParams =
+                parse_params(params, &["block_id", "transactions", "simulation_flags"])?;
+            check_block_id(&params.block_id)?;
+            let config = simulation_config(config, &params.simulation_flags);
+            to_value(simulate_transactions(state, &config, params.transactions)?)
+        }
+        _ => return Err(RpcError::new(METHOD_NOT_FOUND, "Method not found")),
+    };
+    Ok(result)
+}
+
+/// Parses positional or named parameters, mapping positional ones to `names`.
+fn parse_params<T: DeserializeOwned>(params: Value, names: &[&str]) -> Result<T, RpcError> {
+    let params = match params {
+        Value::Array(values) => Value::Object(
+            names
+                .iter()
+                .map(|name| name.to_string())
+                .zip(values)
+                .collect::<Map<_, _>>(),
+        ),
+        params => params,
+    };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn to_value<T: serde::Serialize>(value: T) -> Value {
+    serde_json::to_value(value).unwrap_or_default()
+}
+
+/// The local state has no history: only the latest and pending blocks exist.
+fn check_block_id(block_id: &Value) -> Result<(), RpcError> {
+    match block_id.as_str() {
+        Some("latest" | "pending") => Ok(()),
+        _ => Err(RpcError::new(BLOCK_NOT_FOUND, "Block not found")),
+    }
+}
+
+fn check_contract_exists(state: &State, contract_address: &Felt252) -> Result<(), RpcError> {
+    state
+        .get_class_hash_at(contract_address)
+        .map(|_| ())
+        .map_err(|_| RpcError::new(CONTRACT_NOT_FOUND, "Contract not found"))
+}
+
+/// Runs a call without committing its state changes, returning its return data.
+fn call(
+    state: &State,
+    config: &RunConfig,
+    request: FunctionCall,
+) -> Result<Vec<Felt252>, RpcError> {
+    check_contract_exists(state, &request.contract_address)?;
+    let call = CallEntryPoint {
+        call_type: CallType::Call,
+        caller_address: Felt252::ZERO,
+        contract_address: request.contract_address,
+        class_hash: None,
+        entry_point_type: EntryPointType::External,
+        entry_point_selector: request.entry_point_selector,
+        calldata: request.calldata,
+        initial_gas: config.initial_gas,
+    };
+    let call_info = execute_call(&mut state.clone(), &mut 0, &call, config)
+        .map_err(|e| contract_error(e.to_string()))?;
+    match call_info.status {
+        ExecutionStatus::Succeeded => Ok(call_info.retdata),
+        _ => Err(contract_error(revert_reason(&call_info))),
+    }
+}
+
+fn contract_error(revert_reason: String) -> RpcError {
+    RpcError::new(CONTRACT_ERROR, "Contract error")
+        .with_data(serde_json::json!({ "revert_error": revert_reason }))
+}
+
+/// Returns a declared class in the format of the specification: its Sierra program, entrypoints
+/// and ABI as a string.
+fn get_class(state: &State, class_hash: &Felt252) -> Result<Value, RpcError> {
+    let class_not_found = || RpcError::new(CLASS_HASH_NOT_FOUND, "Class hash not found");
+    let class = state.get_class(class_hash).map_err(|_| class_not_found())?;
+    let sierra_contract_class = class
+        .sierra_contract_class
+        .as_ref()
+        .ok_or_else(class_not_found)?;
+
+    Ok(rpc_class(sierra_contract_class))
+}
+
+/// Converts a Sierra class to the format of `starknet_getClass`, without debug info and with the
+/// ABI as a string.
+pub fn rpc_class(sierra_contract_class: &ContractClass) -> Value {
+    let mut class = to_value(sierra_contract_class);
+    if let Some(class) = class.as_object_mut() {
+        class.remove("sierra_program_debug_info");
+        let abi = class.get("abi").map(Value::to_string).unwrap_or_default();
+        class.insert("abi".to_string(), Value::String(abi));
+    }
+    class
+}
+
+/// The config of an estimate or a simulation with the given flags.
+fn simulation_config(config: &RunConfig, flags: &[SimulationFlag]) -> RunConfig {
+    RunConfig {
+        skip_validate: flags.contains(&SimulationFlag::SkipValidate),
+        skip_fee_charge: flags.contains(&SimulationFlag::SkipFeeCharge),
+        ..config.clone()
+    }
+}
+
+fn estimate_fee(
+    state: &State,
+    config: &RunConfig,
+    transactions: Vec<BroadcastedTransaction>,
+) -> Result<Vec<FeeEstimate>, RpcError> {
+    let mut state = state.clone();
+    transactions
+        .into_iter()
+        .enumerate()
+        .map(|(index, transaction)| {
+            let execution = execute_transaction(&mut state, config, transaction)
+                .map_err(|e| transaction_execution_error(index, e))?;
+            if let Some(reason) = execution.revert_reason {
+                return Err(transaction_execution_error(index, reason));
+            }
+            Ok(execution.fee_estimate(config))
+        })
+        .collect()
+}
+
+fn transaction_execution_error(transaction_index: usize, execution_error: String) -> RpcError {
+    RpcError::new(TRANSACTION_EXECUTION_ERROR, "Transaction execution error").with_data(
+        serde_json::json!({
+            "transaction_index": transaction_index,
+            "execution_error": execution_error,
+        }),
+    )
+}
+
+/// Executes transactions one after the other on a copy of the state.
+fn simulate_transactions(
+    state: &State,
+    config: &RunConfig,
+    transactions: Vec<BroadcastedTransaction>,
+) -> Result<Vec<SimulatedTransaction>, RpcError> {
+    let mut state = state.clone();
+    transactions
+        .into_iter()
+        .enumerate()
+        .map(|(index, transaction)| {
+            let execution = execute_transaction(&mut state, config, transaction)
+                .map_err(|e| transaction_execution_error(index, e))?;
+            Ok(SimulatedTransaction {
+                transaction_trace: transaction_trace(&execution),
+                fee_estimation: execution.fee_estimate(config),
+                data_availability_fee: execution.data_availability_fee(config),
+            })
+        })
+        .collect()
+}
+
+/// The trace of an executed transaction, in the shape of its type.
+fn transaction_trace(execution: &TransactionExecution) -> TransactionTrace {
+    let validate_invocation = execution
+        .validate_call_info
+        .as_ref()
+        .map(FunctionInvocation::from);
+    let execute_invocation = execution
+        .execute_call_info
+        .as_ref()
+        .map(FunctionInvocation::from);
+    let fee_transfer_invocation = execution
+        .fee_transfer_call_info
+        .as_ref()
+        .map(FunctionInvocation::from);
+    let state_diff = execution.state_diff.clone();
+    let execution_resources = TransactionExecutionResources::from(&execution.resources);
+    match (execution.transaction_type, execute_invocation) {
+        (TransactionType::DeployAccount, Some(constructor_invocation)) => {
+            TransactionTrace::DeployAccount {
+                validate_invocation,
+                constructor_invocation: Box::new(constructor_invocation),
+                fee_transfer_invocation,
+                state_diff,
+                execution_resources,
+            }
+        }
+        (TransactionType::Invoke, Some(execute_invocation)) => TransactionTrace::Invoke {
+            validate_invocation,
+            execute_invocation: match &execution.revert_reason {
+                None => ExecuteInvocation::Succeeded(Box::new(execute_invocation)),
+                Some(revert_reason) => ExecuteInvocation::Reverted {
+                    revert_reason: revert_reason.clone(),
+                },
+            },
+            fee_transfer_invocation,
+            state_diff,
+            execution_resources,
+        },
+        _ => TransactionTrace::Declare {
+            validate_invocation,
+            fee_transfer_invocation,
+            state_diff,
+            execution_resources,
+        },
+    }
+}
+
+/// Executes a transaction against the state, committing its changes unless it is rejected.
+fn execute_transaction(
+    state: &mut State,
+    config: &RunConfig,
+    transaction: BroadcastedTransaction,
+) -> Result<TransactionExecution, String> {
+    match transaction {
+        BroadcastedTransaction::Invoke(transaction) => execute_invoke(state, config, &transaction),
+        BroadcastedTransaction::Declare(transaction) => {
+            execute_declare(state, config, &transaction)
+        }
+        BroadcastedTransaction::DeployAccount(transaction) => {
+            execute_deploy_account(state, config, &transaction)
+        }
+    }
+}
+
+/// Renders the return data of a failed call, short strings as text.
+pub fn revert_reason(call_info: &CallInfo) -> String {
+    if let ExecutionStatus::StepLimitExceeded { .. } = call_info.status {
+        return "Step limit exceeded".to_string();
+    }
+    call_info
+        .retdata
+        .iter()
+        .map(|value| as_short_string(&value.to_biguint()).unwrap_or_else(|| value.to_hex_string()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::genesis::GenesisConfig;
+
+    #[test]
+    fn test_handle_requests() {
+        let mut state = State::default();
+        let config = RunConfig::default();
+        let contract_address = Felt252::from(0x10);
+        state.contracts.insert(contract_address, Felt252::ONE);
+        state.set_storage_at(contract_address, Felt252::TWO, Felt252::from(7));
+
+        let response = handle(
+            &mut state,
+            &config,
+            serde_json::json!([
+                {
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "starknet_getStorageAt",
+                    "params": ["0x10", "0x2", "latest"]
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "id": 2,
+                    "method": "starknet_getStorageAt",
+                    "params": { "contract_address": "0x11", "key": "0x2", "block_id": "latest" }
+                },
+                { "jsonrpc": "2.0", "id": 3, "method": "starknet_getNonce", "params": ["latest", "0x10"] },
+                { "jsonrpc": "2.0", "id": 4, "method": "starknet_getBlockWithTxs", "params": [] }
+            ]),
+        );
+
+        assert_eq!(response[0]["result"], "0x7");
+        assert_eq!(response[1]["error"]["code"], CONTRACT_NOT_FOUND);
+        assert_eq!(response[2]["result"], "0x0");
+        assert_eq!(response[3]["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_estimate_fee_simulation_flags() {
+        let genesis = GenesisConfig {
+            accounts: 1,
+            ..GenesisConfig::default()
+        };
+        let mut state = State::default();
+        let accounts = genesis.predeploy(&mut state).unwrap();
+        let config = RunConfig::default();
+        // An unsigned transaction, rejected by the validation of the account.
+        let estimate_fee = |simulation_flags: Value| {
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "starknet_estimateFee",
+                "params": {
+                    "request": [{
+                        "type": "INVOKE",
+                        "sender_address": accounts[0].address,
+                        "calldata": ["0x0"],
+                        "nonce": "0x0",
+                        "version": "0x1",
+                        "max_fee": "0x0"
+                    }],
+                    "simulation_flags": simulation_flags,
+                    "block_id": "latest"
+                }
+            })
+        };
+
+        let response = handle(&mut state, &config, estimate_fee(serde_json::json!([])));
+        assert_eq!(response["error"]["code"], TRANSACTION_EXECUTION_ERROR);
+
+        let response = handle(
+            &mut state,
+            &config,
+            estimate_fee(serde_json::json!(["SKIP_VALIDATE"])),
+        );
+        assert_eq!(response["result"][0]["unit"], "WEI", "{}", response);
+        assert_ne!(response["result"][0]["overall_fee"], "0x0");
+    }
+}
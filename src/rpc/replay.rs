@@ -0,0 +1,489 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use starknet_types_core::felt::Felt as Felt252;
+
+use super::types::{
+    BroadcastedTransaction, ContractStorageDiff, Event, FunctionInvocation,
+    TransactionExecutionStatus,
+};
+use super::{execute_transaction, to_value};
+use crate::state::fork::compile_class;
+use crate::state::State;
+use crate::trace::cairo_runner::ExecutionStatus;
+use crate::trace::config::{ExecutionContext, RunConfig};
+use crate::trace::execution::CallInfo;
+use crate::trace::short_string::as_short_string;
+
+/// The state a transaction touched, as it was before the transaction.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct StateDump {
+    /// Classes in the format of `starknet_getClass`, by class hash.
+    pub classes: HashMap<Felt252, Value>,
+    /// The class hash of every contract, by address.
+    pub contracts: HashMap<Felt252, Felt252>,
+    /// Storage values by contract address and storage key.
+    pub storage: HashMap<Felt252, HashMap<Felt252, Felt252>>,
+    pub nonces: HashMap<Felt252, Felt252>,
+}
+
+impl StateDump {
+    /// Builds a state from the dump, compiling its classes.
+    pub fn into_state(self) -> Result<State> {
+        let mut state = State::default();
+        for (class_hash, class) in self.classes {
+            let class = compile_class(class).with_context(|| {
+                format!("Failed to compile class {}.", class_hash.to_hex_string())
+            })?;
+            state.classes.insert(class_hash, Arc::new(class));
+        }
+        state.contracts = self.contracts;
+        for (contract_address, storage) in self.storage {
+            for (key, value) in storage {
+                state.set_storage_at(contract_address, key, value);
+            }
+        }
+        state.nonces = self.nonces;
+        Ok(state)
+    }
+}
+
+/// The fields of a `starknet_getTransactionReceipt` result compared by a replay.
+#[derive(Debug, Deserialize)]
+pub struct ExpectedReceipt {
+    pub execution_status: TransactionExecutionStatus,
+    #[serde(default)]
+    pub revert_reason: Option<String>,
+    #[serde(default)]
+    pub events: Vec<Event>,
+}
+
+/// The fields of a `starknet_traceTransaction` result compared by a replay.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ExpectedTrace {
+    pub execute_invocation: Option<Value>,
+    /// The constructor call of a deploy account transaction, compared like `execute_invocation`.
+    pub constructor_invocation: Option<Value>,
+    pub state_diff: Option<ExpectedStateDiff>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ExpectedStateDiff {
+    pub storage_diffs: Vec<ContractStorageDiff>,
+}
+
+/// A transaction to re-execute against the state it ran on, with what it did on the network.
+#[derive(Debug, Deserialize)]
+pub struct Replay {
+    pub transaction: BroadcastedTransaction,
+    pub state: StateDump,
+    /// The block the transaction was included in.
+    #[serde(default)]
+    pub block: ExecutionContext,
+    pub receipt: ExpectedReceipt,
+    #[serde(default)]
+    pub trace: Option<ExpectedTrace>,
+}
+
+/// A field whose replayed value differs from the network one.
+#[derive(Debug, Serialize)]
+pub struct Mismatch {
+    pub field: &'static str,
+    pub expected: Value,
+    pub actual: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplayReport {
+    /// Whether the replay behaved like the transaction on the network.
+    pub matches: bool,
+    pub execution_status: TransactionExecutionStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+    /// The `__execute__` call of an invoke transaction or the constructor call of a deploy
+    /// account transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execute_invocation: Option<FunctionInvocation>,
+    pub events: Vec<Event>,
+    pub storage_diffs: Vec<ContractStorageDiff>,
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// A call of a call tree, without the fields that depend on the executing node.
+#[derive(Debug, Serialize)]
+struct CallSummary {
+    contract_address: Felt252,
+    entry_point_selector: Felt252,
+    result: Vec<Felt252>,
+}
+
+/// A call of a `starknet_traceTransaction` call tree.
+#[derive(Deserialize)]
+struct ExpectedCall {
+    contract_address: Felt252,
+    entry_point_selector: Felt252,
+    #[serde(default)]
+    result: Vec<Felt252>,
+    #[serde(default)]
+    calls: Vec<ExpectedCall>,
+}
+
+/// Re-executes a transaction and compares its outcome with the network receipt and trace.
+pub fn replay(replay: Replay) -> Result<ReplayReport> {
+    let mut state = replay.state.into_state()?;
+    let config = RunConfig {
+        execution_context: replay.block,
+        ..RunConfig::default()
+    };
+    let execution =
+        execute_transaction(&mut state, &config, replay.transaction).map_err(anyhow::Error::msg)?;
+    let call_info = execution.execute_call_info.as_ref();
+    let execution_status = execution.execution_status();
+    let events = execution.events();
+    let storage_diffs = execution.state_diff.storage_diffs.clone();
+
+    let mut mismatches = vec![];
+    let mut compare = |field: &'static str, expected: Value, actual: Value| {
+        if expected != actual {
+            mismatches.push(Mismatch {
+                field,
+                expected,
+                actual,
+            });
+        }
+    };
+    let receipt = &replay.receipt;
+    compare(
+        "execution_status",
+        to_value(receipt.execution_status),
+        to_value(execution_status),
+    );
+    if let (Some(revert_reason), Some(call_info)) = (&execution.revert_reason, call_info) {
+        let expected = receipt.revert_reason.as_deref().unwrap_or_default();
+        if !revert_reason_matches(expected, revert_reason, call_info) {
+            compare("revert_reason", to_value(expected), to_value(revert_reason));
+        }
+    }
+    compare("events", to_value(&receipt.events), to_value(&events));
+    if let Some(trace) = &replay.trace {
+        if let (Some(expected), Some(call_info)) = (
+            trace
+                .execute_invocation
+                .as_ref()
+                .or(trace.constructor_invocation.as_ref())
+                .and_then(|invocation| {
+                    serde_json::from_value::<ExpectedCall>(invocation.clone()).ok()
+                }),
+            call_info,
+        ) {
+            let mut expected_calls = vec![];
+            expected_calls_summary(&expected, &mut expected_calls);
+            let mut calls = vec![];
+            calls_summary(call_info, &mut calls);
+            compare("calls", to_value(expected_calls), to_value(calls));
+        }
+        if let Some(state_diff) = &trace.state_diff {
+            compare(
+                "storage_diffs",
+                to_value(sorted_storage_diffs(&state_diff.storage_diffs)),
+                to_value(&storage_diffs),
+            );
+        }
+    }
+
+    Ok(ReplayReport {
+        matches: mismatches.is_empty(),
+        execution_status,
+        revert_reason: execution.revert_reason.clone(),
+        execute_invocation: match execution_status {
+            TransactionExecutionStatus::Succeeded => call_info.map(FunctionInvocation::from),
+            TransactionExecutionStatus::Reverted => None,
+        },
+        events,
+        storage_diffs,
+        mismatches,
+    })
+}
+
+fn calls_summary(call_info: &CallInfo, calls: &mut Vec<CallSummary>) {
+    calls.push(CallSummary {
+        contract_address: call_info.contract_address,
+        entry_point_selector: call_info.entry_point_selector,
+        result: call_info.retdata.clone(),
+    });
+    for inner_call in &call_info.inner_calls {
+        calls_summary(inner_call, calls);
+    }
+}
+
+fn expected_calls_summary(call: &ExpectedCall, calls: &mut Vec<CallSummary>) {
+    calls.push(CallSummary {
+        contract_address: call.contract_address,
+        entry_point_selector: call.entry_point_selector,
+        result: call.result.clone(),
+    });
+    for inner_call in &call.calls {
+        expected_calls_summary(inner_call, calls);
+    }
+}
+
+/// Network revert reasons wrap the failure data in the call stack, so the reason only has to
+/// mention every felt of the return data of the failed call, as a short string or in hex.
+fn revert_reason_matches(expected: &str, revert_reason: &str, call_info: &CallInfo) -> bool {
+    if call_info.status != ExecutionStatus::Reverted {
+        return expected.contains(revert_reason);
+    }
+    call_info.retdata.iter().all(|value| {
+        expected.contains(&value.to_hex_string())
+            || as_short_string(&value.to_biguint()).is_some_and(|string| expected.contains(&string))
+    })
+}
+
+fn sorted_storage_diffs(diffs: &[ContractStorageDiff]) -> Vec<ContractStorageDiff> {
+    let mut diffs = diffs.to_vec();
+    diffs.sort_by_key(|diff| diff.address);
+    for diff in &mut diffs {
+        diff.storage_entries.sort_by_key(|entry| entry.key);
+    }
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::compile_contract::compile_contract;
+    use crate::rpc::rpc_class;
+    use crate::rpc::transaction_hash::calculate_contract_address;
+    use crate::rpc::types::StorageEntry;
+    use crate::trace::abi_decoder::selector_from_name;
+
+    const CODE: &str = r#"#[starknet::contract]
+    mod Account {
+        #[storage]
+        struct Storage {
+            counter: felt252,
+        }
+
+        #[event]
+        #[derive(Drop, starknet::Event)]
+        enum Event {
+            Increased: Increased,
+        }
+
+        #[derive(Drop, starknet::Event)]
+        struct Increased {
+            amount: felt252,
+        }
+
+        #[external(v0)]
+        fn __validate__(self: @ContractState, amount: felt252) -> felt252 {
+            'VALID'
+        }
+
+        #[external(v0)]
+        fn __execute__(ref self: ContractState, amount: felt252) -> felt252 {
+            assert(amount != 0, 'Zero amount');
+            self.counter.write(self.counter.read() + amount);
+            self.emit(Increased { amount });
+            amount
+        }
+    }
+    "#;
+
+    fn replay_input(amount: u64, receipt: Value) -> Replay {
+        let compilation_result = compile_contract(CODE, "account").unwrap();
+        let class = rpc_class(&compilation_result.cairo_sierra.sierra_contract_class);
+        let counter_key = selector_from_name("counter").to_hex_string();
+        serde_json::from_value(serde_json::json!({
+            "transaction": {
+                "type": "INVOKE",
+                "sender_address": "0x100",
+                "calldata": [Felt252::from(amount)],
+                "nonce": "0x0",
+                "version": "0x1",
+                "max_fee": "0x0"
+            },
+            "state": {
+                "classes": { "0x1234": class },
+                "contracts": { "0x100": "0x1234" },
+                "storage": { "0x100": { counter_key: "0x2" } }
+            },
+            "block": { "block_number": 10 },
+            "receipt": receipt
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_replay() {
+        let report = replay(replay_input(
+            3,
+            serde_json::json!({
+                "execution_status": "SUCCEEDED",
+                "events": [{
+                    "from_address": "0x100",
+                    "keys": [selector_from_name("Increased")],
+                    "data": ["0x3"]
+                }]
+            }),
+        ))
+        .unwrap();
+        assert!(report.matches, "{:?}", report.mismatches);
+        assert_eq!(
+            report.storage_diffs,
+            vec![ContractStorageDiff {
+                address: Felt252::from(0x100),
+                storage_entries: vec![StorageEntry {
+                    key: selector_from_name("counter"),
+                    value: Felt252::from(5),
+                }],
+            }]
+        );
+
+        let report = replay(replay_input(
+            0,
+            serde_json::json!({
+                "execution_status": "REVERTED",
+                "revert_reason": "Execution failed. Failure reason: 0x5a65726f20616d6f756e74 ('Zero amount')."
+            }),
+        ))
+        .unwrap();
+        assert!(report.matches, "{:?}", report.mismatches);
+        assert!(report.storage_diffs.is_empty());
+
+        let report = replay(replay_input(
+            0,
+            serde_json::json!({ "execution_status": "SUCCEEDED" }),
+        ))
+        .unwrap();
+        assert!(!report.matches);
+        assert_eq!(report.mismatches[0].field, "execution_status");
+    }
+
+    #[test]
+    fn test_replay_declare() {
+        let account_code = r#"#[starknet::contract]
+        mod Account {
+            #[storage]
+            struct Storage {
+                declared: felt252,
+            }
+
+            #[external(v0)]
+            fn __validate_declare__(ref self: ContractState, class_hash: felt252) -> felt252 {
+                self.declared.write(class_hash);
+                'VALID'
+            }
+        }
+        "#;
+        let account = compile_contract(account_code, "account").unwrap();
+        let declared = compile_contract(CODE, "declared").unwrap();
+        let contract_class = rpc_class(&declared.cairo_sierra.sierra_contract_class);
+        let class = compile_class(contract_class.clone()).unwrap();
+        let replay_input = serde_json::from_value(serde_json::json!({
+            "transaction": {
+                "type": "DECLARE",
+                "sender_address": "0x100",
+                "compiled_class_hash": class.compiled_class_hash,
+                "contract_class": contract_class,
+                "nonce": "0x0",
+                "version": "0x2",
+                "max_fee": "0x0"
+            },
+            "state": {
+                "classes": { "0x1234": rpc_class(&account.cairo_sierra.sierra_contract_class) },
+                "contracts": { "0x100": "0x1234" }
+            },
+            "receipt": { "execution_status": "SUCCEEDED" },
+            "trace": {
+                "state_diff": {
+                    "storage_diffs": [{
+                        "address": "0x100",
+                        "storage_entries": [{
+                            "key": selector_from_name("declared"),
+                            "value": class.class_hash
+                        }]
+                    }]
+                }
+            }
+        }))
+        .unwrap();
+
+        let report = replay(replay_input).unwrap();
+        assert!(report.matches, "{:?}", report.mismatches);
+        assert!(report.execute_invocation.is_none());
+    }
+
+    #[test]
+    fn test_replay_deploy_account() {
+        let code = r#"#[starknet::contract]
+        mod Account {
+            #[storage]
+            struct Storage {
+                owner: felt252,
+            }
+
+            #[constructor]
+            fn constructor(ref self: ContractState, owner: felt252) {
+                self.owner.write(owner);
+            }
+
+            #[external(v0)]
+            fn __validate_deploy__(
+                self: @ContractState, class_hash: felt252, salt: felt252, owner: felt252
+            ) -> felt252 {
+                assert(self.owner.read() == owner, 'Not deployed');
+                'VALID'
+            }
+        }
+        "#;
+        let compilation_result = compile_contract(code, "account").unwrap();
+        let class_hash = Felt252::from(0x1234);
+        let salt = Felt252::from(7);
+        let owner = Felt252::from(0x42);
+        let address = calculate_contract_address(salt, class_hash, &[owner], Felt252::ZERO);
+        let replay_input = serde_json::from_value(serde_json::json!({
+            "transaction": {
+                "type": "DEPLOY_ACCOUNT",
+                "class_hash": class_hash,
+                "contract_address_salt": salt,
+                "constructor_calldata": [owner],
+                "nonce": "0x0",
+                "version": "0x1",
+                "max_fee": "0x0"
+            },
+            "state": {
+                "classes": {
+                    "0x1234": rpc_class(&compilation_result.cairo_sierra.sierra_contract_class)
+                }
+            },
+            "receipt": { "execution_status": "SUCCEEDED" },
+            "trace": {
+                "constructor_invocation": {
+                    "contract_address": address,
+                    "entry_point_selector": selector_from_name("constructor")
+                },
+                "state_diff": {
+                    "storage_diffs": [{
+                        "address": address,
+                        "storage_entries": [{
+                            "key": selector_from_name("owner"),
+                            "value": owner
+                        }]
+                    }]
+                }
+            }
+        }))
+        .unwrap();
+
+        let report = replay(replay_input).unwrap();
+        assert!(report.matches, "{:?}", report.mismatches);
+        assert_eq!(report.execute_invocation.unwrap().contract_address, address);
+    }
+}
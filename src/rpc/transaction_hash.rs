@@ -0,0 +1,432 @@
+use anyhow::Result;
+use starknet_crypto::{get_public_key, rfc6979_generate_k, FieldElement};
+use starknet_types_core::felt::Felt as Felt252;
+use starknet_types_core::hash::{Pedersen, Poseidon, StarkHash};
+
+use super::types::{
+    BroadcastedDeclareTransaction, BroadcastedDeployAccountTransaction,
+    BroadcastedInvokeTransaction, BroadcastedTransaction, ResourceBounds, ResourceFields,
+    SierraClass, SierraEntryPoint,
+};
+use crate::trace::abi_decoder::selector_from_name;
+
+/// Added to the version of transactions only meant to be simulated or estimated, `2**128`.
+const QUERY_VERSION_BASE: &str = "0x100000000000000000000000000000000";
+
+/// `2**192` and `2**128`, to pack a resource name, amount and price into a felt.
+const RESOURCE_NAME_SHIFT: &str = "0x1000000000000000000000000000000000000000000000000";
+const MAX_AMOUNT_SHIFT: &str = "0x100000000000000000000000000000000";
+
+/// The bound of contract addresses, `2**251 - 256`.
+const ADDR_BOUND: &str = "0x7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff00";
+
+/// Strips the query bit of a transaction version.
+fn base_version(version: Felt252) -> Felt252 {
+    let query_version_base = Felt252::from_hex_unchecked(QUERY_VERSION_BASE);
+    if version >= query_version_base {
+        version - query_version_base
+    } else {
+        version
+    }
+}
+
+/// Whether a transaction is a v3 transaction, paying its fee in STRK.
+pub fn is_v3(version: Felt252) -> bool {
+    base_version(version) == Felt252::THREE
+}
+
+/// Computes the hash of a transaction on a chain, the message its signature signs.
+///
+/// Invoke and deploy account transactions are hashed with Pedersen up to v1 and with Poseidon in
+/// v3. Declare transactions are supported from v2, the first version declaring Sierra classes.
+pub fn transaction_hash(
+    transaction: &BroadcastedTransaction,
+    chain_id: Felt252,
+) -> Result<Felt252> {
+    match transaction {
+        BroadcastedTransaction::Invoke(transaction) => {
+            invoke_transaction_hash(transaction, chain_id)
+        }
+        BroadcastedTransaction::Declare(transaction) => {
+            declare_transaction_hash(transaction, chain_id)
+        }
+        BroadcastedTransaction::DeployAccount(transaction) => {
+            deploy_account_transaction_hash(transaction, chain_id)
+        }
+    }
+}
+
+pub fn invoke_transaction_hash(
+    transaction: &BroadcastedInvokeTransaction,
+    chain_id: Felt252,
+) -> Result<Felt252> {
+    let prefix = Felt252::from_bytes_be_slice(b"invoke");
+    let version = transaction.version;
+    if base_version(version) == Felt252::ONE {
+        return Ok(Pedersen::hash_array(&[
+            prefix,
+            version,
+            transaction.sender_address,
+            Felt252::ZERO,
+            Pedersen::hash_array(&transaction.calldata),
+            transaction.max_fee.unwrap_or_default(),
+            chain_id,
+            transaction.nonce,
+        ]));
+    }
+    check_v3(version)?;
+    Ok(v3_transaction_hash(
+        [prefix, version, transaction.sender_address],
+        &transaction.resources,
+        chain_id,
+        transaction.nonce,
+        &[
+            Poseidon::hash_array(&transaction.account_deployment_data),
+            Poseidon::hash_array(&transaction.calldata),
+        ],
+    ))
+}
+
+pub fn declare_transaction_hash(
+    transaction: &BroadcastedDeclareTransaction,
+    chain_id: Felt252,
+) -> Result<Felt252> {
+    let prefix = Felt252::from_bytes_be_slice(b"declare");
+    let version = transaction.version;
+    let class_hash = sierra_class_hash(&transaction.contract_class);
+    if base_version(version) == Felt252::TWO {
+        return Ok(Pedersen::hash_array(&[
+            prefix,
+            version,
+            transaction.sender_address,
+            Felt252::ZERO,
+            Pedersen::hash_array(&[class_hash]),
+            transaction.max_fee.unwrap_or_default(),
+            chain_id,
+            transaction.nonce,
+            transaction.compiled_class_hash,
+        ]));
+    }
+    check_v3(version)?;
+    Ok(v3_transaction_hash(
+        [prefix, version, transaction.sender_address],
+        &transaction.resources,
+        chain_id,
+        transaction.nonce,
+        &[
+            Poseidon::hash_array(&transaction.account_deployment_data),
+            class_hash,
+            transaction.compiled_class_hash,
+        ],
+    ))
+}
+
+pub fn deploy_account_transaction_hash(
+    transaction: &BroadcastedDeployAccountTransaction,
+    chain_id: Felt252,
+) -> Result<Felt252> {
+    let prefix = Felt252::from_bytes_be_slice(b"deploy_account");
+    let version = transaction.version;
+    let contract_address = calculate_contract_address(
+        transaction.contract_address_salt,
+        transaction.class_hash,
+        &transaction.constructor_calldata,
+        Felt252::ZERO,
+    );
+    if base_version(version) == Felt252::ONE {
+        let mut deployment = vec![transaction.class_hash, transaction.contract_address_salt];
+        deployment.extend(&transaction.constructor_calldata);
+        return Ok(Pedersen::hash_array(&[
+            prefix,
+            version,
+            contract_address,
+            Felt252::ZERO,
+            Pedersen::hash_array(&deployment),
+            transaction.max_fee.unwrap_or_default(),
+            chain_id,
+            transaction.nonce,
+        ]));
+    }
+    check_v3(version)?;
+    Ok(v3_transaction_hash(
+        [prefix, version, contract_address],
+        &transaction.resources,
+        chain_id,
+        transaction.nonce,
+        &[
+            Poseidon::hash_array(&transaction.constructor_calldata),
+            transaction.class_hash,
+            transaction.contract_address_salt,
+        ],
+    ))
+}
+
+fn check_v3(version: Felt252) -> Result<()> {
+    if !is_v3(version) {
+        anyhow::bail!(
+            "Unsupported transaction version {}.",
+            version.to_hex_string()
+        );
+    }
+    Ok(())
+}
+
+/// Hashes the fields common to v3 transactions, then the fields of the transaction type.
+fn v3_transaction_hash(
+    [prefix, version, sender_address]: [Felt252; 3],
+    resources: &ResourceFields,
+    chain_id: Felt252,
+    nonce: Felt252,
+    fields: &[Felt252],
+) -> Felt252 {
+    let bounds = &resources.resource_bounds;
+    let mut fee_fields = vec![
+        resources.tip,
+        resource_bounds(b"L1_GAS", &bounds.l1_gas),
+        resource_bounds(b"L2_GAS", &bounds.l2_gas),
+    ];
+    if let Some(l1_data_gas) = &bounds.l1_data_gas {
+        fee_fields.push(resource_bounds(b"L1_DATA", l1_data_gas));
+    }
+    let data_availability_modes = Felt252::from(resources.nonce_data_availability_mode.value())
+        * Felt252::from(1_u64 << 32)
+        + Felt252::from(resources.fee_data_availability_mode.value());
+
+    let mut elements = vec![
+        prefix,
+        version,
+        sender_address,
+        Poseidon::hash_array(&fee_fields),
+        Poseidon::hash_array(&resources.paymaster_data),
+        chain_id,
+        nonce,
+        data_availability_modes,
+    ];
+    elements.extend(fields);
+    Poseidon::hash_array(&elements)
+}
+
+/// Packs the name of a resource, its maximal amount (64 bits) and its maximal price (128 bits).
+fn resource_bounds(name: &[u8], bounds: &ResourceBounds) -> Felt252 {
+    Felt252::from_bytes_be_slice(name) * Felt252::from_hex_unchecked(RESOURCE_NAME_SHIFT)
+        + bounds.max_amount * Felt252::from_hex_unchecked(MAX_AMOUNT_SHIFT)
+        + bounds.max_price_per_unit
+}
+
+/// Computes the hash of a Sierra class from its entrypoints, ABI and program.
+pub fn sierra_class_hash(class: &SierraClass) -> Felt252 {
+    let entry_points_hash = |entry_points: &[SierraEntryPoint]| {
+        let elements: Vec<Felt252> = entry_points
+            .iter()
+            .flat_map(|entry_point| {
+                [
+                    entry_point.selector,
+                    Felt252::from(entry_point.function_idx),
+                ]
+            })
+            .collect();
+        Poseidon::hash_array(&elements)
+    };
+    let entry_points = &class.entry_points_by_type;
+    Poseidon::hash_array(&[
+        Felt252::from_bytes_be_slice(b"CONTRACT_CLASS_V0.1.0"),
+        entry_points_hash(&entry_points.external),
+        entry_points_hash(&entry_points.l1_handler),
+        entry_points_hash(&entry_points.constructor),
+        selector_from_name(&class.abi),
+        Poseidon::hash_array(&class.sierra_program),
+    ])
+}
+
+/// Computes the address of a contract deployed by `deployer_address`, zero for deploy account
+/// transactions.
+pub fn calculate_contract_address(
+    salt: Felt252,
+    class_hash: Felt252,
+    constructor_calldata: &[Felt252],
+    deployer_address: Felt252,
+) -> Felt252 {
+    let address = Pedersen::hash_array(&[
+        Felt252::from_bytes_be_slice(b"STARKNET_CONTRACT_ADDRESS"),
+        deployer_address,
+        salt,
+        class_hash,
+        Pedersen::hash_array(constructor_calldata),
+    ]);
+    let addr_bound = Felt252::from_hex_unchecked(ADDR_BOUND);
+    if address >= addr_bound {
+        address - addr_bound
+    } else {
+        address
+    }
+}
+
+/// The Stark public key of a private key.
+pub fn public_key(private_key: Felt252) -> Felt252 {
+    felt(get_public_key(&field_element(private_key)))
+}
+
+/// Signs a transaction hash with a private key, returning the `[r, s]` signature checked by
+/// accounts.
+pub fn sign(private_key: Felt252, transaction_hash: Felt252) -> Result<Vec<Felt252>> {
+    let private_key = field_element(private_key);
+    let message = field_element(transaction_hash);
+    let k = rfc6979_generate_k(&message, &private_key, None);
+    let signature = starknet_crypto::sign(&private_key, &message, &k).map_err(|e| {
+        anyhow::anyhow!("Failed to sign {}: {:?}.", felt(message).to_hex_string(), e)
+    })?;
+    Ok(vec![felt(signature.r), felt(signature.s)])
+}
+
+fn field_element(felt: Felt252) -> FieldElement {
+    FieldElement::from_bytes_be(&felt.to_bytes_be()).unwrap()
+}
+
+fn felt(field_element: FieldElement) -> Felt252 {
+    Felt252::from_bytes_be(&field_element.to_bytes_be())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoke(version: Felt252) -> BroadcastedInvokeTransaction {
+        BroadcastedInvokeTransaction {
+            sender_address: Felt252::from(0x100),
+            calldata: vec![Felt252::ONE, Felt252::TWO],
+            nonce: Felt252::ONE,
+            version,
+            max_fee: Some(Felt252::from(10_u128.pow(15))),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sign_transaction_hash() {
+        let chain_id = Felt252::from_bytes_be_slice(b"SN_SEPOLIA");
+        let hash = invoke_transaction_hash(&invoke(Felt252::ONE), chain_id).unwrap();
+        let query_hash = invoke_transaction_hash(
+            &invoke(Felt252::from_hex_unchecked(QUERY_VERSION_BASE) + Felt252::ONE),
+            chain_id,
+        )
+        .unwrap();
+        assert_ne!(hash, query_hash);
+
+        let mut transaction = invoke(Felt252::THREE);
+        let v3_hash = invoke_transaction_hash(&transaction, chain_id).unwrap();
+        transaction.resources.tip = Felt252::ONE;
+        assert_ne!(
+            invoke_transaction_hash(&transaction, chain_id).unwrap(),
+            v3_hash
+        );
+        assert!(invoke_transaction_hash(&invoke(Felt252::TWO), chain_id).is_err());
+
+        let private_key = Felt252::from(0x1234);
+        let signature = sign(private_key, hash).unwrap();
+        assert!(starknet_crypto::verify(
+            &field_element(public_key(private_key)),
+            &field_element(hash),
+            &field_element(signature[0]),
+            &field_element(signature[1]),
+        )
+        .unwrap());
+    }
+
+    /// The fee fields of the v3 transactions of the vectors: a tip of 7 and 100 L1 gas at 10.
+    fn v3_resources() -> serde_json::Value {
+        serde_json::json!({
+            "l1_gas": { "max_amount": "0x64", "max_price_per_unit": "0xa" },
+            "l2_gas": { "max_amount": "0x0", "max_price_per_unit": "0x0" }
+        })
+    }
+
+    fn declare(version: u64) -> BroadcastedDeclareTransaction {
+        serde_json::from_value(serde_json::json!({
+            "sender_address": "0x100",
+            "compiled_class_hash": "0x1234",
+            "contract_class": {
+                "sierra_program": ["0x1", "0x2", "0x3"],
+                "contract_class_version": "0.1.0",
+                "entry_points_by_type": {
+                    "CONSTRUCTOR": [],
+                    "EXTERNAL": [{ "selector": selector_from_name("increase"), "function_idx": 0 }],
+                    "L1_HANDLER": []
+                },
+                "abi": "[]"
+            },
+            "nonce": "0x1",
+            "version": Felt252::from(version),
+            "max_fee": Felt252::from(10_u128.pow(15)),
+            "resource_bounds": v3_resources(),
+            "tip": "0x7"
+        }))
+        .unwrap()
+    }
+
+    fn deploy_account(version: u64) -> BroadcastedDeployAccountTransaction {
+        serde_json::from_value(serde_json::json!({
+            "class_hash": "0x1234",
+            "contract_address_salt": "0x7",
+            "constructor_calldata": ["0x42"],
+            "nonce": "0x0",
+            "version": Felt252::from(version),
+            "max_fee": Felt252::from(10_u128.pow(15)),
+            "resource_bounds": v3_resources(),
+            "tip": "0x7"
+        }))
+        .unwrap()
+    }
+
+    // The expected hashes were computed with a separate implementation of the Starknet hash
+    // definitions, whose Pedersen, Poseidon and Keccak match the test vectors of cairo-lang.
+    #[test]
+    fn test_known_hashes() {
+        let chain_id = Felt252::from_bytes_be_slice(b"SN_SEPOLIA");
+        let hash = |hex: &str| Felt252::from_hex_unchecked(hex);
+
+        assert_eq!(
+            invoke_transaction_hash(&invoke(Felt252::ONE), chain_id).unwrap(),
+            hash("0x5185d2efe8e0f6e316c635839f3626d0bb027e835e56a73c6cafae1c8ed960b")
+        );
+        let mut transaction = invoke(Felt252::THREE);
+        transaction.resources = serde_json::from_value(
+            serde_json::json!({ "resource_bounds": v3_resources(), "tip": "0x7" }),
+        )
+        .unwrap();
+        assert_eq!(
+            invoke_transaction_hash(&transaction, chain_id).unwrap(),
+            hash("0x1b05de3b35018ffcecfac64f23dd0866f0954e34af40bed65109f34e0b7b527")
+        );
+
+        assert_eq!(
+            sierra_class_hash(&declare(2).contract_class),
+            hash("0x98fe1b25efd7080b35abdafd99c7bec6f0dac26dd8b7a9e6a00f9c6c855f30")
+        );
+        assert_eq!(
+            declare_transaction_hash(&declare(2), chain_id).unwrap(),
+            hash("0xddf604ca8f738ceb2b7a444ddb383bb9e87785e36b25d65d65e6df7e23dbfd")
+        );
+        assert_eq!(
+            declare_transaction_hash(&declare(3), chain_id).unwrap(),
+            hash("0x7631d4e5ea1ab4819d303333664e6498688b4e2bf2196797bea13b4dcc6e639")
+        );
+
+        assert_eq!(
+            calculate_contract_address(
+                Felt252::from(7),
+                Felt252::from(0x1234),
+                &[Felt252::from(0x42)],
+                Felt252::ZERO
+            ),
+            hash("0x4c6fbd8b156befb54bd6bac12c14a17cbc8558bfee5db57b6edd1f3172e1e76")
+        );
+        assert_eq!(
+            deploy_account_transaction_hash(&deploy_account(1), chain_id).unwrap(),
+            hash("0x24c0a654671225f181aa9782688aa504d68217145d2c9a81c40471a9329595e")
+        );
+        assert_eq!(
+            deploy_account_transaction_hash(&deploy_account(3), chain_id).unwrap(),
+            hash("0x4af01255baea76c061fef2d673c0ebe3488d1cac4c89dc657776184bf9135d5")
+        );
+    }
+}
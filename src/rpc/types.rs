@@ -0,0 +1,493 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use starknet_types_core::felt::Felt as Felt252;
+
+use crate::trace::events::EmittedEvent;
+use crate::trace::execution::{CallInfo, CallType, EntryPointType};
+use crate::trace::resources::{ExecutionResources, TransactionResources};
+
+#[derive(Debug, Deserialize)]
+pub struct RpcRequest {
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RpcResponse {
+    pub jsonrpc: &'static str,
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+}
+
+/// A call to a contract, as taken by `starknet_call`.
+#[derive(Debug, Deserialize)]
+pub struct FunctionCall {
+    pub contract_address: Felt252,
+    pub entry_point_selector: Felt252,
+    pub calldata: Vec<Felt252>,
+}
+
+/// A transaction as sent to the sequencer, before it is assigned a hash.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BroadcastedTransaction {
+    Invoke(BroadcastedInvokeTransaction),
+    Declare(BroadcastedDeclareTransaction),
+    DeployAccount(BroadcastedDeployAccountTransaction),
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BroadcastedInvokeTransaction {
+    pub sender_address: Felt252,
+    pub calldata: Vec<Felt252>,
+    #[serde(default)]
+    pub signature: Vec<Felt252>,
+    pub nonce: Felt252,
+    pub version: Felt252,
+    /// The fee bound of transactions before v3.
+    #[serde(default)]
+    pub max_fee: Option<Felt252>,
+    #[serde(flatten)]
+    pub resources: ResourceFields,
+    #[serde(default)]
+    pub account_deployment_data: Vec<Felt252>,
+}
+
+/// A declare transaction of a Sierra class, v2 or v3.
+#[derive(Debug, Deserialize)]
+pub struct BroadcastedDeclareTransaction {
+    pub sender_address: Felt252,
+    pub compiled_class_hash: Felt252,
+    pub contract_class: SierraClass,
+    #[serde(default)]
+    pub signature: Vec<Felt252>,
+    pub nonce: Felt252,
+    pub version: Felt252,
+    #[serde(default)]
+    pub max_fee: Option<Felt252>,
+    #[serde(flatten)]
+    pub resources: ResourceFields,
+    #[serde(default)]
+    pub account_deployment_data: Vec<Felt252>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BroadcastedDeployAccountTransaction {
+    pub class_hash: Felt252,
+    pub contract_address_salt: Felt252,
+    #[serde(default)]
+    pub constructor_calldata: Vec<Felt252>,
+    #[serde(default)]
+    pub signature: Vec<Felt252>,
+    pub nonce: Felt252,
+    pub version: Felt252,
+    #[serde(default)]
+    pub max_fee: Option<Felt252>,
+    #[serde(flatten)]
+    pub resources: ResourceFields,
+}
+
+/// The fee fields of v3 transactions, left to their defaults by earlier versions.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ResourceFields {
+    pub resource_bounds: ResourceBoundsMapping,
+    pub tip: Felt252,
+    pub paymaster_data: Vec<Felt252>,
+    pub nonce_data_availability_mode: DataAvailabilityMode,
+    pub fee_data_availability_mode: DataAvailabilityMode,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ResourceBoundsMapping {
+    pub l1_gas: ResourceBounds,
+    pub l2_gas: ResourceBounds,
+    /// Only bounded by transactions from Starknet 0.13.4.
+    #[serde(default)]
+    pub l1_data_gas: Option<ResourceBounds>,
+}
+
+impl ResourceBoundsMapping {
+    /// The maximal fee of the bounds: the sum of their maximal amounts times their maximal prices.
+    pub fn max_fee(&self) -> Felt252 {
+        [
+            Some(&self.l1_gas),
+            Some(&self.l2_gas),
+            self.l1_data_gas.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(Felt252::ZERO, |max_fee, bounds| {
+            max_fee + bounds.max_amount * bounds.max_price_per_unit
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ResourceBounds {
+    pub max_amount: Felt252,
+    pub max_price_per_unit: Felt252,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum DataAvailabilityMode {
+    #[default]
+    L1,
+    L2,
+}
+
+impl DataAvailabilityMode {
+    /// The mode as encoded by Starknet: 0 for L1, 1 for L2.
+    pub fn value(self) -> u32 {
+        match self {
+            DataAvailabilityMode::L1 => 0,
+            DataAvailabilityMode::L2 => 1,
+        }
+    }
+}
+
+/// A Sierra class in the format of `starknet_getClass`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SierraClass {
+    pub sierra_program: Vec<Felt252>,
+    pub contract_class_version: String,
+    pub entry_points_by_type: SierraEntryPointsByType,
+    /// The ABI as a JSON string, hashed as is.
+    #[serde(default)]
+    pub abi: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SierraEntryPointsByType {
+    #[serde(rename = "CONSTRUCTOR")]
+    pub constructor: Vec<SierraEntryPoint>,
+    #[serde(rename = "EXTERNAL")]
+    pub external: Vec<SierraEntryPoint>,
+    #[serde(rename = "L1_HANDLER")]
+    pub l1_handler: Vec<SierraEntryPoint>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SierraEntryPoint {
+    pub selector: Felt252,
+    pub function_idx: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeeEstimate {
+    pub gas_consumed: Felt252,
+    pub gas_price: Felt252,
+    pub data_gas_consumed: Felt252,
+    pub data_gas_price: Felt252,
+    pub overall_fee: Felt252,
+    pub unit: &'static str,
+}
+
+/// The VM resources of a call or a transaction.
+#[derive(Debug, Serialize)]
+pub struct ComputationResources {
+    pub steps: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_holes: Option<usize>,
+    /// The instances of each builtin used, e.g. `pedersen_builtin_applications`.
+    #[serde(flatten)]
+    pub builtin_applications: BTreeMap<String, usize>,
+}
+
+impl From<&ExecutionResources> for ComputationResources {
+    fn from(resources: &ExecutionResources) -> Self {
+        Self {
+            steps: resources.n_steps,
+            memory_holes: Some(resources.n_memory_holes).filter(|holes| *holes > 0),
+            builtin_applications: resources
+                .builtin_instance_counter
+                .iter()
+                .map(|(name, count)| match name.as_str() {
+                    "segment_arena_builtin" => (name.clone(), *count),
+                    _ => (format!("{}_applications", name), *count),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The resources of a transaction, including the gas of its state diff.
+#[derive(Debug, Serialize)]
+pub struct TransactionExecutionResources {
+    #[serde(flatten)]
+    pub computation: ComputationResources,
+    pub data_availability: DataAvailabilityResources,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DataAvailabilityResources {
+    pub l1_gas: u128,
+    pub l1_data_gas: u128,
+}
+
+impl From<&TransactionResources> for TransactionExecutionResources {
+    fn from(resources: &TransactionResources) -> Self {
+        Self {
+            computation: ComputationResources::from(&resources.computation),
+            data_availability: DataAvailabilityResources {
+                l1_gas: resources.da_gas.l1_gas,
+                l1_data_gas: resources.da_gas.l1_data_gas,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderedMessage {
+    /// The index of the message among those of its call.
+    pub order: usize,
+    pub to_address: Felt252,
+    pub payload: Vec<Felt252>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderedEvent {
+    pub order: usize,
+    pub keys: Vec<Felt252>,
+    pub data: Vec<Felt252>,
+}
+
+impl From<&EmittedEvent> for OrderedEvent {
+    fn from(event: &EmittedEvent) -> Self {
+        Self {
+            order: event.order,
+            keys: event.keys.clone(),
+            data: event.data.clone(),
+        }
+    }
+}
+
+/// A node of the call tree of a transaction trace.
+#[derive(Debug, Serialize)]
+pub struct FunctionInvocation {
+    pub contract_address: Felt252,
+    pub entry_point_selector: Felt252,
+    pub calldata: Vec<Felt252>,
+    pub caller_address: Felt252,
+    pub class_hash: Felt252,
+    pub entry_point_type: &'static str,
+    pub call_type: &'static str,
+    pub result: Vec<Felt252>,
+    pub calls: Vec<FunctionInvocation>,
+    pub events: Vec<OrderedEvent>,
+    pub messages: Vec<OrderedMessage>,
+    pub execution_resources: ComputationResources,
+}
+
+impl From<&CallInfo> for FunctionInvocation {
+    fn from(call_info: &CallInfo) -> Self {
+        Self {
+            contract_address: call_info.contract_address,
+            entry_point_selector: call_info.entry_point_selector,
+            calldata: call_info.calldata.clone(),
+            caller_address: call_info.caller_address,
+            class_hash: call_info.class_hash,
+            entry_point_type: match call_info.entry_point_type {
+                EntryPointType::External => "EXTERNAL",
+                EntryPointType::L1Handler => "L1_HANDLER",
+                EntryPointType::Constructor => "CONSTRUCTOR",
+            },
+            call_type: match call_info.call_type {
+                CallType::Call => "CALL",
+                CallType::Delegate => "LIBRARY_CALL",
+            },
+            result: call_info.retdata.clone(),
+            calls: call_info.inner_calls.iter().map(Into::into).collect(),
+            events: call_info.events.iter().map(Into::into).collect(),
+            messages: call_info
+                .l2_to_l1_messages
+                .iter()
+                .enumerate()
+                .map(|(order, message)| OrderedMessage {
+                    order,
+                    to_address: message.to_address,
+                    payload: message.payload.clone(),
+                })
+                .collect(),
+            execution_resources: ComputationResources::from(&call_info.resources),
+        }
+    }
+}
+
+/// The outcome of the `__execute__` call of an invoke transaction.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum ExecuteInvocation {
+    Succeeded(Box<FunctionInvocation>),
+    Reverted { revert_reason: String },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransactionTrace {
+    Invoke {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        validate_invocation: Option<FunctionInvocation>,
+        execute_invocation: ExecuteInvocation,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fee_transfer_invocation: Option<FunctionInvocation>,
+        state_diff: StateDiff,
+        execution_resources: TransactionExecutionResources,
+    },
+    Declare {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        validate_invocation: Option<FunctionInvocation>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fee_transfer_invocation: Option<FunctionInvocation>,
+        state_diff: StateDiff,
+        execution_resources: TransactionExecutionResources,
+    },
+    DeployAccount {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        validate_invocation: Option<FunctionInvocation>,
+        constructor_invocation: Box<FunctionInvocation>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fee_transfer_invocation: Option<FunctionInvocation>,
+        state_diff: StateDiff,
+        execution_resources: TransactionExecutionResources,
+    },
+}
+
+/// A flag of `starknet_estimateFee` and `starknet_simulateTransactions`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SimulationFlag {
+    SkipValidate,
+    SkipFeeCharge,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimulatedTransaction {
+    pub transaction_trace: TransactionTrace,
+    pub fee_estimation: FeeEstimate,
+    /// The part of `fee_estimation` paying for the publication of the state diff.
+    pub data_availability_fee: FeePayment,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransactionExecutionStatus {
+    Succeeded,
+    Reverted,
+}
+
+/// An event of a transaction receipt.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Event {
+    pub from_address: Felt252,
+    pub keys: Vec<Felt252>,
+    pub data: Vec<Felt252>,
+}
+
+impl From<&EmittedEvent> for Event {
+    fn from(event: &EmittedEvent) -> Self {
+        Self {
+            from_address: event.contract_address,
+            keys: event.keys.clone(),
+            data: event.data.clone(),
+        }
+    }
+}
+
+/// The storage values a transaction wrote to a contract.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractStorageDiff {
+    pub address: Felt252,
+    pub storage_entries: Vec<StorageEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageEntry {
+    pub key: Felt252,
+    pub value: Felt252,
+}
+
+/// The changes of a transaction to the state, as published on L1.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct StateDiff {
+    pub storage_diffs: Vec<ContractStorageDiff>,
+    /// Cairo 0 classes, never declared by the simulator.
+    pub deprecated_declared_classes: Vec<Felt252>,
+    pub declared_classes: Vec<DeclaredClassHash>,
+    pub deployed_contracts: Vec<DeployedContract>,
+    pub replaced_classes: Vec<ReplacedClass>,
+    pub nonces: Vec<NonceUpdate>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DeclaredClassHash {
+    pub class_hash: Felt252,
+    pub compiled_class_hash: Felt252,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DeployedContract {
+    pub address: Felt252,
+    pub class_hash: Felt252,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ReplacedClass {
+    pub contract_address: Felt252,
+    pub class_hash: Felt252,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct NonceUpdate {
+    pub contract_address: Felt252,
+    pub nonce: Felt252,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeePayment {
+    pub amount: Felt252,
+    pub unit: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransactionReceipt {
+    pub transaction_hash: Felt252,
+    pub actual_fee: FeePayment,
+    pub execution_status: TransactionExecutionStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+    pub finality_status: &'static str,
+    pub events: Vec<Event>,
+    pub execution_resources: TransactionExecutionResources,
+}
@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use starknet_types_core::felt::Felt as Felt252;
+
+use super::State;
+use crate::rpc::types::{
+    ContractStorageDiff, DeclaredClassHash, DeployedContract, NonceUpdate, ReplacedClass,
+    StateDiff, StorageEntry,
+};
+
+/// The changes from `previous_state` to `state`, sorted by address and key.
+///
+/// A value written back to what it was is not a change. Contracts at an address without a class
+/// are deployed, the others had their class replaced.
+pub fn state_diff(previous_state: &State, state: &State) -> Result<StateDiff> {
+    let mut storage_diffs: BTreeMap<Felt252, Vec<StorageEntry>> = BTreeMap::new();
+    for (&(address, key), &value) in &state.storage {
+        if previous_state.get_storage_at(address, key)? != value {
+            storage_diffs
+                .entry(address)
+                .or_default()
+                .push(StorageEntry { key, value });
+        }
+    }
+
+    let mut declared_classes = vec![];
+    for (&class_hash, class) in &state.classes {
+        if !previous_state.classes.contains_key(&class_hash) {
+            declared_classes.push(DeclaredClassHash {
+                class_hash,
+                compiled_class_hash: class.compiled_class_hash,
+            });
+        }
+    }
+    declared_classes.sort_by_key(|class| class.class_hash);
+
+    let mut deployed_contracts = vec![];
+    let mut replaced_classes = vec![];
+    for (&address, &class_hash) in &state.contracts {
+        match previous_state.get_class_hash_at(&address) {
+            Ok(previous_class_hash) if previous_class_hash == class_hash => {}
+            Ok(_) => replaced_classes.push(ReplacedClass {
+                contract_address: address,
+                class_hash,
+            }),
+            Err(_) => deployed_contracts.push(DeployedContract {
+                address,
+                class_hash,
+            }),
+        }
+    }
+    deployed_contracts.sort_by_key(|contract| contract.address);
+    replaced_classes.sort_by_key(|class| class.contract_address);
+
+    let mut nonces = vec![];
+    for (&contract_address, &nonce) in &state.nonces {
+        if previous_state.get_nonce_at(contract_address)? != nonce {
+            nonces.push(NonceUpdate {
+                contract_address,
+                nonce,
+            });
+        }
+    }
+    nonces.sort_by_key(|update| update.contract_address);
+
+    Ok(StateDiff {
+        storage_diffs: storage_diffs
+            .into_iter()
+            .map(|(address, mut storage_entries)| {
+                storage_entries.sort_by_key(|entry| entry.key);
+                ContractStorageDiff {
+                    address,
+                    storage_entries,
+                }
+            })
+            .collect(),
+        deprecated_declared_classes: vec![],
+        declared_classes,
+        deployed_contracts,
+        replaced_classes,
+        nonces,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_diff() {
+        let mut previous_state = State::default();
+        let (account, token) = (Felt252::from(0x100), Felt252::from(0x200));
+        previous_state.contracts.insert(token, Felt252::ONE);
+        previous_state.set_storage_at(token, Felt252::ONE, Felt252::from(5));
+
+        let mut state = previous_state.clone();
+        state.contracts.insert(account, Felt252::TWO);
+        state.contracts.insert(token, Felt252::THREE);
+        state.set_storage_at(token, Felt252::ONE, Felt252::from(5));
+        state.set_storage_at(token, Felt252::TWO, Felt252::from(7));
+        state.nonces.insert(account, Felt252::ONE);
+
+        let diff = state_diff(&previous_state, &state).unwrap();
+        assert_eq!(
+            diff.storage_diffs,
+            vec![ContractStorageDiff {
+                address: token,
+                storage_entries: vec![StorageEntry {
+                    key: Felt252::TWO,
+                    value: Felt252::from(7),
+                }],
+            }]
+        );
+        assert_eq!(
+            diff.deployed_contracts,
+            vec![DeployedContract {
+                address: account,
+                class_hash: Felt252::TWO,
+            }]
+        );
+        assert_eq!(
+            diff.replaced_classes,
+            vec![ReplacedClass {
+                contract_address: token,
+                class_hash: Felt252::THREE,
+            }]
+        );
+        assert_eq!(
+            diff.nonces,
+            vec![NonceUpdate {
+                contract_address: account,
+                nonce: Felt252::ONE,
+            }]
+        );
+        assert!(diff.declared_classes.is_empty());
+    }
+}
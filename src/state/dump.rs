@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use cairo_lang_starknet_classes::contract_class::ContractClass;
+use cairo_lang_starknet_classes_2_point_6::casm_contract_class::CasmContractClass;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use starknet_types_core::felt::Felt as Felt252;
+
+use super::fork::ForkStateReader;
+use super::{compiled_class_hash, BlockInfo, DeclaredClass, State};
+use crate::cairo_sierra::storage::StorageVariable;
+use crate::compiler::abi::Abi;
+
+/// The first bytes of a gzip stream, telling binary dumps from JSON ones.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// How a state is serialized.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DumpFormat {
+    #[default]
+    Json,
+    /// Gzip compressed JSON.
+    Binary,
+}
+
+/// A declared class as it is dumped.
+///
+/// The debug info is not: it is only built when compiling a contract, so the runs of a loaded
+/// class are not source mapped.
+#[derive(Serialize, Deserialize)]
+struct ClassDump {
+    casm_contract_class: CasmContractClass,
+    abi: Option<Abi>,
+    storage_variables: Vec<StorageVariable>,
+    sierra_contract_class: Option<ContractClass>,
+}
+
+/// The node a dumped state is forked from. What was read from it is fetched again.
+#[derive(Serialize, Deserialize)]
+struct ForkDump {
+    rpc_url: String,
+    block_id: Value,
+}
+
+/// The whole local state, with maps sorted so that dumps of the same state are identical.
+#[derive(Serialize, Deserialize)]
+pub struct StateFile {
+    classes: BTreeMap<Felt252, ClassDump>,
+    contracts: BTreeMap<Felt252, Felt252>,
+    /// Storage values by contract address and storage key.
+    storage: BTreeMap<Felt252, BTreeMap<Felt252, Felt252>>,
+    nonces: BTreeMap<Felt252, Felt252>,
+    block: BlockInfo,
+    fork: Option<ForkDump>,
+}
+
+impl StateFile {
+    pub fn new(state: &State) -> Self {
+        let mut storage: BTreeMap<_, BTreeMap<_, _>> = BTreeMap::new();
+        for (&(contract_address, key), &value) in &state.storage {
+            storage
+                .entry(contract_address)
+                .or_default()
+                .insert(key, value);
+        }
+        Self {
+            classes: state
+                .classes
+                .iter()
+                .map(|(&class_hash, class)| {
+                    let class = ClassDump {
+                        casm_contract_class: class.casm_contract_class.clone(),
+                        abi: class.abi.clone(),
+                        storage_variables: class.storage_variables.clone(),
+                        sierra_contract_class: class.sierra_contract_class.clone(),
+                    };
+                    (class_hash, class)
+                })
+                .collect(),
+            contracts: state.contracts.clone().into_iter().collect(),
+            storage,
+            nonces: state.nonces.clone().into_iter().collect(),
+            block: state.block.clone(),
+            fork: state.fork.as_ref().map(|fork| ForkDump {
+                rpc_url: fork.url().to_string(),
+                block_id: fork.block_id().clone(),
+            }),
+        }
+    }
+
+    pub fn into_state(self) -> Result<State> {
+        let mut state = match self.fork {
+            Some(fork) => State::forked(ForkStateReader::new(
+                &fork.rpc_url,
+                serde_json::from_value(fork.block_id).context("Invalid fork block.")?,
+            )),
+            None => State::default(),
+        };
+        for (class_hash, class) in self.classes {
+            let class = DeclaredClass {
+                class_hash,
+                compiled_class_hash: compiled_class_hash(&class.casm_contract_class),
+                casm_contract_class: class.casm_contract_class,
+                abi: class.abi,
+                debug_info: None,
+                storage_variables: class.storage_variables,
+                sierra_contract_class: class.sierra_contract_class,
+            };
+            state.classes.insert(class_hash, Arc::new(class));
+        }
+        state.contracts = self.contracts.into_iter().collect();
+        for (contract_address, storage) in self.storage {
+            for (key, value) in storage {
+                state.set_storage_at(contract_address, key, value);
+            }
+        }
+        state.nonces = self.nonces.into_iter().collect();
+        state.block = self.block;
+        Ok(state)
+    }
+}
+
+/// Serializes the state in the given format.
+pub fn dump(state: &State, format: DumpFormat) -> Result<Vec<u8>> {
+    let state_file = StateFile::new(state);
+    match format {
+        DumpFormat::Json => Ok(serde_json::to_vec(&state_file)?),
+        DumpFormat::Binary => {
+            let mut encoder = GzEncoder::new(vec![], Compression::default());
+            serde_json::to_writer(&mut encoder, &state_file)?;
+            Ok(encoder.finish()?)
+        }
+    }
+}
+
+/// Reads a state serialized by `dump`, in either format.
+pub fn load(bytes: &[u8]) -> Result<State> {
+    let state_file: StateFile = if bytes.starts_with(&GZIP_MAGIC) {
+        let mut json = vec![];
+        GzDecoder::new(bytes).read_to_end(&mut json)?;
+        serde_json::from_slice(&json)
+    } else {
+        serde_json::from_slice(bytes)
+    }
+    .context("Failed to parse the state dump.")?;
+    state_file.into_state()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::genesis::GenesisConfig;
+
+    #[test]
+    fn test_dump_and_load() {
+        let mut state = State::default();
+        let genesis = GenesisConfig {
+            accounts: 1,
+            ..GenesisConfig::default()
+        };
+        let accounts = genesis.predeploy(&mut state).unwrap();
+        state.nonces.insert(accounts[0].address, Felt252::ONE);
+        state.block.block_number = 42;
+
+        for format in [DumpFormat::Json, DumpFormat::Binary] {
+            let loaded = load(&dump(&state, format).unwrap()).unwrap();
+            assert_eq!(loaded.contracts, state.contracts);
+            assert_eq!(loaded.storage, state.storage);
+            assert_eq!(loaded.nonces, state.nonces);
+            assert_eq!(loaded.block, state.block);
+            assert!(loaded.fork.is_none());
+            let class = loaded.get_class(&accounts[0].class_hash).unwrap();
+            assert_eq!(class.class_hash, accounts[0].class_hash);
+            assert!(class.abi.is_some());
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use cairo_lang_starknet_classes_2_point_6::casm_contract_class::CasmContractClass;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use starknet_types_core::felt::Felt as Felt252;
+
+use super::{compiled_class_hash, DeclaredClass};
+use crate::casm_sierra::cairo_contract::{compile_contract_class_to_casm, ContractClassIgnoreAbi};
+use crate::compiler::abi::Abi;
+use crate::rpc::transaction_hash::sierra_class_hash;
+use crate::rpc::types::SierraClass;
+
+/// The JSON-RPC error codes of missing contracts and classes.
+const CONTRACT_NOT_FOUND: i64 = 20;
+const CLASS_HASH_NOT_FOUND: i64 = 28;
+
+/// The block a fork reads the state of.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForkBlock {
+    BlockNumber(u64),
+    BlockHash(Felt252),
+}
+
+/// Reads the state of a Starknet node at a pinned block through its JSON-RPC API.
+///
+/// Everything fetched is cached: the state of a past block never changes.
+pub struct ForkStateReader {
+    url: String,
+    block_id: Value,
+    storage: Mutex<HashMap<(Felt252, Felt252), Felt252>>,
+    nonces: Mutex<HashMap<Felt252, Felt252>>,
+    class_hashes: Mutex<HashMap<Felt252, Option<Felt252>>>,
+    classes: Mutex<HashMap<Felt252, Option<Arc<DeclaredClass>>>>,
+}
+
+impl ForkStateReader {
+    pub fn new(url: &str, block: ForkBlock) -> Self {
+        Self {
+            url: url.to_string(),
+            block_id: serde_json::to_value(block).unwrap_or_default(),
+            storage: Mutex::new(HashMap::new()),
+            nonces: Mutex::new(HashMap::new()),
+            class_hashes: Mutex::new(HashMap::new()),
+            classes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The URL of the node.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The block the state is read at, as a JSON-RPC block id.
+    pub fn block_id(&self) -> &Value {
+        &self.block_id
+    }
+
+    pub fn get_storage_at(&self, contract_address: Felt252, key: Felt252) -> Result<Felt252> {
+        if let Some(value) = self.storage.lock().unwrap().get(&(contract_address, key)) {
+            return Ok(*value);
+        }
+        let value = match self.request(
+            "starknet_getStorageAt",
+            serde_json::json!([contract_address, key, self.block_id]),
+        )? {
+            Ok(value) => serde_json::from_value(value)?,
+            // Contracts deployed after the fork block have empty storage.
+            Err(CONTRACT_NOT_FOUND) => Felt252::ZERO,
+            Err(code) => anyhow::bail!("starknet_getStorageAt failed with code {}.", code),
+        };
+        self.storage
+            .lock()
+            .unwrap()
+            .insert((contract_address, key), value);
+        Ok(value)
+    }
+
+    pub fn get_nonce_at(&self, contract_address: Felt252) -> Result<Felt252> {
+        if let Some(nonce) = self.nonces.lock().unwrap().get(&contract_address) {
+            return Ok(*nonce);
+        }
+        let nonce = match self.request(
+            "starknet_getNonce",
+            serde_json::json!([self.block_id, contract_address]),
+        )? {
+            Ok(nonce) => serde_json::from_value(nonce)?,
+            Err(CONTRACT_NOT_FOUND) => Felt252::ZERO,
+            Err(code) => anyhow::bail!("starknet_getNonce failed with code {}.", code),
+        };
+        self.nonces.lock().unwrap().insert(contract_address, nonce);
+        Ok(nonce)
+    }
+
+    /// Returns the class hash of a contract, `None` if it is not deployed at the fork block.
+    pub fn get_class_hash_at(&self, contract_address: Felt252) -> Result<Option<Felt252>> {
+        if let Some(class_hash) = self.class_hashes.lock().unwrap().get(&contract_address) {
+            return Ok(*class_hash);
+        }
+        let class_hash = match self.request(
+            "starknet_getClassHashAt",
+            serde_json::json!([self.block_id, contract_address]),
+        )? {
+            Ok(class_hash) => Some(serde_json::from_value(class_hash)?),
+            Err(CONTRACT_NOT_FOUND) => None,
+            Err(code) => anyhow::bail!("starknet_getClassHashAt failed with code {}.", code),
+        };
+        self.class_hashes
+            .lock()
+            .unwrap()
+            .insert(contract_address, class_hash);
+        Ok(class_hash)
+    }
+
+    /// Fetches a Sierra class and compiles it, `None` if it is not declared at the fork block.
+    pub fn get_class(&self, class_hash: Felt252) -> Result<Option<Arc<DeclaredClass>>> {
+        if let Some(class) = self.classes.lock().unwrap().get(&class_hash) {
+            return Ok(class.clone());
+        }
+        let class = match self.request(
+            "starknet_getClass",
+            serde_json::json!([self.block_id, class_hash]),
+        )? {
+            Ok(class) => Some(Arc::new(compile_class(class).with_context(|| {
+                format!("Failed to compile class {}.", class_hash.to_hex_string())
+            })?)),
+            Err(CLASS_HASH_NOT_FOUND) => None,
+            Err(code) => anyhow::bail!("starknet_getClass failed with code {}.", code),
+        };
+        self.classes
+            .lock()
+            .unwrap()
+            .insert(class_hash, class.clone());
+        Ok(class)
+    }
+
+    /// Sends a JSON-RPC request, returning its result or its error code.
+    fn request(&self, method: &str, params: Value) -> Result<Result<Value, i64>> {
+        let response: Value = ureq::post(&self.url)
+            .send_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": method,
+                "params": params,
+            }))
+            .with_context(|| format!("Failed to send {} to {}.", method, self.url))?
+            .into_json()?;
+        if let Some(error) = response.get("error") {
+            return Ok(Err(error["code"].as_i64().unwrap_or_default()));
+        }
+        response
+            .get("result")
+            .cloned()
+            .map(Ok)
+            .with_context(|| format!("Invalid response to {}.", method))
+    }
+}
+
+/// Compiles a class in the format of `starknet_getClass`, whose ABI is a JSON string.
+pub fn compile_class(class: Value) -> Result<DeclaredClass> {
+    let abi: Option<Abi> = class["abi"]
+        .as_str()
+        .and_then(|abi| serde_json::from_str(abi).ok());
+    if class.get("sierra_program").is_none() {
+        anyhow::bail!("Cairo 0 classes are not supported.");
+    }
+    // The class hash is computed from the ABI string as published.
+    let class_hash = sierra_class_hash(&serde_json::from_value::<SierraClass>(class.clone())?);
+    let contract_class: ContractClassIgnoreAbi = serde_json::from_value(class)?;
+    let compiled = compile_contract_class_to_casm(contract_class)?;
+    let casm_contract_class: CasmContractClass =
+        serde_json::from_value(serde_json::to_value(&compiled.casm_contract_class)?)?;
+    Ok(DeclaredClass {
+        class_hash,
+        compiled_class_hash: compiled_class_hash(&casm_contract_class),
+        casm_contract_class,
+        abi,
+        debug_info: None,
+        storage_variables: vec![],
+        sierra_contract_class: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Serves `starknet_getStorageAt` and `starknet_getClassHashAt`, counting the requests.
+    fn mock_node(requests: Arc<AtomicUsize>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let request: Value = serde_json::from_slice(&body).unwrap();
+                requests.fetch_add(1, Ordering::SeqCst);
+
+                let response = match request["method"].as_str().unwrap() {
+                    "starknet_getStorageAt" => serde_json::json!({ "result": "0x2a" }),
+                    _ => {
+                        serde_json::json!({ "error": { "code": 20, "message": "Contract not found" } })
+                    }
+                };
+                let response = serde_json::to_string(&response).unwrap();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.len(),
+                    response
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_fork_reads_are_cached() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let fork = ForkStateReader::new(&mock_node(requests.clone()), ForkBlock::BlockNumber(100));
+
+        let contract_address = Felt252::from(0x123);
+        for _ in 0..2 {
+            assert_eq!(
+                fork.get_storage_at(contract_address, Felt252::ONE).unwrap(),
+                Felt252::from(42)
+            );
+            assert_eq!(fork.get_class_hash_at(contract_address).unwrap(), None);
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+}
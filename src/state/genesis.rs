@@ -0,0 +1,256 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use starknet_types_core::felt::Felt as Felt252;
+use starknet_types_core::hash::{Pedersen, StarkHash};
+
+use super::{DeclaredClass, State};
+use crate::compiler::compile_contract::compile_contract;
+use crate::rpc::revert_reason;
+use crate::rpc::transaction_hash::{calculate_contract_address, public_key};
+use crate::trace::abi_decoder::selector_from_name;
+use crate::trace::cairo_runner::ExecutionStatus;
+use crate::trace::config::{RunConfig, ETH_FEE_TOKEN_ADDRESS, STRK_FEE_TOKEN_ADDRESS};
+use crate::trace::execution::{execute_call, CallEntryPoint, CallType, EntryPointType};
+
+/// An account checking the Stark signature of its public key, with the entrypoints of
+/// OpenZeppelin accounts.
+const ACCOUNT: &str = include_str!("genesis/account.cairo");
+
+/// A minimal ERC20 token, used for the fee tokens.
+const ERC20: &str = include_str!("genesis/erc20.cairo");
+
+/// The accounts and fee tokens predeployed in a fresh state.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct GenesisConfig {
+    /// The number of predeployed accounts.
+    pub accounts: usize,
+    /// Derives the private keys of the accounts, so that they are the same on every start.
+    pub seed: Felt252,
+    /// The ETH and STRK balance of every account, in wei and fri.
+    pub initial_balance: u128,
+    pub eth_fee_token_address: Felt252,
+    pub strk_fee_token_address: Felt252,
+}
+
+impl Default for GenesisConfig {
+    fn default() -> Self {
+        Self {
+            accounts: 10,
+            seed: Felt252::ZERO,
+            // 1000 ETH or STRK.
+            initial_balance: 1_000 * 10_u128.pow(18),
+            eth_fee_token_address: Felt252::from_hex_unchecked(ETH_FEE_TOKEN_ADDRESS),
+            strk_fee_token_address: Felt252::from_hex_unchecked(STRK_FEE_TOKEN_ADDRESS),
+        }
+    }
+}
+
+/// A predeployed account and the keys signing its transactions.
+#[derive(Debug, Serialize)]
+pub struct PredeployedAccount {
+    pub address: Felt252,
+    pub private_key: Felt252,
+    pub public_key: Felt252,
+    pub class_hash: Felt252,
+}
+
+impl GenesisConfig {
+    /// Reads a genesis config from a JSON file, defaulting missing fields.
+    pub fn load(path: &Path) -> Result<Self> {
+        let config = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}.", path.display()))?;
+        serde_json::from_str(&config)
+            .with_context(|| format!("Failed to parse genesis config {}.", path.display()))
+    }
+
+    /// The private key of the account at `index`, the low 128 bits of a hash of the seed, which
+    /// are a valid Stark private key.
+    ///
+    /// Anyone knowing the seed can derive the keys, and they only have 128 bits of entropy: they
+    /// are only meant for testing and must never hold real funds.
+    pub fn private_key(&self, index: usize) -> Felt252 {
+        let hash = Pedersen::hash(&self.seed, &Felt252::from(index)).to_bytes_be();
+        Felt252::from_bytes_be_slice(&hash[16..])
+    }
+
+    /// Declares the account and token classes, deploys the accounts and the fee tokens with their
+    /// constructors, the fee tokens funding the accounts.
+    pub fn predeploy(&self, state: &mut State) -> Result<Vec<PredeployedAccount>> {
+        let account_class_hash = declare(state, ACCOUNT, "account")?;
+        let erc20_class_hash = declare(state, ERC20, "erc20")?;
+
+        let accounts = (0..self.accounts)
+            .map(|index| {
+                let private_key = self.private_key(index);
+                let public_key = public_key(private_key);
+                PredeployedAccount {
+                    address: calculate_contract_address(
+                        public_key,
+                        account_class_hash,
+                        &[public_key],
+                        Felt252::ZERO,
+                    ),
+                    private_key,
+                    public_key,
+                    class_hash: account_class_hash,
+                }
+            })
+            .collect::<Vec<_>>();
+        for account in &accounts {
+            deploy(
+                state,
+                account.address,
+                account.class_hash,
+                vec![account.public_key],
+            )?;
+        }
+
+        for (address, name, symbol) in [
+            (self.eth_fee_token_address, "Ether", "ETH"),
+            (self.strk_fee_token_address, "Starknet Token", "STRK"),
+        ] {
+            let mut calldata = vec![
+                Felt252::from_bytes_be_slice(name.as_bytes()),
+                Felt252::from_bytes_be_slice(symbol.as_bytes()),
+                Felt252::from(18),
+                // The initial balance as a u256.
+                Felt252::from(self.initial_balance),
+                Felt252::ZERO,
+                Felt252::from(accounts.len()),
+            ];
+            calldata.extend(accounts.iter().map(|account| account.address));
+            deploy(state, address, erc20_class_hash, calldata)?;
+        }
+        Ok(accounts)
+    }
+}
+
+/// Deploys a contract at an address and runs its constructor.
+fn deploy(
+    state: &mut State,
+    address: Felt252,
+    class_hash: Felt252,
+    calldata: Vec<Felt252>,
+) -> Result<()> {
+    state.deploy_contract(address, class_hash)?;
+    let config = RunConfig::default();
+    let call = CallEntryPoint {
+        call_type: CallType::Call,
+        caller_address: Felt252::ZERO,
+        contract_address: address,
+        class_hash: Some(class_hash),
+        entry_point_type: EntryPointType::Constructor,
+        entry_point_selector: selector_from_name("constructor"),
+        calldata,
+        initial_gas: config.initial_gas,
+    };
+    let call_info = execute_call(state, &mut 0, &call, &config)?;
+    if call_info.status != ExecutionStatus::Succeeded {
+        anyhow::bail!(
+            "The constructor of {} failed: {}.",
+            address.to_hex_string(),
+            revert_reason(&call_info)
+        );
+    }
+    Ok(())
+}
+
+/// Compiles and declares a contract, returning its class hash.
+fn declare(state: &mut State, code: &str, name: &str) -> Result<Felt252> {
+    let compilation_result = compile_contract(code, name)?;
+    Ok(state.declare_class(DeclaredClass::from_compilation_result(compilation_result)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::invoke::{execute_invoke, multicall_calldata};
+    use crate::rpc::transaction_hash::{invoke_transaction_hash, sign};
+    use crate::rpc::types::{
+        BroadcastedInvokeTransaction, FunctionCall, TransactionExecutionStatus,
+    };
+
+    fn call(
+        state: &mut State,
+        contract_address: Felt252,
+        entry_point: &str,
+        calldata: Vec<Felt252>,
+    ) -> Vec<Felt252> {
+        let call = CallEntryPoint {
+            call_type: CallType::Call,
+            caller_address: Felt252::ZERO,
+            contract_address,
+            class_hash: None,
+            entry_point_type: EntryPointType::External,
+            entry_point_selector: selector_from_name(entry_point),
+            calldata,
+            initial_gas: RunConfig::default().initial_gas,
+        };
+        execute_call(state, &mut 0, &call, &RunConfig::default())
+            .unwrap()
+            .retdata
+    }
+
+    #[test]
+    fn test_predeploy() {
+        let genesis = GenesisConfig {
+            accounts: 2,
+            ..GenesisConfig::default()
+        };
+        let mut state = State::default();
+        let accounts = genesis.predeploy(&mut state).unwrap();
+        assert_eq!(accounts.len(), 2);
+        assert_ne!(accounts[0].address, accounts[1].address);
+        assert_eq!(
+            accounts[0].private_key,
+            GenesisConfig::default().private_key(0)
+        );
+
+        let token = genesis.eth_fee_token_address;
+        let initial_balance = Felt252::from(genesis.initial_balance);
+        assert_eq!(
+            call(&mut state, token, "balance_of", vec![accounts[0].address]),
+            vec![initial_balance, Felt252::ZERO]
+        );
+        assert_eq!(
+            call(&mut state, token, "total_supply", vec![]),
+            vec![initial_balance * Felt252::TWO, Felt252::ZERO]
+        );
+        assert_eq!(
+            call(&mut state, accounts[0].address, "get_public_key", vec![]),
+            vec![accounts[0].public_key]
+        );
+
+        // The accounts only validate transactions signed with their key.
+        let config = RunConfig::default();
+        let (sender, recipient) = (&accounts[0], &accounts[1]);
+        let mut transaction = BroadcastedInvokeTransaction {
+            sender_address: sender.address,
+            calldata: multicall_calldata(&[FunctionCall {
+                contract_address: token,
+                entry_point_selector: selector_from_name("transfer"),
+                calldata: vec![recipient.address, Felt252::from(100), Felt252::ZERO],
+            }]),
+            version: Felt252::ONE,
+            ..Default::default()
+        };
+        let hash =
+            invoke_transaction_hash(&transaction, config.execution_context.chain_id).unwrap();
+        transaction.signature = sign(recipient.private_key, hash).unwrap();
+        assert!(execute_invoke(&mut state, &config, &transaction).is_err());
+
+        transaction.signature = sign(sender.private_key, hash).unwrap();
+        let execution = execute_invoke(&mut state, &config, &transaction).unwrap();
+        assert_eq!(
+            execution.execution_status(),
+            TransactionExecutionStatus::Succeeded
+        );
+        assert_eq!(
+            call(&mut state, token, "balance_of", vec![recipient.address]),
+            vec![initial_balance + Felt252::from(100), Felt252::ZERO]
+        );
+    }
+}
@@ -0,0 +1,90 @@
+#[starknet::contract]
+mod Account {
+    use core::ecdsa::check_ecdsa_signature;
+    use starknet::{SyscallResultTrait, get_caller_address, get_tx_info};
+    use starknet::account::Call;
+    use starknet::syscalls::call_contract_syscall;
+
+    #[storage]
+    struct Storage {
+        public_key: felt252,
+    }
+
+    #[constructor]
+    fn constructor(ref self: ContractState, public_key: felt252) {
+        self.public_key.write(public_key);
+    }
+
+    #[external(v0)]
+    fn __validate__(self: @ContractState, calls: Array<Call>) -> felt252 {
+        self.validate_transaction()
+    }
+
+    #[external(v0)]
+    fn __validate_declare__(self: @ContractState, class_hash: felt252) -> felt252 {
+        self.validate_transaction()
+    }
+
+    #[external(v0)]
+    fn __validate_deploy__(
+        self: @ContractState, class_hash: felt252, contract_address_salt: felt252, public_key: felt252
+    ) -> felt252 {
+        self.validate_transaction()
+    }
+
+    #[external(v0)]
+    fn __execute__(ref self: ContractState, mut calls: Array<Call>) -> Array<Span<felt252>> {
+        let caller: felt252 = get_caller_address().into();
+        assert(caller == 0, 'Account: invalid caller');
+        let mut results = array![];
+        loop {
+            match calls.pop_front() {
+                Option::Some(call) => {
+                    results.append(
+                        call_contract_syscall(call.to, call.selector, call.calldata)
+                            .unwrap_syscall()
+                    );
+                },
+                Option::None => { break; },
+            };
+        };
+        results
+    }
+
+    #[external(v0)]
+    fn is_valid_signature(
+        self: @ContractState, hash: felt252, signature: Array<felt252>
+    ) -> felt252 {
+        if self.is_valid_signature_span(hash, signature.span()) {
+            'VALID'
+        } else {
+            0
+        }
+    }
+
+    #[external(v0)]
+    fn get_public_key(self: @ContractState) -> felt252 {
+        self.public_key.read()
+    }
+
+    #[generate_trait]
+    impl InternalImpl of InternalTrait {
+        fn validate_transaction(self: @ContractState) -> felt252 {
+            let tx_info = get_tx_info().unbox();
+            assert(
+                self.is_valid_signature_span(tx_info.transaction_hash, tx_info.signature),
+                'Account: invalid signature'
+            );
+            'VALID'
+        }
+
+        fn is_valid_signature_span(
+            self: @ContractState, hash: felt252, signature: Span<felt252>
+        ) -> bool {
+            if signature.len() != 2 {
+                return false;
+            }
+            check_ecdsa_signature(hash, self.public_key.read(), *signature.at(0), *signature.at(1))
+        }
+    }
+}
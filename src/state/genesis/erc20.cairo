@@ -0,0 +1,138 @@
+#[starknet::contract]
+mod ERC20 {
+    use starknet::{ContractAddress, contract_address_const, get_caller_address};
+
+    #[storage]
+    struct Storage {
+        name: felt252,
+        symbol: felt252,
+        decimals: u8,
+        total_supply: u256,
+        // Named like in the fee tokens of Starknet, whose balance keys the fee estimates count.
+        ERC20_balances: LegacyMap<ContractAddress, u256>,
+        allowances: LegacyMap<(ContractAddress, ContractAddress), u256>,
+    }
+
+    #[event]
+    #[derive(Drop, starknet::Event)]
+    enum Event {
+        Transfer: Transfer,
+        Approval: Approval,
+    }
+
+    #[derive(Drop, starknet::Event)]
+    struct Transfer {
+        #[key]
+        from: ContractAddress,
+        #[key]
+        to: ContractAddress,
+        value: u256,
+    }
+
+    #[derive(Drop, starknet::Event)]
+    struct Approval {
+        #[key]
+        owner: ContractAddress,
+        #[key]
+        spender: ContractAddress,
+        value: u256,
+    }
+
+    /// Mints `initial_balance` to every recipient.
+    #[constructor]
+    fn constructor(
+        ref self: ContractState,
+        name: felt252,
+        symbol: felt252,
+        decimals: u8,
+        initial_balance: u256,
+        mut recipients: Array<ContractAddress>,
+    ) {
+        self.name.write(name);
+        self.symbol.write(symbol);
+        self.decimals.write(decimals);
+        let zero = contract_address_const::<0>();
+        let mut total_supply: u256 = 0;
+        loop {
+            match recipients.pop_front() {
+                Option::Some(recipient) => {
+                    self.ERC20_balances.write(recipient, initial_balance);
+                    total_supply += initial_balance;
+                    self.emit(Transfer { from: zero, to: recipient, value: initial_balance });
+                },
+                Option::None => { break; },
+            };
+        };
+        self.total_supply.write(total_supply);
+    }
+
+    #[external(v0)]
+    fn name(self: @ContractState) -> felt252 {
+        self.name.read()
+    }
+
+    #[external(v0)]
+    fn symbol(self: @ContractState) -> felt252 {
+        self.symbol.read()
+    }
+
+    #[external(v0)]
+    fn decimals(self: @ContractState) -> u8 {
+        self.decimals.read()
+    }
+
+    #[external(v0)]
+    fn total_supply(self: @ContractState) -> u256 {
+        self.total_supply.read()
+    }
+
+    #[external(v0)]
+    fn balance_of(self: @ContractState, account: ContractAddress) -> u256 {
+        self.ERC20_balances.read(account)
+    }
+
+    #[external(v0)]
+    fn allowance(self: @ContractState, owner: ContractAddress, spender: ContractAddress) -> u256 {
+        self.allowances.read((owner, spender))
+    }
+
+    #[external(v0)]
+    fn transfer(ref self: ContractState, recipient: ContractAddress, amount: u256) -> bool {
+        let sender = get_caller_address();
+        self.transfer_helper(sender, recipient, amount);
+        true
+    }
+
+    #[external(v0)]
+    fn transfer_from(
+        ref self: ContractState, sender: ContractAddress, recipient: ContractAddress, amount: u256
+    ) -> bool {
+        let caller = get_caller_address();
+        let allowance = self.allowances.read((sender, caller));
+        assert(allowance >= amount, 'ERC20: insufficient allowance');
+        self.allowances.write((sender, caller), allowance - amount);
+        self.transfer_helper(sender, recipient, amount);
+        true
+    }
+
+    #[external(v0)]
+    fn approve(ref self: ContractState, spender: ContractAddress, amount: u256) -> bool {
+        let owner = get_caller_address();
+        self.allowances.write((owner, spender), amount);
+        self.emit(Approval { owner, spender, value: amount });
+        true
+    }
+
+    #[generate_trait]
+    impl InternalImpl of InternalTrait {
+        fn transfer_helper(
+            ref self: ContractState, sender: ContractAddress, recipient: ContractAddress, amount: u256
+        ) {
+            let balance = self.ERC20_balances.read(sender);
+            assert(balance >= amount, 'ERC20: insufficient balance');
+            self.ERC20_balances.write(sender, balance - amount);
+            self.ERC20_balances.write(recipient, self.ERC20_balances.read(recipient) + amount);
+            self.emit(Transfer { from: sender, to: recipient, value: amount });
+        }
+    }
+}
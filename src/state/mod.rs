@@ -0,0 +1,455 @@
+pub mod diff;
+pub mod dump;
+pub mod fork;
+pub mod genesis;
+pub mod snapshot;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use cairo_lang_starknet_classes::contract_class::ContractClass;
+use cairo_lang_starknet_classes_2_point_6::casm_contract_class::CasmContractClass;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use starknet_types_core::felt::Felt as Felt252;
+
+use crate::cairo_sierra::storage::StorageVariable;
+use crate::compiler::abi::{contract_abi, Abi};
+use crate::compiler::compile_contract::ContractCompilationResult;
+use crate::rpc::rpc_class;
+use crate::rpc::transaction_hash::sierra_class_hash;
+use crate::rpc::types::SierraClass;
+use crate::trace::config::{ExecutionContext, RunConfig};
+use crate::trace::sierra_trace::SierraDebugInfo;
+use fork::ForkStateReader;
+
+/// A declared contract class, with the information needed to decode and source map its runs.
+pub struct DeclaredClass {
+    /// The hash of the Sierra class, or the compiled class hash if the Sierra class is unknown.
+    pub class_hash: Felt252,
+    pub compiled_class_hash: Felt252,
+    pub casm_contract_class: CasmContractClass,
+    pub abi: Option<Abi>,
+    pub debug_info: Option<SierraDebugInfo>,
+    /// The storage layout of the class, to name the storage keys it accesses.
+    pub storage_variables: Vec<StorageVariable>,
+    /// The Sierra class the CASM class was compiled from, as returned by `starknet_getClass`.
+    pub sierra_contract_class: Option<ContractClass>,
+}
+
+impl DeclaredClass {
+    pub fn new(
+        casm_contract_class: CasmContractClass,
+        compilation_result: Option<ContractCompilationResult>,
+    ) -> Result<Self> {
+        let abi = compilation_result.as_ref().map(contract_abi).transpose()?;
+        let debug_info = compilation_result
+            .as_ref()
+            .map(SierraDebugInfo::from_contract)
+            .transpose()?;
+        let (storage_variables, sierra_contract_class) = match compilation_result {
+            Some(compilation_result) => (
+                compilation_result.cairo_sierra.storage_layout,
+                Some(compilation_result.cairo_sierra.sierra_contract_class),
+            ),
+            None => (vec![], None),
+        };
+        let compiled_class_hash = compiled_class_hash(&casm_contract_class);
+        let class_hash = match &sierra_contract_class {
+            Some(sierra_contract_class) => {
+                let sierra_class: SierraClass =
+                    serde_json::from_value(rpc_class(sierra_contract_class))?;
+                sierra_class_hash(&sierra_class)
+            }
+            None => compiled_class_hash,
+        };
+        Ok(Self {
+            class_hash,
+            compiled_class_hash,
+            casm_contract_class,
+            abi,
+            debug_info,
+            storage_variables,
+            sierra_contract_class,
+        })
+    }
+
+    /// The class compiled from Cairo code, with the CASM class of the compilation result.
+    pub fn from_compilation_result(compilation_result: ContractCompilationResult) -> Result<Self> {
+        let casm_contract_class: CasmContractClass = serde_json::from_value(serde_json::to_value(
+            &compilation_result.casm_sierra.casm_contract_class,
+        )?)?;
+        Self::new(casm_contract_class, Some(compilation_result))
+    }
+}
+
+/// The hash of a CASM class, declared along with the hash of its Sierra class.
+pub fn compiled_class_hash(casm_contract_class: &CasmContractClass) -> Felt252 {
+    let hash = casm_contract_class.compiled_class_hash();
+    Felt252::from_bytes_be_slice(&hash.to_bytes_be())
+}
+
+/// The block the state is at, executing the JSON-RPC requests.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlockInfo {
+    pub block_number: u64,
+    pub block_timestamp: u64,
+    pub sequencer_address: Felt252,
+    /// The prices of L1 gas and L1 blob gas, in wei.
+    pub gas_price: u128,
+    pub data_gas_price: u128,
+    /// The prices of L1 gas and L1 blob gas, in fri.
+    pub strk_gas_price: u128,
+    pub strk_data_gas_price: u128,
+}
+
+impl Default for BlockInfo {
+    fn default() -> Self {
+        let context = ExecutionContext::default();
+        Self {
+            block_number: context.block_number,
+            block_timestamp: context.block_timestamp,
+            sequencer_address: context.sequencer_address,
+            gas_price: context.gas_price,
+            data_gas_price: context.data_gas_price,
+            strk_gas_price: context.strk_gas_price,
+            strk_data_gas_price: context.strk_data_gas_price,
+        }
+    }
+}
+
+impl BlockInfo {
+    /// The default run config, executing in this block.
+    pub fn run_config(&self) -> RunConfig {
+        let mut config = RunConfig::default();
+        self.fill_config(&mut config, &Value::Null);
+        config
+    }
+
+    /// Executes the config of a request in this block, except for the block fields the request
+    /// sets, given as the JSON of its config.
+    pub fn fill_config(&self, config: &mut RunConfig, request_config: &Value) {
+        let is_set = |field: &str| request_config["execution_context"].get(field).is_some();
+        let context = &mut config.execution_context;
+        if !is_set("block_number") {
+            context.block_number = self.block_number;
+        }
+        if !is_set("block_timestamp") {
+            context.block_timestamp = self.block_timestamp;
+        }
+        if !is_set("sequencer_address") {
+            context.sequencer_address = self.sequencer_address;
+        }
+        if !is_set("gas_price") {
+            context.gas_price = self.gas_price;
+        }
+        if !is_set("data_gas_price") {
+            context.data_gas_price = self.data_gas_price;
+        }
+        if !is_set("strk_gas_price") {
+            context.strk_gas_price = self.strk_gas_price;
+        }
+        if !is_set("strk_data_gas_price") {
+            context.strk_data_gas_price = self.strk_data_gas_price;
+        }
+    }
+}
+
+/// A write to the state, with the value it replaced.
+#[derive(Clone)]
+enum JournalEntry {
+    Contract {
+        contract_address: Felt252,
+        previous_class_hash: Option<Felt252>,
+    },
+    Storage {
+        contract_address: Felt252,
+        key: Felt252,
+        previous_value: Option<Felt252>,
+    },
+}
+
+/// The in-process Starknet state contracts are executed against.
+///
+/// The writes made while a checkpoint is open are journaled, so that a call that reverts undoes
+/// only its own writes.
+#[derive(Clone, Default)]
+pub struct State {
+    pub classes: HashMap<Felt252, Arc<DeclaredClass>>,
+    /// The class hash of every deployed contract, by address.
+    pub contracts: HashMap<Felt252, Felt252>,
+    /// Storage values by contract address and storage key.
+    pub storage: HashMap<(Felt252, Felt252), Felt252>,
+    pub nonces: HashMap<Felt252, Felt252>,
+    pub block: BlockInfo,
+    /// The node the state is forked from, read for everything not set locally.
+    pub fork: Option<Arc<ForkStateReader>>,
+    /// The writes made since the outermost open checkpoint.
+    journal: Vec<JournalEntry>,
+    open_checkpoints: usize,
+}
+
+impl State {
+    /// An empty state on top of the state of a node.
+    pub fn forked(fork: ForkStateReader) -> Self {
+        Self {
+            fork: Some(Arc::new(fork)),
+            ..Self::default()
+        }
+    }
+
+    /// Declares a class, returning its class hash.
+    pub fn declare_class(&mut self, class: DeclaredClass) -> Felt252 {
+        let class_hash = class.class_hash;
+        self.classes.insert(class_hash, Arc::new(class));
+        class_hash
+    }
+
+    /// Deploys a contract of a declared class at the given address, without running a constructor.
+    pub fn deploy_contract(
+        &mut self,
+        contract_address: Felt252,
+        class_hash: Felt252,
+    ) -> Result<()> {
+        self.get_class(&class_hash)?;
+        if self.get_class_hash_at(&contract_address).is_ok() {
+            anyhow::bail!(
+                "A contract is already deployed at {}.",
+                contract_address.to_hex_string()
+            );
+        }
+        self.set_class_hash_at(contract_address, class_hash);
+        Ok(())
+    }
+
+    /// Replaces the class of a deployed contract with a declared class.
+    pub fn replace_class(&mut self, contract_address: Felt252, class_hash: Felt252) -> Result<()> {
+        self.get_class(&class_hash)?;
+        self.get_class_hash_at(&contract_address)?;
+        self.set_class_hash_at(contract_address, class_hash);
+        Ok(())
+    }
+
+    fn set_class_hash_at(&mut self, contract_address: Felt252, class_hash: Felt252) {
+        let previous_class_hash = self.contracts.insert(contract_address, class_hash);
+        if self.open_checkpoints > 0 {
+            self.journal.push(JournalEntry::Contract {
+                contract_address,
+                previous_class_hash,
+            });
+        }
+    }
+
+    pub fn get_class(&self, class_hash: &Felt252) -> Result<Arc<DeclaredClass>> {
+        if let Some(class) = self.classes.get(class_hash) {
+            return Ok(class.clone());
+        }
+        let class = match &self.fork {
+            Some(fork) => fork.get_class(*class_hash)?,
+            None => None,
+        };
+        class.with_context(|| format!("Class {} is not declared.", class_hash.to_hex_string()))
+    }
+
+    pub fn get_class_hash_at(&self, contract_address: &Felt252) -> Result<Felt252> {
+        if let Some(class_hash) = self.contracts.get(contract_address) {
+            return Ok(*class_hash);
+        }
+        let class_hash = match &self.fork {
+            Some(fork) => fork.get_class_hash_at(*contract_address)?,
+            None => None,
+        };
+        class_hash.with_context(|| {
+            format!(
+                "No contract is deployed at {}.",
+                contract_address.to_hex_string()
+            )
+        })
+    }
+
+    pub fn get_storage_at(&self, contract_address: Felt252, key: Felt252) -> Result<Felt252> {
+        if let Some(value) = self.storage.get(&(contract_address, key)) {
+            return Ok(*value);
+        }
+        match &self.fork {
+            Some(fork) => fork.get_storage_at(contract_address, key),
+            None => Ok(Felt252::ZERO),
+        }
+    }
+
+    pub fn set_storage_at(&mut self, contract_address: Felt252, key: Felt252, value: Felt252) {
+        let previous_value = self.storage.insert((contract_address, key), value);
+        if self.open_checkpoints > 0 {
+            self.journal.push(JournalEntry::Storage {
+                contract_address,
+                key,
+                previous_value,
+            });
+        }
+    }
+
+    /// Opens a checkpoint the writes made from now on can be reverted to.
+    ///
+    /// Every checkpoint must be closed by `commit` or `revert`, innermost first.
+    pub fn checkpoint(&mut self) -> usize {
+        self.open_checkpoints += 1;
+        self.journal.len()
+    }
+
+    /// Keeps the writes made since the innermost open checkpoint.
+    pub fn commit(&mut self) {
+        self.close_checkpoint();
+    }
+
+    /// Undoes the writes made since the checkpoint.
+    pub fn revert(&mut self, checkpoint: usize) {
+        for entry in self.journal.drain(checkpoint..).rev() {
+            match entry {
+                JournalEntry::Contract {
+                    contract_address,
+                    previous_class_hash,
+                } => match previous_class_hash {
+                    Some(class_hash) => self.contracts.insert(contract_address, class_hash),
+                    None => self.contracts.remove(&contract_address),
+                },
+                JournalEntry::Storage {
+                    contract_address,
+                    key,
+                    previous_value,
+                } => match previous_value {
+                    Some(value) => self.storage.insert((contract_address, key), value),
+                    None => self.storage.remove(&(contract_address, key)),
+                },
+            };
+        }
+        self.close_checkpoint();
+    }
+
+    fn close_checkpoint(&mut self) {
+        self.open_checkpoints -= 1;
+        // Nothing can be reverted once the outermost checkpoint is closed.
+        if self.open_checkpoints == 0 {
+            self.journal.clear();
+        }
+    }
+
+    pub fn get_nonce_at(&self, contract_address: Felt252) -> Result<Felt252> {
+        if let Some(nonce) = self.nonces.get(&contract_address) {
+            return Ok(*nonce);
+        }
+        match &self.fork {
+            Some(fork) => fork.get_nonce_at(contract_address),
+            None => Ok(Felt252::ZERO),
+        }
+    }
+}
+
+#[cfg(test)]
+impl State {
+    /// Compiles and declares a contract, returning its class hash.
+    pub fn declare_code(&mut self, code: &str) -> Felt252 {
+        let compilation_result =
+            crate::compiler::compile_contract::compile_contract(code, "contract").unwrap();
+        self.declare_class(DeclaredClass::from_compilation_result(compilation_result).unwrap())
+    }
+
+    /// Compiles, declares and deploys a contract at the given address, returning its class hash.
+    pub fn deploy_code(&mut self, code: &str, contract_address: Felt252) -> Felt252 {
+        let class_hash = self.declare_code(code);
+        self.deploy_contract(contract_address, class_hash).unwrap();
+        class_hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::compile_contract::compile_contract;
+
+    #[test]
+    fn test_storage_defaults_to_zero() {
+        let mut state = State::default();
+        let address = Felt252::from(1);
+        assert_eq!(
+            state.get_storage_at(address, Felt252::from(2)).unwrap(),
+            Felt252::ZERO
+        );
+        state.set_storage_at(address, Felt252::from(2), Felt252::from(3));
+        assert_eq!(
+            state.get_storage_at(address, Felt252::from(2)).unwrap(),
+            Felt252::from(3)
+        );
+        assert!(state.deploy_contract(address, Felt252::from(4)).is_err());
+    }
+
+    #[test]
+    fn test_revert_to_checkpoint() {
+        let mut state = State::default();
+        let (address, key) = (Felt252::from(1), Felt252::from(2));
+        state.set_storage_at(address, key, Felt252::from(3));
+
+        let outer = state.checkpoint();
+        state.set_storage_at(address, key, Felt252::from(4));
+        let inner = state.checkpoint();
+        state.set_storage_at(address, Felt252::from(5), Felt252::from(6));
+        state.set_class_hash_at(Felt252::from(8), Felt252::from(9));
+        state.revert(inner);
+        assert_eq!(state.storage.len(), 1);
+        assert_eq!(
+            state.get_storage_at(address, key).unwrap(),
+            Felt252::from(4)
+        );
+        assert!(state.get_class_hash_at(&Felt252::from(8)).is_err());
+
+        state.revert(outer);
+        assert_eq!(
+            state.get_storage_at(address, key).unwrap(),
+            Felt252::from(3)
+        );
+        assert!(state.journal.is_empty());
+    }
+
+    #[test]
+    fn test_fill_config() {
+        let block = BlockInfo {
+            block_number: 42,
+            gas_price: 7,
+            ..BlockInfo::default()
+        };
+        let request_config = serde_json::json!({ "execution_context": { "block_number": 1 } });
+        let mut config: RunConfig = serde_json::from_value(request_config.clone()).unwrap();
+        block.fill_config(&mut config, &request_config);
+        assert_eq!(config.execution_context.block_number, 1);
+        assert_eq!(config.execution_context.gas_price, 7);
+        assert_eq!(block.run_config().execution_context.block_number, 42);
+    }
+
+    #[test]
+    fn test_class_hash() {
+        let code = r#"#[starknet::contract]
+        mod Empty {
+            #[storage]
+            struct Storage {}
+        }
+        "#;
+        let compilation_result = compile_contract(code, "empty").unwrap();
+        let sierra_class: SierraClass = serde_json::from_value(rpc_class(
+            &compilation_result.cairo_sierra.sierra_contract_class,
+        ))
+        .unwrap();
+
+        let class = DeclaredClass::from_compilation_result(compilation_result).unwrap();
+        assert_eq!(class.class_hash, sierra_class_hash(&sierra_class));
+        assert_eq!(
+            class.compiled_class_hash,
+            compiled_class_hash(&class.casm_contract_class)
+        );
+        assert_ne!(class.class_hash, class.compiled_class_hash);
+
+        // Without its Sierra class, a class is identified by its compiled class hash.
+        let class = DeclaredClass::new(class.casm_contract_class.clone(), None).unwrap();
+        assert_eq!(class.class_hash, class.compiled_class_hash);
+    }
+}
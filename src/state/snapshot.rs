@@ -0,0 +1,60 @@
+use anyhow::{Context, Result};
+
+use super::State;
+
+/// Copies of the state to return to between the steps of a scenario.
+#[derive(Default)]
+pub struct Snapshots {
+    states: Vec<State>,
+}
+
+impl Snapshots {
+    /// Saves a copy of the state, returning the id to revert to it.
+    pub fn take(&mut self, state: &State) -> usize {
+        self.states.push(state.clone());
+        self.states.len() - 1
+    }
+
+    /// The state saved under `id`.
+    ///
+    /// The snapshots taken after it are dropped, but not the snapshot itself, which can be
+    /// reverted to again.
+    pub fn revert(&mut self, id: usize) -> Result<State> {
+        let state = self
+            .states
+            .get(id)
+            .cloned()
+            .with_context(|| format!("Snapshot {} does not exist.", id))?;
+        self.states.truncate(id + 1);
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet_types_core::felt::Felt as Felt252;
+
+    use super::*;
+
+    #[test]
+    fn test_revert() {
+        let mut snapshots = Snapshots::default();
+        let mut state = State::default();
+        let address = Felt252::from(0x100);
+        let first = snapshots.take(&state);
+        state.set_storage_at(address, Felt252::ONE, Felt252::TWO);
+        let second = snapshots.take(&state);
+        state.set_storage_at(address, Felt252::ONE, Felt252::THREE);
+
+        let reverted = snapshots.revert(second).unwrap();
+        assert_eq!(
+            reverted.get_storage_at(address, Felt252::ONE).unwrap(),
+            Felt252::TWO
+        );
+
+        let reverted = snapshots.revert(first).unwrap();
+        assert!(reverted.storage.is_empty());
+        assert!(snapshots.revert(second).is_err());
+        assert!(snapshots.revert(first).is_ok());
+    }
+}
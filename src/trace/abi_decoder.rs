@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+
+use cairo_lang_starknet_classes::keccak::starknet_keccak;
+use num_bigint::BigUint;
+use serde_json::{Map, Value};
+use starknet_types_core::felt::Felt as Felt252;
+
+use super::hint_processor::read_byte_array;
+use crate::compiler::abi::{Abi, AbiEntry, AbiEnum, AbiFunction, AbiStruct};
+
+/// Reads the felts of a serialized value one at a time.
+#[derive(Clone, Copy)]
+pub struct FeltReader<'a> {
+    values: &'a [Felt252],
+    position: usize,
+}
+
+impl<'a> FeltReader<'a> {
+    pub fn new(values: &'a [Felt252]) -> Self {
+        Self {
+            values,
+            position: 0,
+        }
+    }
+
+    pub fn peek(&self) -> Option<Felt252> {
+        self.values.get(self.position).copied()
+    }
+
+    pub fn remaining(&self) -> &'a [Felt252] {
+        &self.values[self.position..]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position == self.values.len()
+    }
+}
+
+impl Iterator for FeltReader<'_> {
+    type Item = Felt252;
+
+    fn next(&mut self) -> Option<Felt252> {
+        let value = self.peek()?;
+        self.position += 1;
+        Some(value)
+    }
+}
+
+/// Decodes serialized values, calldata and return data against the types of an ABI.
+pub struct AbiDecoder<'a> {
+    structs: HashMap<&'a str, &'a AbiStruct>,
+    enums: HashMap<&'a str, &'a AbiEnum>,
+    functions: HashMap<Felt252, &'a AbiFunction>,
+}
+
+impl<'a> AbiDecoder<'a> {
+    pub fn new(abi: &'a Abi) -> Self {
+        let mut decoder = Self {
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            functions: HashMap::new(),
+        };
+        decoder.add_entries(abi);
+        decoder
+    }
+
+    fn add_entries(&mut self, entries: &'a [AbiEntry]) {
+        for entry in entries {
+            match entry {
+                AbiEntry::Struct(item) => {
+                    self.structs.insert(item.name.as_str(), item);
+                }
+                AbiEntry::Enum(item) => {
+                    self.enums.insert(item.name.as_str(), item);
+                }
+                AbiEntry::Function(function) | AbiEntry::L1Handler(function) => {
+                    self.functions
+                        .insert(selector_from_name(&function.name), function);
+                }
+                AbiEntry::Interface(interface) => self.add_entries(&interface.items),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn function_name(&self, selector: &Felt252) -> Option<String> {
+        self.functions
+            .get(selector)
+            .map(|function| function.name.clone())
+    }
+
+    /// Decodes the calldata of the function with the given selector into its named inputs.
+    pub fn decode_calldata(&self, selector: &Felt252, calldata: &[Felt252]) -> Option<Value> {
+        let function = self.functions.get(selector)?;
+        let mut reader = FeltReader::new(calldata);
+        let mut inputs = Map::new();
+        for input in &function.inputs {
+            inputs.insert(
+                input.name.clone(),
+                self.decode_value(&input.ty, &mut reader)?,
+            );
+        }
+        reader.is_empty().then_some(Value::Object(inputs))
+    }
+
+    /// Decodes the return data of the function with the given selector.
+    pub fn decode_result(&self, selector: &Felt252, retdata: &[Felt252]) -> Option<Value> {
+        let function = self.functions.get(selector)?;
+        let mut reader = FeltReader::new(retdata);
+        let outputs = function
+            .outputs
+            .iter()
+            .map(|output| self.decode_value(&output.ty, &mut reader))
+            .collect::<Option<_>>()?;
+        reader.is_empty().then_some(Value::Array(outputs))
+    }
+
+    /// Decodes a value of the given Cairo type.
+    pub fn decode_value(&self, ty: &str, reader: &mut FeltReader) -> Option<Value> {
+        match ty {
+            "()" => return Some(Value::Null),
+            "core::bool" => return Some(Value::Bool(reader.next()? != Felt252::ZERO)),
+            "core::integer::u256" => {
+                let low = reader.next()?.to_biguint();
+                let high = reader.next()?.to_biguint();
+                return Some(Value::String(((high << 128_u32) + low).to_string()));
+            }
+            "core::byte_array::ByteArray" => {
+                let (string, len) = read_byte_array(reader.remaining())?;
+                reader.position += len;
+                return Some(Value::String(string));
+            }
+            _ => {}
+        }
+
+        if let Some(inner) = ty
+            .strip_prefix("core::array::Array::<")
+            .or_else(|| ty.strip_prefix("core::array::Span::<"))
+            .and_then(|inner| inner.strip_suffix('>'))
+        {
+            let len = reader.next()?.to_biguint();
+            let len: usize = len.try_into().ok()?;
+            let items = (0..len)
+                .map(|_| self.decode_value(inner, reader))
+                .collect::<Option<_>>()?;
+            return Some(Value::Array(items));
+        }
+        if let Some(inner) = ty
+            .strip_prefix('(')
+            .and_then(|inner| inner.strip_suffix(')'))
+        {
+            let items = split_tuple(inner)
+                .into_iter()
+                .map(|item| self.decode_value(item, reader))
+                .collect::<Option<_>>()?;
+            return Some(Value::Array(items));
+        }
+        if let Some(item) = self.structs.get(ty) {
+            let mut members = Map::new();
+            for member in &item.members {
+                members.insert(member.name.clone(), self.decode_value(&member.ty, reader)?);
+            }
+            return Some(Value::Object(members));
+        }
+        if let Some(item) = self.enums.get(ty) {
+            let index: usize = reader.next()?.to_biguint().try_into().ok()?;
+            let variant = item.variants.get(index)?;
+            let mut value = Map::new();
+            value.insert("variant".to_string(), Value::String(variant.name.clone()));
+            value.insert("value".to_string(), self.decode_value(&variant.ty, reader)?);
+            return Some(Value::Object(value));
+        }
+
+        let value = reader.next()?;
+        if ty.starts_with("core::integer::i")
+            && value.to_biguint() > Felt252::MAX.to_biguint() / 2u32
+        {
+            return Some(Value::String(format!("-{}", (-value).to_biguint())));
+        }
+        if ty.starts_with("core::integer::") {
+            return Some(Value::String(value.to_biguint().to_string()));
+        }
+        Some(Value::String(value.to_hex_string()))
+    }
+}
+
+/// The Starknet keccak of a name: the selector of an entrypoint or of an event enum variant, and
+/// the base address of a storage variable.
+pub fn selector_from_name(name: &str) -> Felt252 {
+    let selector: BigUint = starknet_keccak(name.as_bytes());
+    Felt252::from_bytes_be_slice(&selector.to_bytes_be())
+}
+
+/// Splits the members of a tuple type, ignoring commas of nested generic and tuple types.
+pub fn split_tuple(members: &str) -> Vec<&str> {
+    let mut items = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in members.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(members[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    let last = members[start..].trim();
+    if !last.is_empty() {
+        items.push(last);
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_calldata() {
+        let abi: Abi = serde_json::from_str(
+            r#"[
+                {
+                    "type": "interface",
+                    "name": "erc20::IERC20",
+                    "items": [{
+                        "type": "function",
+                        "name": "transfer",
+                        "inputs": [
+                            { "name": "to", "type": "core::starknet::contract_address::ContractAddress" },
+                            { "name": "amounts", "type": "core::array::Span::<(core::integer::u8, core::bool)>" }
+                        ],
+                        "outputs": [{ "type": "core::bool" }],
+                        "state_mutability": "external"
+                    }]
+                }
+            ]"#,
+        )
+        .unwrap();
+        let decoder = AbiDecoder::new(&abi);
+        let selector = selector_from_name("transfer");
+        let calldata = [Felt252::TWO, Felt252::ONE, Felt252::from(7), Felt252::ONE];
+
+        assert_eq!(decoder.function_name(&selector).unwrap(), "transfer");
+        assert_eq!(
+            decoder.decode_calldata(&selector, &calldata).unwrap(),
+            serde_json::json!({ "to": "0x2", "amounts": [["7", true]] })
+        );
+        assert!(decoder.decode_calldata(&selector, &calldata[..3]).is_none());
+        assert_eq!(
+            decoder.decode_result(&selector, &[Felt252::ONE]).unwrap(),
+            serde_json::json!([true])
+        );
+    }
+}
@@ -1,18 +1,33 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
 use cairo_lang_starknet_classes_2_point_6::casm_contract_class::CasmContractClass;
 use cairo_vm::{
-    hint_processor::cairo_1_hint_processor::hint_processor::Cairo1HintProcessor,
-    types::{builtin_name::BuiltinName, layout_name::LayoutName, relocatable::MaybeRelocatable},
+    types::{builtin_name::BuiltinName, relocatable::MaybeRelocatable},
     vm::{
-        errors::{cairo_run_errors::CairoRunError, vm_exception::get_traceback},
-        runners::cairo_runner::{CairoArg, CairoRunner, RunResources},
+        errors::{
+            cairo_run_errors::CairoRunError, vm_errors::VirtualMachineError,
+            vm_exception::get_traceback,
+        },
+        runners::cairo_runner::{CairoArg, CairoRunner},
         trace::trace_entry::RelocatedTraceEntry,
         vm_core::VirtualMachine,
     },
 };
 use hex::decode;
+use num_traits::ToPrimitive;
 use serde::{Deserialize, Serialize};
 use starknet_types_core::felt::Felt as Felt252;
 
+use super::config::RunConfig;
+use super::events::{decode_events, EmittedEvent};
+use super::execution::{resolve_storage_accesses, StorageAccess};
+use super::hint_processor::{PrintOutput, TracingHintProcessor};
+use super::memory::{dump_memory, MemoryDump};
+use super::sierra_trace::SierraTraceEntry;
+use super::syscalls::SyscallHandler;
+use crate::state::{DeclaredClass, State};
+
 fn hex_to_string(hex: &str) -> Result<String, hex::FromHexError> {
     //remove the 0x prefix
     let hex = &hex[2..];
@@ -21,35 +36,214 @@ fn hex_to_string(hex: &str) -> Result<String, hex::FromHexError> {
     Ok(string)
 }
 
+/// The panic reason of an entrypoint that ran out of gas, `'Out of gas'` as a short string.
+const OUT_OF_GAS_ERROR: &str = "0x4f7574206f6620676173";
+
+/// How an entrypoint execution ended.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExecutionStatus {
+    Succeeded,
+    /// The entrypoint panicked, `retdata` holds the panic data.
+    Reverted,
+    OutOfGas {
+        initial_gas: u64,
+    },
+    StepLimitExceeded {
+        max_steps: usize,
+    },
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ContractExecutionResult {
+    pub status: ExecutionStatus,
+    /// The gas consumed by the entrypoint, unknown if the step limit was exceeded.
+    pub gas_consumed: Option<u64>,
     pub retdata: String,
     pub trace: Vec<RelocatedTraceEntry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sierra_trace: Option<Vec<SierraTraceEntry>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryDump>,
+    /// What the entrypoint printed with `print`/`println!`.
+    pub prints: Vec<PrintOutput>,
+    /// The events emitted by the entrypoint, in emission order.
+    pub events: Vec<EmittedEvent>,
+    /// The storage accesses of the entrypoint, against an empty storage.
+    pub storage_accesses: Vec<StorageAccess>,
+}
+
+/// A finished run of a contract entrypoint.
+pub struct EntrypointRun {
+    pub runner: CairoRunner,
+    pub vm: VirtualMachine,
+    /// The names of the segments created for the implicit arguments and the calldata.
+    pub segment_names: HashMap<isize, &'static str>,
+    pub program_segment_size: usize,
+    /// Whether the run was stopped by the step limit before the entrypoint returned.
+    pub step_limit_exceeded: bool,
 }
 
 pub fn trace_error(
-    casm_contract_class: CasmContractClass,
+    class: &DeclaredClass,
     entrypoint_offset: usize,
     args: &[MaybeRelocatable],
-) -> Result<ContractExecutionResult, CairoRunError> {
+    export_memory: bool,
+    config: &RunConfig,
+) -> Result<ContractExecutionResult> {
+    let context = &config.execution_context;
+    let mut syscall_handler = SyscallHandler::new(
+        config,
+        State::default(),
+        context.caller_address.unwrap_or_default(),
+        context.contract_address.unwrap_or_default(),
+    );
+    if let Some(entry_point) = class
+        .casm_contract_class
+        .entry_points_by_type
+        .external
+        .iter()
+        .find(|entry_point| entry_point.offset == entrypoint_offset)
+    {
+        syscall_handler.entry_point_selector =
+            Felt252::from_bytes_be_slice(&entry_point.selector.to_bytes_be());
+    }
+
     let mut hint_processor =
-        Cairo1HintProcessor::new(&casm_contract_class.hints, RunResources::default());
-
-    let mut runner = CairoRunner::new(
-        &(casm_contract_class.clone().try_into().unwrap()),
-        LayoutName::all_cairo,
-        false,
-    )
-    .unwrap();
+        TracingHintProcessor::new(&class.casm_contract_class.hints, syscall_handler);
+    let EntrypointRun {
+        mut runner,
+        mut vm,
+        segment_names,
+        program_segment_size,
+        step_limit_exceeded,
+    } = run_entrypoint(
+        &class.casm_contract_class,
+        entrypoint_offset,
+        args,
+        config,
+        &mut hint_processor,
+    )?;
+
+    let (status, gas_consumed, retdata) = read_entrypoint_result(&vm, step_limit_exceeded, config)?;
+    let retdata = if retdata.is_empty() {
+        String::new()
+    } else {
+        let hex_retdata: Vec<String> = retdata.iter().map(|c| c.to_hex_string()).collect();
+        hex_to_string(&hex_retdata.join("")).unwrap()
+    };
+
+    let memory = if export_memory {
+        Some(dump_memory(&mut runner, &mut vm, &segment_names)?)
+    } else {
+        let _ = runner.relocate_trace(&vm, &vec![1, 1 + program_segment_size]);
+        None
+    };
+
+    let trace = runner.relocated_trace.unwrap();
+    let debug_info = class.debug_info.as_ref();
+    let sierra_trace = debug_info.map(|debug_info| debug_info.sierra_trace(&trace, 0));
+    let prints = hint_processor.take_prints(debug_info);
+    let mut syscall_handler = hint_processor.syscall_handler;
+    resolve_storage_accesses(&mut syscall_handler.storage_accesses, class, &vm);
+    let mut events = syscall_handler.events;
+    if let Some(abi) = &class.abi {
+        decode_events(&mut events, abi);
+    }
+
+    Ok(ContractExecutionResult {
+        status,
+        gas_consumed,
+        retdata,
+        trace,
+        sierra_trace,
+        memory,
+        prints,
+        events,
+        storage_accesses: syscall_handler.storage_accesses,
+    })
+}
+
+/// Reads how a finished entrypoint run ended: its status, the gas it consumed and its retdata.
+pub fn read_entrypoint_result(
+    vm: &VirtualMachine,
+    step_limit_exceeded: bool,
+    config: &RunConfig,
+) -> Result<(ExecutionStatus, Option<u64>, Vec<Felt252>)> {
+    if step_limit_exceeded {
+        let max_steps = config.max_steps.unwrap_or_default();
+        return Ok((
+            ExecutionStatus::StepLimitExceeded { max_steps },
+            None,
+            vec![],
+        ));
+    }
+
+    let return_values = vm.get_return_values(5)?;
+    let remaining_gas = return_values[0].get_int().unwrap_or_default();
+    let failure_flag = return_values[2].get_int().unwrap_or_default();
+    let retdata_start = return_values[3]
+        .get_relocatable()
+        .with_context(|| "Invalid retdata start.")?;
+    let retdata_end = return_values[4]
+        .get_relocatable()
+        .with_context(|| "Invalid retdata end.")?;
+    let retdata: Vec<Felt252> = vm
+        .get_integer_range(retdata_start, (retdata_end - retdata_start)?)?
+        .iter()
+        .map(|c| c.clone().into_owned())
+        .collect();
+
+    let status = if failure_flag == Felt252::ZERO {
+        ExecutionStatus::Succeeded
+    } else if retdata.first() == Some(&Felt252::from_hex_unchecked(OUT_OF_GAS_ERROR)) {
+        ExecutionStatus::OutOfGas {
+            initial_gas: config.initial_gas,
+        }
+    } else {
+        ExecutionStatus::Reverted
+    };
+    let gas_consumed = config
+        .initial_gas
+        .checked_sub(remaining_gas.to_u64().unwrap_or_default());
+    Ok((status, gas_consumed, retdata))
+}
+
+/// Runs a contract entrypoint on a fresh VM with the given layout, executing its hints with the
+/// given processor.
+///
+/// The processor is borrowed so that its syscall handler, and the state it holds, outlives a
+/// failed run.
+pub fn run_entrypoint(
+    casm_contract_class: &CasmContractClass,
+    entrypoint_offset: usize,
+    args: &[MaybeRelocatable],
+    config: &RunConfig,
+    hint_processor: &mut TracingHintProcessor,
+) -> Result<EntrypointRun> {
+    let program = casm_contract_class
+        .clone()
+        .try_into()
+        .map_err(|e| anyhow::anyhow!("Invalid CASM contract class: {}", e))?;
+    let mut runner = CairoRunner::new(&program, config.layout()?, false)
+        .map_err(|e| anyhow::anyhow!("Failed to create the runner: {}", e))?;
     let mut vm = VirtualMachine::new(true);
 
-    let program_builtins = get_casm_contract_builtins(&casm_contract_class, entrypoint_offset);
+    let program_builtins = get_casm_contract_builtins(casm_contract_class, entrypoint_offset)?;
     runner
         .initialize_function_runner_cairo_1(&mut vm, &program_builtins)
-        .unwrap();
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to initialize the builtins {:?} in layout {}: {}",
+                program_builtins,
+                config.layout,
+                e
+            )
+        })?;
 
     // Implicit Args
-    let syscall_segment = MaybeRelocatable::from(vm.add_memory_segment());
+    let syscall_segment_ptr = vm.add_memory_segment();
+    let syscall_segment = MaybeRelocatable::from(syscall_segment_ptr);
 
     let builtins = runner.get_program_builtins();
 
@@ -60,17 +254,21 @@ pub fn trace_error(
         .flat_map(|b| b.initial_stack())
         .collect();
 
-    let initial_gas = MaybeRelocatable::from(usize::MAX);
+    let initial_gas = MaybeRelocatable::from(Felt252::from(config.initial_gas));
 
     let mut implicit_args = builtin_segment;
     implicit_args.extend([initial_gas]);
     implicit_args.extend([syscall_segment]);
 
     // Load builtin costs
-    let builtin_costs: Vec<MaybeRelocatable> =
-        vec![0.into(), 0.into(), 0.into(), 0.into(), 0.into()];
+    let builtin_costs: Vec<MaybeRelocatable> = config
+        .builtin_costs()
+        .to_vec()
+        .into_iter()
+        .map(|cost| Felt252::from(cost).into())
+        .collect();
     let builtin_costs_ptr = vm.add_memory_segment();
-    vm.load_data(builtin_costs_ptr, &builtin_costs).unwrap();
+    vm.load_data(builtin_costs_ptr, &builtin_costs)?;
 
     // Load extra data
     let core_program_end_ptr =
@@ -82,7 +280,7 @@ pub fn trace_error(
 
     // Load calldata
     let calldata_start = vm.add_memory_segment();
-    let calldata_end = vm.load_data(calldata_start, &args.to_vec()).unwrap();
+    let calldata_end = vm.load_data(calldata_start, &args.to_vec())?;
 
     // Create entrypoint_args
     let mut entrypoint_args: Vec<CairoArg> = implicit_args
@@ -95,59 +293,64 @@ pub fn trace_error(
     ]);
     let entrypoint_args: Vec<&CairoArg> = entrypoint_args.iter().collect();
 
+    let program_segment_size = runner.get_program().data_len() + program_extra_data.len();
+
     // Run contract entrypoint
+    let mut step_limit_exceeded = false;
     match runner.run_from_entrypoint(
         entrypoint_offset,
         &entrypoint_args,
         true,
-        Some(runner.get_program().data_len() + program_extra_data.len()),
+        Some(program_segment_size),
         &mut vm,
-        &mut hint_processor,
+        hint_processor,
     ) {
         Ok(_) => {
             println!("Execution completed successfully.");
         }
+        Err(CairoRunError::VirtualMachine(VirtualMachineError::UnfinishedExecution)) => {
+            println!("Execution stopped by the step limit.");
+            step_limit_exceeded = true;
+        }
         Err(e) => {
             let traceback = get_traceback(&vm, &runner);
             println!("Error during execution: {:?}", e);
             println!("Traceback: {:?}", traceback);
-            return Err(e);
+            return Err(e.into());
         }
     }
 
-    let program_segment_size = runner.get_program().data_len() + program_extra_data.len();
-    let _ = runner.relocate_trace(&mut vm, &vec![1, 1 + program_segment_size]);
-
-    let return_values = vm.get_return_values(5).unwrap();
-    let retdata_start = return_values[3].get_relocatable().unwrap();
-    let retdata_end = return_values[4].get_relocatable().unwrap();
-    let vec_retdata: Vec<Felt252> = vm
-        .get_integer_range(retdata_start, (retdata_end - retdata_start).unwrap())
-        .unwrap()
-        .iter()
-        .map(|c| c.clone().into_owned())
-        .collect();
-    let hex_retdata: Vec<String> = vec_retdata.iter().map(|c| c.to_hex_string()).collect();
-    let retdata = hex_to_string(&hex_retdata.join("")).unwrap();
+    let segment_names = HashMap::from([
+        (syscall_segment_ptr.segment_index, "syscall"),
+        (builtin_costs_ptr.segment_index, "builtin_costs"),
+        (calldata_start.segment_index, "calldata"),
+    ]);
 
-    Ok(ContractExecutionResult {
-        retdata,
-        trace: runner.relocated_trace.unwrap(),
+    Ok(EntrypointRun {
+        runner,
+        vm,
+        segment_names,
+        program_segment_size,
+        step_limit_exceeded,
     })
 }
 
-fn get_casm_contract_builtins(
+/// The builtins of the entrypoint at the given offset, of any entrypoint type.
+pub fn get_casm_contract_builtins(
     contract_class: &CasmContractClass,
     entrypoint_offset: usize,
-) -> Vec<BuiltinName> {
-    contract_class
-        .entry_points_by_type
+) -> Result<Vec<BuiltinName>> {
+    let entry_points = &contract_class.entry_points_by_type;
+    let entry_point = entry_points
         .external
         .iter()
+        .chain(&entry_points.l1_handler)
+        .chain(&entry_points.constructor)
         .find(|e| e.offset == entrypoint_offset)
-        .unwrap()
+        .with_context(|| format!("No entrypoint at offset {}.", entrypoint_offset))?;
+    entry_point
         .builtins
         .iter()
-        .map(|s| BuiltinName::from_str(s).expect("Invalid builtin name"))
+        .map(|s| BuiltinName::from_str(s).with_context(|| format!("Invalid builtin name {}.", s)))
         .collect()
 }
@@ -0,0 +1,265 @@
+use anyhow::Result;
+use cairo_vm::types::layout_name::LayoutName;
+use cairo_vm::vm::runners::cairo_runner::RunResources;
+use serde::{Deserialize, Serialize};
+use starknet_types_core::felt::Felt as Felt252;
+
+use super::versioned_constants::{
+    BuiltinCosts, FeeCosts, OsResources, StarknetVersion, SyscallGasCosts,
+};
+
+/// The initial gas of a transaction on Starknet: 10^8 steps at 100 gas per step.
+pub const DEFAULT_INITIAL_GAS: u64 = 10_000_000_000;
+
+/// The maximal number of steps of an invoke transaction on Starknet.
+pub const DEFAULT_MAX_STEPS: usize = 10_000_000;
+
+/// The maximal number of steps of the `__validate__` entrypoint of an account on Starknet.
+pub const VALIDATE_MAX_STEPS: usize = 1_000_000;
+
+/// The default L1 gas and blob gas prices: 1 gwei.
+pub const DEFAULT_GAS_PRICE: u128 = 1_000_000_000;
+
+/// The default L1 gas and blob gas prices in fri, at 1000 STRK per ETH.
+pub const DEFAULT_STRK_GAS_PRICE: u128 = 1_000 * DEFAULT_GAS_PRICE;
+
+/// The addresses of the ETH and STRK fee tokens on Starknet mainnet and Sepolia.
+pub const ETH_FEE_TOKEN_ADDRESS: &str =
+    "0x49d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7";
+pub const STRK_FEE_TOKEN_ADDRESS: &str =
+    "0x4718f5a0fc34cc1af16a1cdee98ffb20c31f5cd61d6ab07201858f4287c938d";
+
+/// VM layout and resource limits of an execution.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunConfig {
+    pub layout: String,
+    /// The maximal number of steps, `None` for no limit.
+    pub max_steps: Option<usize>,
+    pub initial_gas: u64,
+    /// The protocol version whose constants (e.g. builtin costs) are used.
+    pub starknet_version: StarknetVersion,
+    /// Overrides the builtin costs of `starknet_version`.
+    pub builtin_costs: Option<BuiltinCosts>,
+    /// The block and transaction returned by `get_execution_info`.
+    pub execution_context: ExecutionContext,
+    pub execution_mode: ExecutionMode,
+    /// Account transactions are executed without their validation.
+    pub skip_validate: bool,
+    /// Account transactions are executed without charging their fee.
+    pub skip_fee_charge: bool,
+}
+
+/// Whether an account validates a transaction or executes it.
+///
+/// Validation runs before the fee can be charged, so it may not call other contracts and only
+/// sees a rounded block number and timestamp.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionMode {
+    #[default]
+    Execute,
+    Validate,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            layout: "all_cairo".to_string(),
+            max_steps: Some(DEFAULT_MAX_STEPS),
+            initial_gas: DEFAULT_INITIAL_GAS,
+            starknet_version: StarknetVersion::default(),
+            builtin_costs: None,
+            execution_context: ExecutionContext::default(),
+            execution_mode: ExecutionMode::default(),
+            skip_validate: false,
+            skip_fee_charge: false,
+        }
+    }
+}
+
+/// The block and transaction an execution runs in, as seen by the contract through
+/// `get_execution_info` (`get_caller_address`, `get_block_timestamp`, `get_tx_info`...).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecutionContext {
+    /// The caller of the executed entrypoint, where the endpoint does not take one.
+    pub caller_address: Option<Felt252>,
+    /// The address of the contract running the executed entrypoint, where the endpoint does not
+    /// take one.
+    pub contract_address: Option<Felt252>,
+    pub block_number: u64,
+    pub block_timestamp: u64,
+    pub sequencer_address: Felt252,
+    /// The price of L1 gas, in wei.
+    pub gas_price: u128,
+    /// The price of L1 blob gas, in wei.
+    pub data_gas_price: u128,
+    /// The prices of L1 gas and L1 blob gas in fri, charged to v3 transactions.
+    pub strk_gas_price: u128,
+    pub strk_data_gas_price: u128,
+    pub chain_id: Felt252,
+    pub transaction_hash: Felt252,
+    /// The account sending the transaction.
+    pub account_contract_address: Felt252,
+    pub nonce: Felt252,
+    pub version: Felt252,
+    /// The `max_fee` of transactions before v3, 0 for v3 transactions.
+    pub max_fee: u128,
+    /// The most the transaction may be charged: its `max_fee`, or for v3 transactions the
+    /// product of its L1 gas bounds. Transactions without a bound are not charged.
+    pub fee_bound: u128,
+    pub signature: Vec<Felt252>,
+    /// The fee fields of v3 transactions, left empty by earlier versions.
+    pub resource_bounds: Vec<ResourceBounds>,
+    pub tip: u128,
+    pub paymaster_data: Vec<Felt252>,
+    /// The data availability modes of the nonce and the fee: 0 for L1, 1 for L2.
+    pub nonce_data_availability_mode: u32,
+    pub fee_data_availability_mode: u32,
+    pub account_deployment_data: Vec<Felt252>,
+    /// The ERC20 contract charged the fee of transactions before v3.
+    pub eth_fee_token_address: Felt252,
+    /// The ERC20 contract charged the fee of v3 transactions.
+    pub strk_fee_token_address: Felt252,
+}
+
+impl Default for ExecutionContext {
+    fn default() -> Self {
+        Self {
+            caller_address: None,
+            contract_address: None,
+            block_number: 0,
+            block_timestamp: 0,
+            sequencer_address: Felt252::ZERO,
+            gas_price: DEFAULT_GAS_PRICE,
+            data_gas_price: DEFAULT_GAS_PRICE,
+            strk_gas_price: DEFAULT_STRK_GAS_PRICE,
+            strk_data_gas_price: DEFAULT_STRK_GAS_PRICE,
+            chain_id: Felt252::from_bytes_be_slice(b"SN_SEPOLIA"),
+            transaction_hash: Felt252::ZERO,
+            account_contract_address: Felt252::ZERO,
+            nonce: Felt252::ZERO,
+            version: Felt252::ONE,
+            max_fee: 0,
+            fee_bound: 0,
+            signature: vec![],
+            resource_bounds: vec![],
+            tip: 0,
+            paymaster_data: vec![],
+            nonce_data_availability_mode: 0,
+            fee_data_availability_mode: 0,
+            account_deployment_data: vec![],
+            eth_fee_token_address: Felt252::from_hex_unchecked(ETH_FEE_TOKEN_ADDRESS),
+            strk_fee_token_address: Felt252::from_hex_unchecked(STRK_FEE_TOKEN_ADDRESS),
+        }
+    }
+}
+
+/// The bounds a v3 transaction sets on a resource, as returned by `get_tx_info`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceBounds {
+    /// The name of the resource: `L1_GAS`, `L2_GAS` or `L1_DATA`.
+    pub resource: String,
+    pub max_amount: u64,
+    pub max_price_per_unit: u128,
+}
+
+impl RunConfig {
+    pub fn layout(&self) -> Result<LayoutName> {
+        parse_layout(&self.layout)
+    }
+
+    pub fn builtin_costs(&self) -> BuiltinCosts {
+        self.builtin_costs
+            .clone()
+            .unwrap_or_else(|| self.starknet_version.versioned_constants().builtin_costs)
+    }
+
+    pub fn syscall_gas_costs(&self) -> SyscallGasCosts {
+        self.starknet_version
+            .versioned_constants()
+            .syscall_gas_costs
+    }
+
+    pub fn fee_costs(&self) -> FeeCosts {
+        self.starknet_version.versioned_constants().fee_costs
+    }
+
+    pub fn os_resources(&self) -> OsResources {
+        self.starknet_version.versioned_constants().os_resources
+    }
+
+    pub fn run_resources(&self) -> RunResources {
+        match self.max_steps {
+            Some(max_steps) => RunResources::new(max_steps),
+            None => RunResources::default(),
+        }
+    }
+}
+
+/// Parses a cairo-vm layout name, as used by the prover (e.g. `all_cairo`, `starknet`).
+pub fn parse_layout(name: &str) -> Result<LayoutName> {
+    Ok(match name {
+        "plain" => LayoutName::plain,
+        "small" => LayoutName::small,
+        "dex" => LayoutName::dex,
+        "recursive" => LayoutName::recursive,
+        "starknet" => LayoutName::starknet,
+        "starknet_with_keccak" => LayoutName::starknet_with_keccak,
+        "recursive_large_output" => LayoutName::recursive_large_output,
+        "recursive_with_poseidon" => LayoutName::recursive_with_poseidon,
+        "all_solidity" => LayoutName::all_solidity,
+        "all_cairo" => LayoutName::all_cairo,
+        "dynamic" => LayoutName::dynamic,
+        _ => anyhow::bail!("Unknown layout `{}`.", name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_layout() {
+        assert_eq!(parse_layout("all_cairo").unwrap(), LayoutName::all_cairo);
+        assert_eq!(parse_layout("recursive").unwrap(), LayoutName::recursive);
+        assert!(parse_layout("unknown").is_err());
+    }
+
+    #[test]
+    fn test_default_run_config() {
+        let config: RunConfig = serde_json::from_str(r#"{"max_steps": 100}"#).unwrap();
+        assert_eq!(config.max_steps, Some(100));
+        assert_eq!(config.initial_gas, DEFAULT_INITIAL_GAS);
+        assert_eq!(config.layout().unwrap(), LayoutName::all_cairo);
+    }
+
+    #[test]
+    fn test_execution_context_overrides() {
+        let config: RunConfig = serde_json::from_str(
+            r#"{"execution_context": {"block_timestamp": 1700000000, "caller_address": "0x123"}}"#,
+        )
+        .unwrap();
+        let context = config.execution_context;
+        assert_eq!(context.block_timestamp, 1700000000);
+        assert_eq!(context.caller_address, Some(Felt252::from(0x123)));
+        assert_eq!(
+            context.chain_id,
+            Felt252::from_bytes_be_slice(b"SN_SEPOLIA")
+        );
+    }
+
+    #[test]
+    fn test_builtin_costs_override() {
+        let config: RunConfig = serde_json::from_str(
+            r#"{"starknet_version": "0.13.1", "builtin_costs": {"pedersen": 1}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.builtin_costs().pedersen, 1);
+        assert_eq!(config.builtin_costs().bitwise, 0);
+
+        let config: RunConfig = serde_json::from_str(r#"{"starknet_version": "0.13.1"}"#).unwrap();
+        assert_eq!(config.builtin_costs().bitwise, 594);
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use starknet_types_core::felt::Felt as Felt252;
+
+use super::abi_decoder::{selector_from_name, AbiDecoder, FeltReader};
+use crate::compiler::abi::{Abi, AbiEntry, AbiEvent, AbiEventFieldKind, AbiEventKind};
+
+/// An event emitted with the `emit_event` syscall.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmittedEvent {
+    /// The position of the event among the events of the execution.
+    pub order: usize,
+    pub contract_address: Felt252,
+    pub keys: Vec<Felt252>,
+    pub data: Vec<Felt252>,
+    /// The event decoded against the ABI of the emitting contract.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<DecodedEvent>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DecodedEvent {
+    /// The full name of the event struct, e.g. `erc20::ERC20::Transfer`.
+    pub name: String,
+    /// The variants selected in the (nested) event enums, e.g. `["Transfer"]`.
+    pub variant_path: Vec<String>,
+    pub fields: Vec<DecodedEventField>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DecodedEventField {
+    pub name: String,
+    /// Whether the field was read from the keys (`#[key]`) or the data.
+    pub kind: AbiEventFieldKind,
+    pub value: Value,
+}
+
+/// Decodes the events of a contract against its ABI, leaving undecodable events as they are.
+pub fn decode_events(events: &mut [EmittedEvent], abi: &Abi) {
+    let decoder = EventDecoder::new(abi);
+    for event in events.iter_mut() {
+        event.decoded = decoder.decode(&event.keys, &event.data);
+    }
+}
+
+pub struct EventDecoder<'a> {
+    values: AbiDecoder<'a>,
+    events: HashMap<&'a str, &'a AbiEvent>,
+    /// The event enum of the contract, the one no other event refers to.
+    root: Option<&'a str>,
+}
+
+impl<'a> EventDecoder<'a> {
+    pub fn new(abi: &'a Abi) -> Self {
+        let events: HashMap<&str, &AbiEvent> = abi
+            .iter()
+            .filter_map(|entry| match entry {
+                AbiEntry::Event(event) => Some((event.name.as_str(), event)),
+                _ => None,
+            })
+            .collect();
+
+        let nested: HashSet<&str> = events
+            .values()
+            .flat_map(|event| match &event.kind {
+                AbiEventKind::Struct { members } => members,
+                AbiEventKind::Enum { variants } => variants,
+            })
+            .map(|field| field.ty.as_str())
+            .collect();
+        let mut roots: Vec<&str> = events
+            .values()
+            .filter(|event| matches!(event.kind, AbiEventKind::Enum { .. }))
+            .map(|event| event.name.as_str())
+            .filter(|name| !nested.contains(name))
+            .collect();
+        roots.sort_by_key(|name| (!name.ends_with("::Event"), *name));
+
+        Self {
+            values: AbiDecoder::new(abi),
+            events,
+            root: roots.first().copied(),
+        }
+    }
+
+    /// Decodes an event from its keys and data, `None` if it does not match the ABI.
+    pub fn decode(&self, keys: &[Felt252], data: &[Felt252]) -> Option<DecodedEvent> {
+        let mut keys = FeltReader::new(keys);
+        let mut data = FeltReader::new(data);
+        let mut variant_path = vec![];
+        let mut fields = vec![];
+        let name = self.decode_event(
+            self.root?,
+            &mut keys,
+            &mut data,
+            &mut variant_path,
+            &mut fields,
+        )?;
+        if !keys.is_empty() || !data.is_empty() {
+            return None;
+        }
+        Some(DecodedEvent {
+            name,
+            variant_path,
+            fields,
+        })
+    }
+
+    /// Decodes the event of the given type, returning the name of the event struct.
+    fn decode_event<'r>(
+        &self,
+        name: &str,
+        keys: &mut FeltReader<'r>,
+        data: &mut FeltReader<'r>,
+        variant_path: &mut Vec<String>,
+        fields: &mut Vec<DecodedEventField>,
+    ) -> Option<String> {
+        match &self.events.get(name)?.kind {
+            AbiEventKind::Struct { members } => {
+                for member in members {
+                    let reader = match member.kind {
+                        AbiEventFieldKind::Key => &mut *keys,
+                        AbiEventFieldKind::Data => &mut *data,
+                        AbiEventFieldKind::Nested | AbiEventFieldKind::Flat => {
+                            self.decode_event(&member.ty, keys, data, variant_path, fields)?;
+                            continue;
+                        }
+                    };
+                    fields.push(DecodedEventField {
+                        name: member.name.clone(),
+                        kind: member.kind,
+                        value: self.values.decode_value(&member.ty, reader)?,
+                    });
+                }
+                Some(name.to_string())
+            }
+            AbiEventKind::Enum { variants } => {
+                let selector = keys.peek()?;
+                for variant in variants {
+                    match variant.kind {
+                        AbiEventFieldKind::Nested
+                            if selector == selector_from_name(&variant.name) =>
+                        {
+                            keys.next();
+                            variant_path.push(variant.name.clone());
+                            return self.decode_event(
+                                &variant.ty,
+                                keys,
+                                data,
+                                variant_path,
+                                fields,
+                            );
+                        }
+                        // A flat variant has no selector of its own, it matches if its event does.
+                        AbiEventFieldKind::Flat => {
+                            let (mut flat_keys, mut flat_data) = (*keys, *data);
+                            let mut flat_path = vec![variant.name.clone()];
+                            let mut flat_fields = vec![];
+                            if let Some(name) = self.decode_event(
+                                &variant.ty,
+                                &mut flat_keys,
+                                &mut flat_data,
+                                &mut flat_path,
+                                &mut flat_fields,
+                            ) {
+                                (*keys, *data) = (flat_keys, flat_data);
+                                variant_path.extend(flat_path);
+                                fields.extend(flat_fields);
+                                return Some(name);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ABI: &str = r#"[
+        {
+            "type": "event",
+            "name": "erc20::ERC20::Transfer",
+            "kind": "struct",
+            "members": [
+                { "name": "from", "type": "core::starknet::contract_address::ContractAddress", "kind": "key" },
+                { "name": "to", "type": "core::starknet::contract_address::ContractAddress", "kind": "key" },
+                { "name": "value", "type": "core::integer::u256", "kind": "data" }
+            ]
+        },
+        {
+            "type": "event",
+            "name": "erc20::ownable::Ownable::OwnershipTransferred",
+            "kind": "struct",
+            "members": [
+                { "name": "new_owner", "type": "core::starknet::contract_address::ContractAddress", "kind": "data" }
+            ]
+        },
+        {
+            "type": "event",
+            "name": "erc20::ownable::Ownable::Event",
+            "kind": "enum",
+            "variants": [
+                { "name": "OwnershipTransferred", "type": "erc20::ownable::Ownable::OwnershipTransferred", "kind": "nested" }
+            ]
+        },
+        {
+            "type": "event",
+            "name": "erc20::ERC20::Event",
+            "kind": "enum",
+            "variants": [
+                { "name": "Transfer", "type": "erc20::ERC20::Transfer", "kind": "nested" },
+                { "name": "OwnableEvent", "type": "erc20::ownable::Ownable::Event", "kind": "nested" }
+            ]
+        }
+    ]"#;
+
+    #[test]
+    fn test_decode_event_with_keys() {
+        let abi: Abi = serde_json::from_str(ABI).unwrap();
+        let decoder = EventDecoder::new(&abi);
+        let keys = [selector_from_name("Transfer"), Felt252::ONE, Felt252::TWO];
+        let data = [Felt252::from(5), Felt252::ONE];
+
+        let event = decoder.decode(&keys, &data).unwrap();
+        assert_eq!(event.name, "erc20::ERC20::Transfer");
+        assert_eq!(event.fields[1].kind, AbiEventFieldKind::Key);
+        assert_eq!(event.fields[1].value, Value::String("0x2".to_string()));
+        assert_eq!(
+            event.fields[2].value,
+            Value::String("340282366920938463463374607431768211461".to_string())
+        );
+    }
+
+    #[test]
+    fn test_decode_nested_event() {
+        let abi: Abi = serde_json::from_str(ABI).unwrap();
+        let decoder = EventDecoder::new(&abi);
+        let keys = [
+            selector_from_name("OwnableEvent"),
+            selector_from_name("OwnershipTransferred"),
+        ];
+
+        let event = decoder.decode(&keys, &[Felt252::ONE]).unwrap();
+        assert_eq!(
+            event.variant_path,
+            vec!["OwnableEvent", "OwnershipTransferred"]
+        );
+        assert!(decoder.decode(&keys, &[]).is_none());
+    }
+}
@@ -0,0 +1,620 @@
+use anyhow::{Context, Result};
+use cairo_vm::{
+    types::relocatable::MaybeRelocatable,
+    vm::{trace::trace_entry::RelocatedTraceEntry, vm_core::VirtualMachine},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use starknet_types_core::felt::Felt as Felt252;
+
+use super::abi_decoder::{selector_from_name, AbiDecoder};
+use super::cairo_runner::{read_entrypoint_result, run_entrypoint, EntrypointRun, ExecutionStatus};
+use super::config::RunConfig;
+use super::events::{decode_events, EmittedEvent};
+use super::hint_processor::{PrintOutput, TracingHintProcessor};
+use super::resources::ExecutionResources;
+use super::run::parse_felt;
+use super::sierra_trace::SierraTraceEntry;
+use super::storage::{StorageResolver, StorageVariableRef};
+use super::syscalls::SyscallHandler;
+use crate::state::{DeclaredClass, State};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallType {
+    /// A `call_contract`, running the code of the called contract on its own storage.
+    Call,
+    /// A `library_call`, running the code of a class on the storage of the caller.
+    Delegate,
+}
+
+/// The kind of an entrypoint of a contract class.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryPointType {
+    External,
+    L1Handler,
+    Constructor,
+}
+
+/// A call to an external entrypoint of a contract.
+#[derive(Clone, Debug)]
+pub struct CallEntryPoint {
+    pub call_type: CallType,
+    pub caller_address: Felt252,
+    /// The contract whose storage is used.
+    pub contract_address: Felt252,
+    /// The class whose code runs, the class of `contract_address` if not given.
+    pub class_hash: Option<Felt252>,
+    pub entry_point_type: EntryPointType,
+    pub entry_point_selector: Felt252,
+    pub calldata: Vec<Felt252>,
+    pub initial_gas: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageAccessKind {
+    Read,
+    Write,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StorageAccess {
+    pub kind: StorageAccessKind,
+    pub key: Felt252,
+    /// The value read or written.
+    pub value: Felt252,
+    /// The value a write replaced.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_value: Option<Felt252>,
+    /// The storage variable of the key, if the storage layout of the class is known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variable: Option<StorageVariableRef>,
+}
+
+/// A message sent to an L1 contract with `send_message_to_l1_syscall`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageToL1 {
+    pub from_address: Felt252,
+    pub to_address: Felt252,
+    pub payload: Vec<Felt252>,
+}
+
+/// A node of the call tree: an executed call and the calls it made.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CallInfo {
+    pub call_type: CallType,
+    pub caller_address: Felt252,
+    pub contract_address: Felt252,
+    pub class_hash: Felt252,
+    pub entry_point_type: EntryPointType,
+    pub entry_point_selector: Felt252,
+    /// The name of the called function, if the class ABI is known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function_name: Option<String>,
+    pub calldata: Vec<Felt252>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded_calldata: Option<Value>,
+    pub status: ExecutionStatus,
+    pub retdata: Vec<Felt252>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded_result: Option<Value>,
+    /// The gas consumed by the call, including its inner calls.
+    pub gas_consumed: Option<u64>,
+    pub events: Vec<EmittedEvent>,
+    pub l2_to_l1_messages: Vec<MessageToL1>,
+    pub storage_accesses: Vec<StorageAccess>,
+    pub prints: Vec<PrintOutput>,
+    /// The resources of the call, without those of its inner calls.
+    pub resources: ExecutionResources,
+    pub trace: Vec<RelocatedTraceEntry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sierra_trace: Option<Vec<SierraTraceEntry>>,
+    pub inner_calls: Vec<CallInfo>,
+}
+
+impl CallInfo {
+    /// The steps of the call, including those of its inner calls.
+    pub fn total_steps(&self) -> usize {
+        self.resources.n_steps
+            + self
+                .inner_calls
+                .iter()
+                .map(CallInfo::total_steps)
+                .sum::<usize>()
+    }
+}
+
+/// Parses an entrypoint given by name or by selector, in hex or decimal.
+pub fn parse_entry_point_selector(entry_point: &str) -> Result<Felt252> {
+    if entry_point.starts_with(|c: char| c.is_ascii_digit()) {
+        parse_felt(entry_point)
+    } else {
+        Ok(selector_from_name(entry_point))
+    }
+}
+
+/// Executes a call against the state, running the calls it makes recursively.
+///
+/// Every call runs on a fresh VM. The state changes of a call that does not succeed are reverted,
+/// and its inner calls share its `max_steps`.
+pub fn execute_call(
+    state: &mut State,
+    n_emitted_events: &mut usize,
+    call: &CallEntryPoint,
+    config: &RunConfig,
+) -> Result<CallInfo> {
+    let class_hash = match call.class_hash {
+        Some(class_hash) => class_hash,
+        None => state.get_class_hash_at(&call.contract_address)?,
+    };
+    let class = state.get_class(&class_hash)?;
+    let entry_points_by_type = &class.casm_contract_class.entry_points_by_type;
+    let entry_points = match call.entry_point_type {
+        EntryPointType::External => &entry_points_by_type.external,
+        EntryPointType::L1Handler => &entry_points_by_type.l1_handler,
+        EntryPointType::Constructor => &entry_points_by_type.constructor,
+    };
+    // A class without a constructor is deployed without running anything.
+    if call.entry_point_type == EntryPointType::Constructor && entry_points.is_empty() {
+        if !call.calldata.is_empty() {
+            anyhow::bail!("Cannot pass calldata to a contract with no constructor.");
+        }
+        return Ok(CallInfo {
+            call_type: call.call_type,
+            caller_address: call.caller_address,
+            contract_address: call.contract_address,
+            class_hash,
+            entry_point_type: call.entry_point_type,
+            entry_point_selector: call.entry_point_selector,
+            function_name: None,
+            calldata: vec![],
+            decoded_calldata: None,
+            status: ExecutionStatus::Succeeded,
+            retdata: vec![],
+            decoded_result: None,
+            gas_consumed: Some(0),
+            events: vec![],
+            l2_to_l1_messages: vec![],
+            storage_accesses: vec![],
+            prints: vec![],
+            resources: ExecutionResources::default(),
+            trace: vec![],
+            sierra_trace: None,
+            inner_calls: vec![],
+        });
+    }
+    let entry_point = entry_points
+        .iter()
+        .find(|entry_point| {
+            Felt252::from_bytes_be_slice(&entry_point.selector.to_bytes_be())
+                == call.entry_point_selector
+        })
+        .with_context(|| {
+            format!(
+                "Entrypoint {} not found in class {}.",
+                call.entry_point_selector.to_hex_string(),
+                class_hash.to_hex_string()
+            )
+        })?;
+
+    let mut call_config = config.clone();
+    call_config.initial_gas = call.initial_gas;
+    let checkpoint = state.checkpoint();
+    let mut syscall_handler = SyscallHandler::new(
+        &call_config,
+        std::mem::take(state),
+        call.caller_address,
+        call.contract_address,
+    );
+    syscall_handler.entry_point_selector = call.entry_point_selector;
+    syscall_handler.n_emitted_events = *n_emitted_events;
+    let mut hint_processor =
+        TracingHintProcessor::new(&class.casm_contract_class.hints, syscall_handler);
+
+    let calldata: Vec<MaybeRelocatable> =
+        call.calldata.iter().map(MaybeRelocatable::from).collect();
+    let run = run_entrypoint(
+        &class.casm_contract_class,
+        entry_point.offset,
+        &calldata,
+        &call_config,
+        &mut hint_processor,
+    );
+    let result = run.and_then(|run| {
+        let result = read_entrypoint_result(&run.vm, run.step_limit_exceeded, &call_config)?;
+        Ok((run, result))
+    });
+    let debug_info = class.debug_info.as_ref();
+    let prints = hint_processor.take_prints(debug_info);
+    let mut syscall_handler = hint_processor.syscall_handler;
+    *state = std::mem::take(&mut syscall_handler.state);
+    *n_emitted_events = syscall_handler.n_emitted_events;
+    match &result {
+        Ok((_, (ExecutionStatus::Succeeded, ..))) => state.commit(),
+        _ => state.revert(checkpoint),
+    }
+    let (
+        EntrypointRun {
+            mut runner,
+            vm,
+            program_segment_size,
+            ..
+        },
+        (status, gas_consumed, retdata),
+    ) = result?;
+    resolve_storage_accesses(&mut syscall_handler.storage_accesses, &class, &vm);
+
+    runner.relocate_trace(&vm, &vec![1, 1 + program_segment_size])?;
+    let trace = runner.relocated_trace.take().unwrap_or_default();
+    let resources =
+        ExecutionResources::from_run(&runner, &vm, trace.len(), syscall_handler.syscall_counter);
+    let sierra_trace = debug_info.map(|debug_info| debug_info.sierra_trace(&trace, 0));
+
+    let mut events = syscall_handler.events;
+    let decoder = class.abi.as_ref().map(AbiDecoder::new);
+    let decoded_calldata = decoder
+        .as_ref()
+        .and_then(|decoder| decoder.decode_calldata(&call.entry_point_selector, &call.calldata));
+    let decoded_result = match (&decoder, &status) {
+        (Some(decoder), ExecutionStatus::Succeeded) => {
+            decoder.decode_result(&call.entry_point_selector, &retdata)
+        }
+        _ => None,
+    };
+    if let Some(abi) = &class.abi {
+        decode_events(&mut events, abi);
+    }
+
+    Ok(CallInfo {
+        call_type: call.call_type,
+        caller_address: call.caller_address,
+        contract_address: call.contract_address,
+        class_hash,
+        entry_point_type: call.entry_point_type,
+        entry_point_selector: call.entry_point_selector,
+        function_name: decoder
+            .as_ref()
+            .and_then(|decoder| decoder.function_name(&call.entry_point_selector)),
+        calldata: call.calldata.clone(),
+        decoded_calldata,
+        status,
+        retdata,
+        decoded_result,
+        gas_consumed,
+        events,
+        l2_to_l1_messages: syscall_handler.l2_to_l1_messages,
+        storage_accesses: syscall_handler.storage_accesses,
+        prints,
+        resources,
+        trace,
+        sierra_trace,
+        inner_calls: syscall_handler.inner_calls,
+    })
+}
+
+/// Names the storage variables of the keys accessed by a run of a class.
+pub fn resolve_storage_accesses(
+    storage_accesses: &mut [StorageAccess],
+    class: &DeclaredClass,
+    vm: &VirtualMachine,
+) {
+    if class.storage_variables.is_empty() {
+        return;
+    }
+    let mut resolver = StorageResolver::new(&class.storage_variables);
+    resolver.add_pedersen_preimages(vm);
+    for access in storage_accesses {
+        access.variable = resolver.resolve(access.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::transaction_hash::calculate_contract_address;
+    use crate::rpc::types::ReplacedClass;
+    use crate::state::diff::state_diff;
+
+    #[test]
+    fn test_execute_nested_call() {
+        let code = r#"#[starknet::interface]
+        pub trait ICounter<TContractState> {
+            fn increase(ref self: TContractState, amount: felt252);
+            fn increase_other(ref self: TContractState, other: starknet::ContractAddress, amount: felt252);
+        }
+
+        #[starknet::contract]
+        mod Counter {
+            use super::{ICounterDispatcher, ICounterDispatcherTrait};
+
+            #[storage]
+            struct Storage {
+                counter: felt252,
+            }
+
+            #[abi(embed_v0)]
+            impl CounterImpl of super::ICounter<ContractState> {
+                fn increase(ref self: ContractState, amount: felt252) {
+                    self.counter.write(self.counter.read() + amount);
+                }
+
+                fn increase_other(ref self: ContractState, other: starknet::ContractAddress, amount: felt252) {
+                    ICounterDispatcher { contract_address: other }.increase(amount);
+                }
+            }
+        }
+        "#;
+        let mut state = State::default();
+        let class_hash = state.deploy_code(code, Felt252::ONE);
+        state.deploy_contract(Felt252::TWO, class_hash).unwrap();
+
+        let config = RunConfig::default();
+        let call = CallEntryPoint {
+            call_type: CallType::Call,
+            caller_address: Felt252::ZERO,
+            contract_address: Felt252::ONE,
+            class_hash: None,
+            entry_point_type: EntryPointType::External,
+            entry_point_selector: selector_from_name("increase_other"),
+            calldata: vec![Felt252::TWO, Felt252::from(5)],
+            initial_gas: config.initial_gas,
+        };
+        let call_info = execute_call(&mut state, &mut 0, &call, &config).unwrap();
+
+        assert_eq!(call_info.status, ExecutionStatus::Succeeded);
+        assert_eq!(call_info.function_name.as_deref(), Some("increase_other"));
+        assert_eq!(call_info.inner_calls.len(), 1);
+        let inner_call = &call_info.inner_calls[0];
+        assert_eq!(inner_call.caller_address, Felt252::ONE);
+        assert_eq!(inner_call.storage_accesses.len(), 2);
+        let write = &inner_call.storage_accesses[1];
+        assert_eq!(write.previous_value, Some(Felt252::ZERO));
+        assert_eq!(write.variable.as_ref().unwrap().name, "counter");
+        assert_eq!(state.storage.len(), 1);
+        assert_eq!(state.storage.values().next(), Some(&Felt252::from(5)));
+
+        // The inner call shares the steps of the outer one, one step short of both.
+        let max_steps = call_info.total_steps() - 1;
+        let config = RunConfig {
+            max_steps: Some(max_steps),
+            ..config
+        };
+        let call_info = execute_call(&mut state, &mut 0, &call, &config).unwrap();
+        assert_eq!(
+            call_info.status,
+            ExecutionStatus::StepLimitExceeded { max_steps }
+        );
+        assert_eq!(state.storage.values().next(), Some(&Felt252::from(5)));
+    }
+
+    #[test]
+    fn test_deploy_syscall() {
+        let code = r#"#[starknet::interface]
+        pub trait IFactory<TContractState> {
+            fn deploy(
+                ref self: TContractState,
+                class_hash: starknet::ClassHash,
+                salt: felt252,
+                value: felt252,
+                deploy_from_zero: bool
+            ) -> starknet::ContractAddress;
+            fn get_value(self: @TContractState) -> felt252;
+        }
+
+        #[starknet::contract]
+        mod Factory {
+            use starknet::{ClassHash, ContractAddress, SyscallResultTrait};
+            use starknet::syscalls::deploy_syscall;
+
+            #[storage]
+            struct Storage {
+                value: felt252,
+            }
+
+            #[constructor]
+            fn constructor(ref self: ContractState, value: felt252) {
+                self.value.write(value);
+            }
+
+            #[abi(embed_v0)]
+            impl FactoryImpl of super::IFactory<ContractState> {
+                fn deploy(
+                    ref self: ContractState,
+                    class_hash: ClassHash,
+                    salt: felt252,
+                    value: felt252,
+                    deploy_from_zero: bool
+                ) -> ContractAddress {
+                    let (address, _) = deploy_syscall(
+                        class_hash, salt, array![value].span(), deploy_from_zero
+                    )
+                        .unwrap_syscall();
+                    address
+                }
+
+                fn get_value(self: @ContractState) -> felt252 {
+                    self.value.read()
+                }
+            }
+        }
+        "#;
+        let mut state = State::default();
+        let factory = Felt252::ONE;
+        let class_hash = state.deploy_code(code, factory);
+
+        let config = RunConfig::default();
+        let call = |contract_address, entry_point: &str, calldata| CallEntryPoint {
+            call_type: CallType::Call,
+            caller_address: Felt252::ZERO,
+            contract_address,
+            class_hash: None,
+            entry_point_type: EntryPointType::External,
+            entry_point_selector: selector_from_name(entry_point),
+            calldata,
+            initial_gas: config.initial_gas,
+        };
+        let (salt, value) = (Felt252::from(7), Felt252::from(42));
+        for (deploy_from_zero, deployer_address) in
+            [(Felt252::ZERO, factory), (Felt252::ONE, Felt252::ZERO)]
+        {
+            let deploy = call(
+                factory,
+                "deploy",
+                vec![class_hash, salt, value, deploy_from_zero],
+            );
+            let call_info = execute_call(&mut state, &mut 0, &deploy, &config).unwrap();
+            assert_eq!(call_info.status, ExecutionStatus::Succeeded);
+            let contract_address =
+                calculate_contract_address(salt, class_hash, &[value], deployer_address);
+            assert_eq!(call_info.retdata, vec![contract_address]);
+
+            let constructor = &call_info.inner_calls[0];
+            assert_eq!(constructor.entry_point_type, EntryPointType::Constructor);
+            assert_eq!(constructor.caller_address, deployer_address);
+            let get_value = call(contract_address, "get_value", vec![]);
+            let call_info = execute_call(&mut state, &mut 0, &get_value, &config).unwrap();
+            assert_eq!(call_info.retdata, vec![value]);
+        }
+
+        // The address of a contract is only deployed once: the syscall fails, and
+        // `unwrap_syscall` panics with its reason.
+        let deploy = call(
+            factory,
+            "deploy",
+            vec![class_hash, salt, value, Felt252::ZERO],
+        );
+        let call_info = execute_call(&mut state, &mut 0, &deploy, &config).unwrap();
+        assert_eq!(call_info.status, ExecutionStatus::Reverted);
+        assert_eq!(
+            call_info.retdata,
+            vec![Felt252::from_bytes_be_slice(
+                b"CONTRACT_ADDRESS_UNAVAILABLE"
+            )]
+        );
+        assert!(call_info.inner_calls.is_empty());
+    }
+
+    #[test]
+    fn test_replace_class_syscall() {
+        let v1 = r#"#[starknet::contract]
+        mod V1 {
+            use starknet::{ClassHash, SyscallResultTrait};
+            use starknet::syscalls::replace_class_syscall;
+
+            #[storage]
+            struct Storage {}
+
+            #[external(v0)]
+            fn upgrade(ref self: ContractState, new_class_hash: ClassHash) {
+                replace_class_syscall(new_class_hash).unwrap_syscall();
+            }
+
+            #[external(v0)]
+            fn version(self: @ContractState) -> felt252 {
+                1
+            }
+        }
+        "#;
+        let v2 = r#"#[starknet::contract]
+        mod V2 {
+            #[storage]
+            struct Storage {}
+
+            #[external(v0)]
+            fn version(self: @ContractState) -> felt252 {
+                2
+            }
+        }
+        "#;
+        let mut state = State::default();
+        let (v1_class_hash, v2_class_hash) = (state.declare_code(v1), state.declare_code(v2));
+        let contract_address = Felt252::ONE;
+        state
+            .deploy_contract(contract_address, v1_class_hash)
+            .unwrap();
+        let previous_state = state.clone();
+
+        let config = RunConfig::default();
+        let call = |entry_point: &str, calldata| CallEntryPoint {
+            call_type: CallType::Call,
+            caller_address: Felt252::ZERO,
+            contract_address,
+            class_hash: None,
+            entry_point_type: EntryPointType::External,
+            entry_point_selector: selector_from_name(entry_point),
+            calldata,
+            initial_gas: config.initial_gas,
+        };
+        let upgrade = call("upgrade", vec![v2_class_hash]);
+        let call_info = execute_call(&mut state, &mut 0, &upgrade, &config).unwrap();
+        assert_eq!(call_info.status, ExecutionStatus::Succeeded);
+        assert_eq!(
+            state.get_class_hash_at(&contract_address).unwrap(),
+            v2_class_hash
+        );
+        let call_info =
+            execute_call(&mut state, &mut 0, &call("version", vec![]), &config).unwrap();
+        assert_eq!(call_info.retdata, vec![Felt252::TWO]);
+
+        let diff = state_diff(&previous_state, &state).unwrap();
+        assert_eq!(
+            diff.replaced_classes,
+            vec![ReplacedClass {
+                contract_address,
+                class_hash: v2_class_hash,
+            }]
+        );
+        assert!(diff.deployed_contracts.is_empty());
+    }
+
+    #[test]
+    fn test_builtins_missing_from_layout() {
+        let code = r#"#[starknet::contract]
+        mod Counter {
+            #[storage]
+            struct Storage {
+                counter: u128,
+            }
+
+            #[external(v0)]
+            fn increase(ref self: ContractState, amount: u128) {
+                self.counter.write(self.counter.read() + amount);
+            }
+        }
+        "#;
+        let mut state = State::default();
+        state.deploy_code(code, Felt252::ONE);
+
+        // The plain layout has no range check builtin.
+        let config = RunConfig {
+            layout: "plain".to_string(),
+            ..RunConfig::default()
+        };
+        let call = CallEntryPoint {
+            call_type: CallType::Call,
+            caller_address: Felt252::ZERO,
+            contract_address: Felt252::ONE,
+            class_hash: None,
+            entry_point_type: EntryPointType::External,
+            entry_point_selector: selector_from_name("increase"),
+            calldata: vec![Felt252::from(5)],
+            initial_gas: config.initial_gas,
+        };
+        assert!(execute_call(&mut state, &mut 0, &call, &config).is_err());
+        assert!(state.storage.is_empty());
+    }
+
+    #[test]
+    fn test_parse_entry_point_selector() {
+        assert_eq!(
+            parse_entry_point_selector("transfer").unwrap(),
+            selector_from_name("transfer")
+        );
+        assert_eq!(
+            parse_entry_point_selector("0x10").unwrap(),
+            Felt252::from(16)
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use std::any::Any;
+use std::collections::HashMap;
+
+use cairo_lang_casm_2_point_6::hints::{CoreHint, CoreHintBase, Hint, StarknetHint};
+use cairo_lang_casm_2_point_6::operand::{
+    BinOpOperand, CellRef, DerefOrImmediate, Operation, Register, ResOperand,
+};
+use cairo_vm::{
+    hint_processor::{
+        cairo_1_hint_processor::hint_processor::Cairo1HintProcessor,
+        hint_processor_definition::{HintProcessorLogic, HintReference},
+    },
+    serde::deserialize_program::ApTracking,
+    types::{exec_scope::ExecutionScopes, relocatable::Relocatable},
+    vm::{
+        errors::{hint_errors::HintError, vm_errors::VirtualMachineError},
+        runners::cairo_runner::{ResourceTracker, RunResources},
+        vm_core::VirtualMachine,
+    },
+};
+use num_traits::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use starknet_types_core::felt::Felt as Felt252;
+
+use super::short_string::as_short_string;
+use super::sierra_trace::SierraDebugInfo;
+use super::syscalls::SyscallHandler;
+use crate::cairo_sierra::cairo_helper::CairoLocation;
+
+/// The first felt of a `ByteArray` serialized by `print`/`println!`.
+const BYTE_ARRAY_MAGIC: &str = "0x46a6158a16a947e5916b2a2ca68501a45e93d7110e81aa2d6438b1c57c879a3";
+
+/// Number of bytes held by a full word of a `ByteArray`.
+const BYTES_IN_WORD: usize = 31;
+
+/// Output of a `print`/`println!` call.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrintOutput {
+    /// The program counter offset of the print hint.
+    pub pc: usize,
+    /// The printed felts, as hex.
+    pub values: Vec<String>,
+    /// The printed values, with `ByteArray`s and short strings decoded.
+    pub text: String,
+    /// The Cairo code that printed the values.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<CairoLocation>,
+}
+
+/// A hint processor running Cairo 1 hints, executing syscalls and recording what the execution
+/// prints.
+pub struct TracingHintProcessor {
+    inner: Cairo1HintProcessor,
+    pub syscall_handler: SyscallHandler,
+    pub prints: Vec<PrintOutput>,
+    /// The steps left to the run, from the `max_steps` of the handler's config. The steps of the
+    /// inner calls made by syscalls are taken from them.
+    run_resources: RunResources,
+}
+
+impl TracingHintProcessor {
+    pub fn new(hints: &[(usize, Vec<Hint>)], syscall_handler: SyscallHandler) -> Self {
+        Self {
+            inner: Cairo1HintProcessor::new(hints, RunResources::default()),
+            run_resources: syscall_handler.config.run_resources(),
+            syscall_handler,
+            prints: vec![],
+        }
+    }
+
+    /// Tags the recorded prints with the Cairo location of the code that emitted them.
+    pub fn take_prints(&mut self, debug_info: Option<&SierraDebugInfo>) -> Vec<PrintOutput> {
+        let mut prints = std::mem::take(&mut self.prints);
+        if let Some(debug_info) = debug_info {
+            for print in prints.iter_mut() {
+                print.location = debug_info
+                    .statement_at(print.pc)
+                    .and_then(|idx| debug_info.cairo_locations(idx))
+                    .and_then(|locations| locations.first().cloned());
+            }
+        }
+        prints
+    }
+
+    fn record_print(
+        &mut self,
+        vm: &VirtualMachine,
+        start: &ResOperand,
+        end: &ResOperand,
+    ) -> Result<(), HintError> {
+        let start = get_buffer_ptr(vm, start)?;
+        let end = get_buffer_ptr(vm, end)?;
+        let values: Vec<Felt252> = vm
+            .get_integer_range(start, (end - start)?)?
+            .into_iter()
+            .map(|value| value.into_owned())
+            .collect();
+        self.prints.push(PrintOutput {
+            pc: vm.get_pc().offset,
+            values: values.iter().map(|value| value.to_hex_string()).collect(),
+            text: format_print(&values),
+            location: None,
+        });
+        Ok(())
+    }
+}
+
+impl HintProcessorLogic for TracingHintProcessor {
+    fn compile_hint(
+        &self,
+        hint_code: &str,
+        ap_tracking_data: &ApTracking,
+        reference_ids: &HashMap<String, usize>,
+        references: &[HintReference],
+    ) -> Result<Box<dyn Any>, VirtualMachineError> {
+        self.inner
+            .compile_hint(hint_code, ap_tracking_data, reference_ids, references)
+    }
+
+    fn execute_hint(
+        &mut self,
+        vm: &mut VirtualMachine,
+        exec_scopes: &mut ExecutionScopes,
+        hint_data: &Box<dyn Any>,
+        constants: &HashMap<String, Felt252>,
+    ) -> Result<(), HintError> {
+        let Some(hints) = hint_data.downcast_ref::<Vec<Hint>>() else {
+            return self
+                .inner
+                .execute_hint(vm, exec_scopes, hint_data, constants);
+        };
+        for hint in hints {
+            match hint {
+                Hint::Starknet(StarknetHint::SystemCall { system }) => {
+                    let syscall_ptr = get_buffer_ptr(vm, system)?;
+                    self.syscall_handler.remaining_steps = self.run_resources.get_n_steps();
+                    self.syscall_handler.execute(vm, syscall_ptr)?;
+                    if let Some(n_steps) = self.syscall_handler.remaining_steps {
+                        self.run_resources = RunResources::new(n_steps);
+                    }
+                }
+                _ => {
+                    if let Hint::Core(CoreHintBase::Core(CoreHint::DebugPrint { start, end })) =
+                        hint
+                    {
+                        self.record_print(vm, start, end)?;
+                    }
+                    let hint_data: Box<dyn Any> = Box::new(vec![hint.clone()]);
+                    self.inner
+                        .execute_hint(vm, exec_scopes, &hint_data, constants)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ResourceTracker for TracingHintProcessor {
+    fn consumed(&self) -> bool {
+        self.run_resources.consumed()
+    }
+
+    fn consume_step(&mut self) {
+        self.run_resources.consume_step()
+    }
+
+    fn get_n_steps(&self) -> Option<usize> {
+        self.run_resources.get_n_steps()
+    }
+
+    fn run_resources(&self) -> &RunResources {
+        &self.run_resources
+    }
+}
+
+/// Formats printed felts the way the Cairo runner does: serialized `ByteArray`s as text and
+/// other felts as short strings when possible, as hex otherwise, one per line.
+pub fn format_print(values: &[Felt252]) -> String {
+    let magic = Felt252::from_hex_unchecked(BYTE_ARRAY_MAGIC);
+    let mut text = String::new();
+    let mut i = 0;
+    while i < values.len() {
+        if values[i] == magic {
+            if let Some((byte_array, len)) = read_byte_array(&values[i + 1..]) {
+                text.push_str(&byte_array);
+                i += 1 + len;
+                continue;
+            }
+        }
+        match as_short_string(&values[i].to_biguint()) {
+            Some(short_string) => text.push_str(&short_string),
+            None => text.push_str(&values[i].to_hex_string()),
+        }
+        text.push('\n');
+        i += 1;
+    }
+    text
+}
+
+/// Reads a serialized `ByteArray`: the number of full words, the full words, the pending word
+/// and its length. Returns the string and the number of felts it spans.
+pub fn read_byte_array(values: &[Felt252]) -> Option<(String, usize)> {
+    let num_full_words = values.first()?.to_usize()?;
+    let full_words = values.get(1..1 + num_full_words)?;
+    let pending_word = values.get(1 + num_full_words)?;
+    let pending_word_len = values.get(2 + num_full_words)?.to_usize()?;
+    if pending_word_len >= BYTES_IN_WORD {
+        return None;
+    }
+
+    let mut bytes = vec![];
+    for word in full_words {
+        bytes.extend_from_slice(&word.to_bytes_be()[32 - BYTES_IN_WORD..]);
+    }
+    bytes.extend_from_slice(&pending_word.to_bytes_be()[32 - pending_word_len..]);
+    Some((
+        String::from_utf8_lossy(&bytes).into_owned(),
+        3 + num_full_words,
+    ))
+}
+
+/// Returns the pointer held by a buffer operand, `[cell]` or `[cell] + offset`.
+pub fn get_buffer_ptr(vm: &VirtualMachine, buffer: &ResOperand) -> Result<Relocatable, HintError> {
+    let (cell, offset) = match buffer {
+        ResOperand::Deref(cell) => (cell, 0),
+        ResOperand::BinOp(BinOpOperand {
+            op: Operation::Add,
+            a,
+            b: DerefOrImmediate::Immediate(offset),
+        }) => (
+            a,
+            offset
+                .value
+                .to_usize()
+                .ok_or_else(|| HintError::CustomHint("Invalid buffer offset.".into()))?,
+        ),
+        _ => return Err(HintError::CustomHint("Unsupported buffer operand.".into())),
+    };
+    let ptr = vm.get_relocatable(cell_ref_to_relocatable(vm, cell)?)?;
+    Ok((ptr + offset)?)
+}
+
+/// Returns the address of a cell, relative to `ap` or `fp`.
+fn cell_ref_to_relocatable(vm: &VirtualMachine, cell: &CellRef) -> Result<Relocatable, HintError> {
+    let base = match cell.register {
+        Register::AP => vm.get_ap(),
+        Register::FP => vm.get_fp(),
+    };
+    Ok((base + cell.offset as i32)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_print() {
+        let hello = Felt252::from_bytes_be_slice(b"hello");
+        let values = vec![
+            Felt252::from_hex_unchecked(BYTE_ARRAY_MAGIC),
+            Felt252::ZERO,
+            hello,
+            Felt252::from(5),
+            Felt252::from_bytes_be_slice(b"felt"),
+            Felt252::from(1),
+        ];
+        assert_eq!(format_print(&values), "hellofelt\n0x1\n");
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use cairo_vm::vm::{
+    errors::trace_errors::TraceError, runners::cairo_runner::CairoRunner,
+    trace::trace_entry::RelocatedTraceEntry, vm_core::VirtualMachine,
+};
+use serde::{Deserialize, Serialize};
+use starknet_types_core::felt::Felt as Felt252;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemoryCell {
+    pub address: usize,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SegmentInfo {
+    pub index: isize,
+    pub name: String,
+    /// The relocated address of the first cell of the segment.
+    pub base: usize,
+    pub size: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BuiltinUsage {
+    pub name: String,
+    pub segment_index: usize,
+    pub used_cells: usize,
+    pub used_instances: usize,
+}
+
+/// The relocated memory of an execution, along with its segment layout.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemoryDump {
+    pub memory: Vec<MemoryCell>,
+    pub segments: Vec<SegmentInfo>,
+    pub builtins: Vec<BuiltinUsage>,
+    /// The trace in the cairo-vm binary trace file format, hex encoded.
+    pub encoded_trace: String,
+    /// The memory in the cairo-vm binary memory file format, hex encoded.
+    pub encoded_memory: String,
+}
+
+/// Relocates the memory and trace of a finished run and collects its segment layout.
+///
+/// `segment_names` names the segments created by the caller; the program, execution and builtin
+/// segments are named automatically and any other segment is reported as `temporary`.
+pub fn dump_memory(
+    runner: &mut CairoRunner,
+    vm: &mut VirtualMachine,
+    segment_names: &HashMap<isize, &str>,
+) -> Result<MemoryDump, TraceError> {
+    vm.segments.compute_effective_sizes();
+    let relocation_table = vm.segments.relocate_segments()?;
+    runner.relocate(vm, true)?;
+
+    let mut builtins = vec![];
+    let mut names: HashMap<isize, String> =
+        HashMap::from([(0, "program".to_string()), (1, "execution".to_string())]);
+    for builtin in vm.get_builtin_runners() {
+        let name = builtin.name().to_str().to_string();
+        names.insert(builtin.base() as isize, name.clone());
+        builtins.push(BuiltinUsage {
+            name,
+            segment_index: builtin.base(),
+            used_cells: builtin.get_used_cells(&vm.segments)?,
+            used_instances: builtin.get_used_instances(&vm.segments)?,
+        });
+    }
+    for (index, name) in segment_names {
+        names.insert(*index, name.to_string());
+    }
+
+    let segments = (0..vm.segments.num_segments())
+        .map(|index| SegmentInfo {
+            index: index as isize,
+            name: names
+                .get(&(index as isize))
+                .cloned()
+                .unwrap_or_else(|| "temporary".to_string()),
+            base: relocation_table[index],
+            size: vm.segments.get_segment_used_size(index).unwrap_or_default(),
+        })
+        .collect();
+
+    let memory = runner
+        .relocated_memory
+        .iter()
+        .enumerate()
+        .filter_map(|(address, value)| {
+            value.as_ref().map(|value| MemoryCell {
+                address,
+                value: value.to_hex_string(),
+            })
+        })
+        .collect();
+
+    let trace = runner.relocated_trace.as_deref().unwrap_or_default();
+    Ok(MemoryDump {
+        memory,
+        segments,
+        builtins,
+        encoded_trace: hex::encode(encode_trace(trace)),
+        encoded_memory: hex::encode(encode_memory(&runner.relocated_memory)),
+    })
+}
+
+/// Encodes a relocated trace in the cairo-vm binary trace file format: `ap`, `fp` and `pc` of
+/// every step as little endian u64s.
+pub fn encode_trace(trace: &[RelocatedTraceEntry]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(trace.len() * 24);
+    for entry in trace {
+        encoded.extend((entry.ap as u64).to_le_bytes());
+        encoded.extend((entry.fp as u64).to_le_bytes());
+        encoded.extend((entry.pc as u64).to_le_bytes());
+    }
+    encoded
+}
+
+/// Encodes relocated memory in the cairo-vm binary memory file format: the address of every
+/// assigned cell as a little endian u64, followed by its value as a 32 byte little endian felt.
+pub fn encode_memory(memory: &[Option<Felt252>]) -> Vec<u8> {
+    let mut encoded = vec![];
+    for (address, value) in memory.iter().enumerate() {
+        if let Some(value) = value {
+            encoded.extend((address as u64).to_le_bytes());
+            encoded.extend(value.to_bytes_le());
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_trace() {
+        let trace = vec![RelocatedTraceEntry {
+            pc: 1,
+            ap: 2,
+            fp: 3,
+        }];
+        let encoded = encode_trace(&trace);
+        assert_eq!(encoded.len(), 24);
+        assert_eq!(encoded[0], 2);
+        assert_eq!(encoded[8], 3);
+        assert_eq!(encoded[16], 1);
+    }
+
+    #[test]
+    fn test_encode_memory_skips_holes() {
+        let memory = vec![None, Some(Felt252::from(5)), None];
+        let encoded = encode_memory(&memory);
+        assert_eq!(encoded.len(), 40);
+        assert_eq!(encoded[0], 1);
+        assert_eq!(encoded[8], 5);
+    }
+}
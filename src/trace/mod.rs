@@ -1 +1,16 @@
+pub mod abi_decoder;
 pub mod cairo_runner;
+pub mod config;
+pub mod events;
+pub mod execution;
+pub mod hint_processor;
+pub mod memory;
+pub mod proof;
+pub mod resources;
+pub mod run;
+pub mod short_string;
+pub mod sierra_trace;
+pub mod storage;
+pub mod syscalls;
+pub mod test_runner;
+pub mod versioned_constants;
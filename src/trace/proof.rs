@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use cairo_lang_casm::casm;
+use cairo_lang_starknet_classes_2_point_6::casm_contract_class::CasmContractClass;
+use cairo_vm::serde::deserialize_program::{
+    ApTracking, FlowTrackingData, HintParams, ReferenceManager,
+};
+use cairo_vm::types::program::Program;
+use cairo_vm::types::relocatable::{MaybeRelocatable, Relocatable};
+use cairo_vm::vm::errors::vm_errors::VirtualMachineError;
+use cairo_vm::vm::runners::cairo_runner::CairoRunner;
+use cairo_vm::vm::vm_core::VirtualMachine;
+use serde::{Deserialize, Serialize};
+use starknet_types_core::felt::Felt as Felt252;
+
+use super::cairo_runner::get_casm_contract_builtins;
+use super::config::RunConfig;
+use super::hint_processor::TracingHintProcessor;
+use super::memory::{encode_memory, encode_trace};
+use super::syscalls::SyscallHandler;
+use crate::state::State;
+
+/// Number of values returned by an entrypoint after its builtins: gas, system, the panic flag
+/// and the retdata span.
+const ENTRYPOINT_NON_BUILTIN_RETURN_VALUES: usize = 5;
+
+/// Number of arguments of an entrypoint after its builtins: gas, system and the calldata span.
+const ENTRYPOINT_NON_BUILTIN_ARGS: usize = 4;
+
+/// A `ret` instruction, placed right after the contract code so that the code can locate the
+/// builtin costs that follow it.
+const RET_INSTRUCTION: u64 = 0x208B7FFF7FFF7FFE;
+
+/// The trace and memory files the AIR private input refers to, relative to the prover.
+pub const TRACE_FILE_NAME: &str = "trace.bin";
+pub const MEMORY_FILE_NAME: &str = "memory.bin";
+
+/// The inputs a Stone/Stwo prover needs to prove an entrypoint run.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProofInputs {
+    pub layout: String,
+    pub n_steps: usize,
+    pub air_public_input: serde_json::Value,
+    pub air_private_input: serde_json::Value,
+    /// The trace in the cairo-vm binary trace file format, hex encoded.
+    pub encoded_trace: String,
+    /// The memory in the cairo-vm binary memory file format, hex encoded.
+    pub encoded_memory: String,
+}
+
+/// A contract entrypoint wrapped into a program runnable in proof mode.
+struct ProofModeProgram {
+    program: Program,
+    /// The hints of the contract, at their offset in the program.
+    hints: Vec<(usize, Vec<cairo_lang_casm_2_point_6::hints::Hint>)>,
+    n_builtins: usize,
+}
+
+/// Wraps an entrypoint into a proof mode program: an entry code calling the entrypoint with the
+/// builtins the runner puts on the stack and the arguments loaded after them, then copying the
+/// returned builtins to the top of the stack and ending in a `jmp rel 0` loop, followed by the
+/// contract code and the builtin costs.
+fn proof_mode_program(
+    casm_contract_class: &CasmContractClass,
+    entrypoint_offset: usize,
+    config: &RunConfig,
+) -> Result<ProofModeProgram> {
+    let builtins = get_casm_contract_builtins(casm_contract_class, entrypoint_offset)?;
+    let n_builtins = builtins.len();
+
+    // The entrypoint returns its builtins first: each copy pushes one of them, so the next one
+    // is at the same offset from `ap`.
+    let returned_builtins_offset = (n_builtins + ENTRYPOINT_NON_BUILTIN_RETURN_VALUES) as i16;
+    let mut footer = casm! {};
+    for _ in 0..n_builtins {
+        cairo_lang_casm::casm_extend! {footer,
+            [ap + 0] = [ap - returned_builtins_offset], ap++;
+        };
+    }
+    let end = footer.current_code_offset;
+    cairo_lang_casm::casm_extend! {footer,
+        jmp rel 0;
+    };
+    let header = casm! {
+        ap += ((n_builtins + ENTRYPOINT_NON_BUILTIN_ARGS) as i128);
+    };
+    // `call rel` takes two words.
+    let call_offset = header.current_code_offset;
+    let code_offset = call_offset + 2 + footer.current_code_offset;
+    let call = casm! {
+        call rel ((code_offset + entrypoint_offset - call_offset) as i128);
+    };
+    let entry_code: Vec<MaybeRelocatable> = header
+        .instructions
+        .iter()
+        .chain(&call.instructions)
+        .chain(&footer.instructions)
+        .flat_map(|instruction| instruction.assemble().encode())
+        .map(|word| MaybeRelocatable::from(Felt252::from(word)))
+        .collect();
+
+    let mut data = entry_code;
+    data.extend(casm_contract_class.bytecode.iter().map(|word| {
+        MaybeRelocatable::from(Felt252::from_bytes_be_slice(&word.value.to_bytes_be()))
+    }));
+    // The code reads the builtin costs through a pointer stored after the `ret` that follows it.
+    let builtin_costs_offset = data.len() + 2;
+    data.push(Felt252::from(RET_INSTRUCTION).into());
+    data.push(Relocatable::from((0, builtin_costs_offset)).into());
+    data.extend(
+        config
+            .builtin_costs()
+            .to_vec()
+            .into_iter()
+            .map(|cost| MaybeRelocatable::from(Felt252::from(cost))),
+    );
+
+    let hints: Vec<_> = casm_contract_class
+        .hints
+        .iter()
+        .map(|(pc, hints)| (pc + code_offset, hints.clone()))
+        .collect();
+    let hint_params = hints
+        .iter()
+        .map(|(pc, _)| {
+            let params = HintParams {
+                code: pc.to_string(),
+                accessible_scopes: vec![],
+                flow_tracking_data: FlowTrackingData {
+                    ap_tracking: ApTracking::default(),
+                    reference_ids: HashMap::new(),
+                },
+            };
+            (*pc, vec![params])
+        })
+        .collect();
+    let program = Program::new_for_proof(
+        builtins,
+        data,
+        0,
+        call_offset + 2 + end,
+        hint_params,
+        ReferenceManager { references: vec![] },
+        HashMap::new(),
+        vec![],
+        None,
+    )
+    .map_err(|e| anyhow::anyhow!("Invalid program: {}", e))?;
+
+    Ok(ProofModeProgram {
+        program,
+        hints,
+        n_builtins,
+    })
+}
+
+/// Runs a contract entrypoint in proof mode and generates the AIR inputs of the run.
+///
+/// The run starts with the builtin pointers on the stack and ends in an infinite loop, whose
+/// steps pad the trace to a power of two, so that its inputs can be proven as is.
+///
+/// Nothing is written to disk: the AIR private input refers to the trace and memory as
+/// `TRACE_FILE_NAME` and `MEMORY_FILE_NAME`, the files the caller writes them to.
+pub fn generate_proof_inputs(
+    casm_contract_class: CasmContractClass,
+    entrypoint_offset: usize,
+    args: &[MaybeRelocatable],
+    config: &RunConfig,
+) -> Result<ProofInputs> {
+    let ProofModeProgram {
+        program,
+        hints,
+        n_builtins,
+    } = proof_mode_program(&casm_contract_class, entrypoint_offset, config)?;
+    let mut hint_processor = TracingHintProcessor::new(
+        &hints,
+        SyscallHandler::new(config, State::default(), Felt252::ZERO, Felt252::ZERO),
+    );
+    let mut runner = CairoRunner::new(&program, config.layout()?, true)
+        .map_err(|e| anyhow::anyhow!("Failed to create the runner: {}", e))?;
+    let mut vm = VirtualMachine::new(true);
+    let end = runner.initialize(&mut vm, false).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to initialize the builtins {:?} in layout {}: {}",
+            runner.get_program_builtins(),
+            config.layout,
+            e
+        )
+    })?;
+
+    // The runner put the builtins on the stack: the other arguments follow them.
+    let syscall_segment = vm.add_memory_segment();
+    let calldata_start = vm.add_memory_segment();
+    let calldata_end = vm.load_data(calldata_start, &args.to_vec())?;
+    vm.load_data(
+        (vm.get_ap() + n_builtins)?,
+        &vec![
+            Felt252::from(config.initial_gas).into(),
+            syscall_segment.into(),
+            calldata_start.into(),
+            calldata_end.into(),
+        ],
+    )?;
+
+    match runner.run_until_pc(end, &mut vm, &mut hint_processor) {
+        Ok(()) => {}
+        Err(VirtualMachineError::UnfinishedExecution) => anyhow::bail!(
+            "The entrypoint did not finish within {:?} steps.",
+            config.max_steps
+        ),
+        Err(e) => return Err(e.into()),
+    }
+    runner.end_run(false, false, &mut vm, &mut hint_processor)?;
+    runner.read_return_values(&mut vm, false)?;
+    runner.finalize_segments(&mut vm)?;
+    runner.relocate(&mut vm, true)?;
+
+    let trace = runner
+        .relocated_trace
+        .as_ref()
+        .with_context(|| "Missing relocated trace.")?;
+    let n_steps = trace.len();
+    let encoded_trace = encode_trace(trace);
+    let encoded_memory = encode_memory(&runner.relocated_memory);
+
+    let air_public_input = runner
+        .get_air_public_input(&vm)
+        .with_context(|| "Failed to generate the AIR public input.")?
+        .serialize_json()?;
+
+    let air_private_input = runner
+        .get_air_private_input(&vm)
+        .to_serializable(TRACE_FILE_NAME.to_string(), MEMORY_FILE_NAME.to_string())
+        .serialize_json()?;
+
+    Ok(ProofInputs {
+        layout: config.layout.clone(),
+        n_steps,
+        air_public_input: serde_json::from_str(&air_public_input)?,
+        air_private_input: serde_json::from_str(&air_private_input)?,
+        encoded_trace: hex::encode(encoded_trace),
+        encoded_memory: hex::encode(encoded_memory),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::compile_contract::compile_contract;
+    use crate::state::DeclaredClass;
+
+    #[test]
+    fn test_generate_proof_inputs() {
+        let code = r#"#[starknet::contract]
+        mod Hasher {
+            use core::pedersen::pedersen;
+
+            #[storage]
+            struct Storage {}
+
+            #[external(v0)]
+            fn hash(self: @ContractState, a: felt252, b: felt252) -> felt252 {
+                pedersen(a, b)
+            }
+        }
+        "#;
+        let compilation_result = compile_contract(code, "hasher").unwrap();
+        let casm_contract_class = DeclaredClass::from_compilation_result(compilation_result)
+            .unwrap()
+            .casm_contract_class;
+        let entrypoint_offset = casm_contract_class.entry_points_by_type.external[0].offset;
+
+        let proof_inputs = generate_proof_inputs(
+            casm_contract_class,
+            entrypoint_offset,
+            &[Felt252::ONE.into(), Felt252::TWO.into()],
+            &RunConfig::default(),
+        )
+        .unwrap();
+        let public_input = &proof_inputs.air_public_input;
+        assert!(proof_inputs.n_steps.is_power_of_two());
+        assert_eq!(public_input["n_steps"], proof_inputs.n_steps);
+        assert_eq!(public_input["layout"], "all_cairo");
+        assert_eq!(
+            proof_inputs.encoded_trace.len(),
+            proof_inputs.n_steps * 3 * 8 * 2
+        );
+    }
+}
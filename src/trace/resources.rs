@@ -0,0 +1,475 @@
+use std::collections::{BTreeMap, HashSet};
+
+use cairo_vm::vm::{runners::cairo_runner::CairoRunner, vm_core::VirtualMachine};
+use serde::{Deserialize, Serialize};
+use starknet_types_core::felt::Felt as Felt252;
+use starknet_types_core::hash::{Pedersen, StarkHash};
+
+use super::abi_decoder::selector_from_name;
+use super::config::RunConfig;
+use super::execution::{CallInfo, MessageToL1};
+use super::versioned_constants::FeeCosts;
+use crate::rpc::invoke::TransactionType;
+use crate::rpc::types::StateDiff;
+
+/// The costs below are in 10^-4 L1 gas.
+const FEE_COST_DENOMINATOR: u64 = 10_000;
+
+/// Ethereum gas costs of publishing data and logs on L1.
+const GAS_PER_MEMORY_BYTE: u64 = 16;
+const GAS_PER_ZERO_BYTE: u64 = 4;
+const WORD_WIDTH: u64 = 32;
+const GAS_PER_MEMORY_WORD: u64 = GAS_PER_MEMORY_BYTE * WORD_WIDTH;
+const SHARP_GAS_PER_MEMORY_WORD: u64 = GAS_PER_MEMORY_WORD + 100;
+const GAS_PER_LOG: u64 = 375;
+const GAS_PER_LOG_TOPIC: u64 = 375;
+const GAS_PER_LOG_DATA_WORD: u64 = 8 * WORD_WIDTH;
+
+/// The blob gas of a felt of state diff: a blob holds 4096 felts for 2^17 blob gas.
+const DATA_GAS_PER_FELT: u64 = 32;
+
+/// The header of an L2 to L1 message in the OS output: sender, recipient and payload size.
+const L2_TO_L1_MESSAGE_HEADER_SIZE: usize = 3;
+
+/// The topics and non-payload data words of the `LogMessageToL1` event of the Starknet core
+/// contract, on top of its selector.
+const LOG_MESSAGE_TO_L1_N_TOPICS: u64 = 2;
+const LOG_MESSAGE_TO_L1_DATA_SIZE: u64 = 2;
+
+/// The resources a run used, as counted by the Starknet OS.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionResources {
+    pub n_steps: usize,
+    pub n_memory_holes: usize,
+    /// The number of instances of each builtin used, by name with its `_builtin` suffix.
+    pub builtin_instance_counter: BTreeMap<String, usize>,
+    /// The number of calls of each syscall.
+    pub syscall_counter: BTreeMap<String, usize>,
+}
+
+impl ExecutionResources {
+    /// The resources of a finished run. The memory holes and builtins of a run stopped by the
+    /// step limit are unknown: only its steps are counted.
+    pub fn from_run(
+        runner: &CairoRunner,
+        vm: &VirtualMachine,
+        n_steps: usize,
+        syscall_counter: BTreeMap<String, usize>,
+    ) -> Self {
+        match runner.get_execution_resources(vm) {
+            Ok(resources) => Self {
+                n_steps: resources.n_steps,
+                n_memory_holes: resources.n_memory_holes,
+                builtin_instance_counter: resources
+                    .builtin_instance_counter
+                    .into_iter()
+                    .filter(|(_, count)| *count > 0)
+                    .map(|(name, count)| (format!("{}_builtin", name.to_str()), count))
+                    .collect(),
+                syscall_counter,
+            },
+            Err(_) => Self {
+                n_steps,
+                syscall_counter,
+                ..Self::default()
+            },
+        }
+    }
+
+    pub fn add(&mut self, other: &Self) {
+        self.n_steps += other.n_steps;
+        self.n_memory_holes += other.n_memory_holes;
+        for (name, count) in &other.builtin_instance_counter {
+            *self
+                .builtin_instance_counter
+                .entry(name.clone())
+                .or_default() += count;
+        }
+        for (name, count) in &other.syscall_counter {
+            *self.syscall_counter.entry(name.clone()).or_default() += count;
+        }
+    }
+
+    /// The resources multiplied by `factor`, syscalls excluded.
+    pub fn multiply(&self, factor: usize) -> Self {
+        Self {
+            n_steps: self.n_steps * factor,
+            n_memory_holes: self.n_memory_holes * factor,
+            builtin_instance_counter: self
+                .builtin_instance_counter
+                .iter()
+                .map(|(name, count)| (name.clone(), count * factor))
+                .collect(),
+            syscall_counter: BTreeMap::new(),
+        }
+    }
+
+    /// The resources of a call and of all the calls it made.
+    pub fn of_call_tree(call_info: &CallInfo) -> Self {
+        let mut resources = call_info.resources.clone();
+        for inner_call in &call_info.inner_calls {
+            resources.add(&Self::of_call_tree(inner_call));
+        }
+        resources
+    }
+}
+
+/// The number of state changes of a transaction, which sets the size of its state diff.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct StateChangesCount {
+    pub n_storage_updates: usize,
+    /// Contracts deployed or whose class was replaced.
+    pub n_class_hash_updates: usize,
+    /// Classes declared.
+    pub n_compiled_class_hash_updates: usize,
+    pub n_modified_contracts: usize,
+}
+
+impl StateChangesCount {
+    /// Counts the changes of a state diff, and the update of the fee token balance of the
+    /// account paying the fee, given as the fee token and account addresses, if any. The fee
+    /// balance only counts as a new storage update if the diff does not already change it.
+    pub fn new(state_diff: &StateDiff, fee_balance: Option<(Felt252, Felt252)>) -> Self {
+        let mut modified_contracts: HashSet<Felt252> = state_diff
+            .storage_diffs
+            .iter()
+            .map(|diff| diff.address)
+            .chain(
+                state_diff
+                    .deployed_contracts
+                    .iter()
+                    .map(|contract| contract.address),
+            )
+            .chain(
+                state_diff
+                    .replaced_classes
+                    .iter()
+                    .map(|class| class.contract_address),
+            )
+            .chain(
+                state_diff
+                    .nonces
+                    .iter()
+                    .map(|update| update.contract_address),
+            )
+            .collect();
+        let mut storage_keys: HashSet<(Felt252, Felt252)> = state_diff
+            .storage_diffs
+            .iter()
+            .flat_map(|diff| {
+                diff.storage_entries
+                    .iter()
+                    .map(|entry| (diff.address, entry.key))
+            })
+            .collect();
+        if let Some((fee_token_address, account_address)) = fee_balance {
+            storage_keys.insert((fee_token_address, fee_balance_key(account_address)));
+            modified_contracts.insert(fee_token_address);
+        }
+        Self {
+            n_storage_updates: storage_keys.len(),
+            n_class_hash_updates: state_diff.deployed_contracts.len()
+                + state_diff.replaced_classes.len(),
+            n_compiled_class_hash_updates: state_diff.declared_classes.len(),
+            n_modified_contracts: modified_contracts.len(),
+        }
+    }
+
+    /// The number of felts of the state diff published on L1.
+    pub fn state_diff_size(&self) -> usize {
+        // An address and a header word per contract, a class hash per deployment or
+        // replacement, a key and a value per storage update, and a class hash and a compiled
+        // class hash per declaration.
+        self.n_modified_contracts * 2
+            + self.n_class_hash_updates
+            + self.n_storage_updates * 2
+            + self.n_compiled_class_hash_updates * 2
+    }
+}
+
+/// The storage key of the low word of the fee token balance of an account, in the
+/// `ERC20_balances` map of the fee token.
+pub fn fee_balance_key(account_address: Felt252) -> Felt252 {
+    Pedersen::hash(&selector_from_name("ERC20_balances"), &account_address)
+}
+
+/// Gas amounts of the resources paid by a transaction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct GasVector {
+    pub l1_gas: u128,
+    pub l1_data_gas: u128,
+    pub l2_gas: u128,
+}
+
+/// The resources used by a transaction, and the gas they cost.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TransactionResources {
+    /// The VM resources of all the calls of the transaction, and those the OS spends on the
+    /// transaction and its syscalls.
+    pub computation: ExecutionResources,
+    /// The Sierra gas consumed by the calls.
+    pub l2_gas: u64,
+    pub state_changes: StateChangesCount,
+    pub state_diff_size: usize,
+    pub n_l1_messages: usize,
+    /// The number of felts the L1 messages take in the OS output.
+    pub message_segment_length: usize,
+    pub calldata_length: usize,
+    pub signature_length: usize,
+    pub n_events: usize,
+    pub n_event_keys: usize,
+    pub event_data_length: usize,
+    pub gas: GasVector,
+    /// The part of `gas` paying for the publication of the state diff.
+    pub da_gas: GasVector,
+}
+
+impl TransactionResources {
+    /// Adds up the resources of the calls of a transaction and prices them with the fee costs of
+    /// the Starknet version of `config`.
+    ///
+    /// The events and messages of a reverted execution are not included in `call_infos`, but its
+    /// steps and builtins are in `reverted_call_infos`. `calldata_length` is the length of the
+    /// calldata the OS resources of the transaction type scale with.
+    pub fn new(
+        transaction_type: TransactionType,
+        call_infos: &[&CallInfo],
+        reverted_call_infos: &[&CallInfo],
+        calldata_length: usize,
+        signature_length: usize,
+        state_changes: StateChangesCount,
+        config: &RunConfig,
+    ) -> Self {
+        let mut resources = Self {
+            state_changes,
+            state_diff_size: state_changes.state_diff_size(),
+            calldata_length,
+            signature_length,
+            ..Self::default()
+        };
+        let mut messages = vec![];
+        for call_info in call_infos {
+            resources.add_call(call_info, &mut messages);
+        }
+        for call_info in reverted_call_infos {
+            resources
+                .computation
+                .add(&ExecutionResources::of_call_tree(call_info));
+            resources.l2_gas += call_info.gas_consumed.unwrap_or_default();
+        }
+        let os_resources = config.os_resources();
+        let syscall_resources =
+            os_resources.syscall_resources(&resources.computation.syscall_counter);
+        resources.computation.add(&syscall_resources);
+        resources
+            .computation
+            .add(&os_resources.transaction_resources(transaction_type, calldata_length));
+        resources.n_l1_messages = messages.len();
+        resources.message_segment_length = messages
+            .iter()
+            .map(|message| L2_TO_L1_MESSAGE_HEADER_SIZE + message.payload.len())
+            .sum();
+        (resources.gas, resources.da_gas) = resources.gas_vector(&config.fee_costs(), &messages);
+        resources
+    }
+
+    fn add_call<'a>(&mut self, call_info: &'a CallInfo, messages: &mut Vec<&'a MessageToL1>) {
+        self.computation
+            .add(&ExecutionResources::of_call_tree(call_info));
+        self.l2_gas += call_info.gas_consumed.unwrap_or_default();
+        collect_call_tree(call_info, &mut |call_info| {
+            self.n_events += call_info.events.len();
+            for event in &call_info.events {
+                self.n_event_keys += event.keys.len();
+                self.event_data_length += event.data.len();
+            }
+            messages.extend(&call_info.l2_to_l1_messages);
+        });
+    }
+
+    /// The gas of the transaction, and the gas of its state diff.
+    fn gas_vector(
+        &self,
+        fee_costs: &FeeCosts,
+        messages: &[&MessageToL1],
+    ) -> (GasVector, GasVector) {
+        // The VM resources cost as much as their most expensive resource.
+        let computation = &self.computation;
+        let steps_cost =
+            (computation.n_steps + computation.n_memory_holes) as u64 * fee_costs.n_steps;
+        let vm_cost = computation
+            .builtin_instance_counter
+            .iter()
+            .map(|(name, count)| {
+                *count as u64 * fee_costs.builtins.get(name).copied().unwrap_or_default()
+            })
+            .fold(steps_cost, u64::max);
+
+        let data_felts = (self.calldata_length + self.signature_length + self.event_data_length)
+            as u64
+            + self.n_event_keys as u64 * fee_costs.event_key_factor;
+        let data_cost = data_felts * fee_costs.gas_per_data_felt;
+
+        let messages_gas = messages
+            .iter()
+            .map(|message| {
+                GAS_PER_LOG
+                    + (LOG_MESSAGE_TO_L1_N_TOPICS + 1) * GAS_PER_LOG_TOPIC
+                    + (LOG_MESSAGE_TO_L1_DATA_SIZE + message.payload.len() as u64)
+                        * GAS_PER_LOG_DATA_WORD
+            })
+            .sum::<u64>()
+            + self.message_segment_length as u64 * SHARP_GAS_PER_MEMORY_WORD;
+
+        let state_diff_size = self.state_diff_size as u64;
+        let (da_l1_gas, da_l1_data_gas) = if fee_costs.use_kzg_da {
+            (0, state_diff_size * DATA_GAS_PER_FELT)
+        } else {
+            (self.calldata_da_gas(), 0)
+        };
+
+        let da_gas = GasVector {
+            l1_gas: da_l1_gas as u128,
+            l1_data_gas: da_l1_data_gas as u128,
+            l2_gas: 0,
+        };
+        let gas = GasVector {
+            l1_gas: (vm_cost + data_cost).div_ceil(FEE_COST_DENOMINATOR) as u128
+                + messages_gas as u128
+                + da_gas.l1_gas,
+            l1_data_gas: da_gas.l1_data_gas,
+            l2_gas: self.l2_gas as u128,
+        };
+        (gas, da_gas)
+    }
+
+    /// The L1 gas of publishing the state diff in calldata. Most words of the diff are mostly
+    /// zero bytes: the contract headers and the fee balance update are discounted.
+    fn calldata_da_gas(&self) -> u64 {
+        let naive_cost = self.state_diff_size as u64 * SHARP_GAS_PER_MEMORY_WORD;
+        let calldata_word_cost = |non_zero_bytes: u64| {
+            non_zero_bytes * GAS_PER_MEMORY_BYTE + (WORD_WIDTH - non_zero_bytes) * GAS_PER_ZERO_BYTE
+        };
+        // A contract header holds a class flag (1 byte), a storage update count (2 bytes) and a
+        // nonce (3 bytes); a fee balance fits in 12 bytes.
+        let discount = self.state_changes.n_modified_contracts as u64
+            * (GAS_PER_MEMORY_WORD - calldata_word_cost(6))
+            + (GAS_PER_MEMORY_WORD - calldata_word_cost(12));
+        if naive_cost < discount {
+            naive_cost / 10
+        } else {
+            naive_cost - discount
+        }
+    }
+
+    /// The fee of the gas of the transaction at the gas prices of `config`, in fri for fees paid
+    /// in STRK and in wei otherwise, with the L1 gas and data gas prices.
+    pub fn fee(&self, config: &RunConfig, unit: &str) -> (u128, u128, u128) {
+        let (gas_price, data_gas_price) = gas_prices(config, unit);
+        (
+            gas_price,
+            data_gas_price,
+            self.gas.fee(gas_price, data_gas_price),
+        )
+    }
+
+    /// The part of the fee paying for the publication of the state diff.
+    pub fn data_availability_fee(&self, config: &RunConfig, unit: &str) -> u128 {
+        let (gas_price, data_gas_price) = gas_prices(config, unit);
+        self.da_gas.fee(gas_price, data_gas_price)
+    }
+}
+
+impl GasVector {
+    fn fee(&self, gas_price: u128, data_gas_price: u128) -> u128 {
+        self.l1_gas
+            .saturating_mul(gas_price)
+            .saturating_add(self.l1_data_gas.saturating_mul(data_gas_price))
+    }
+}
+
+fn gas_prices(config: &RunConfig, unit: &str) -> (u128, u128) {
+    let context = &config.execution_context;
+    match unit {
+        "FRI" => (context.strk_gas_price, context.strk_data_gas_price),
+        _ => (context.gas_price, context.data_gas_price),
+    }
+}
+
+fn collect_call_tree<'a>(call_info: &'a CallInfo, f: &mut impl FnMut(&'a CallInfo)) {
+    f(call_info);
+    for inner_call in &call_info.inner_calls {
+        collect_call_tree(inner_call, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::types::{ContractStorageDiff, StorageEntry};
+    use crate::trace::versioned_constants::StarknetVersion;
+
+    #[test]
+    fn test_gas_vector() {
+        let mut resources = TransactionResources {
+            computation: ExecutionResources {
+                n_steps: 1_000,
+                n_memory_holes: 200,
+                builtin_instance_counter: BTreeMap::from([("pedersen_builtin".to_string(), 10)]),
+                ..ExecutionResources::default()
+            },
+            state_changes: StateChangesCount {
+                n_storage_updates: 2,
+                n_modified_contracts: 1,
+                ..StateChangesCount::default()
+            },
+            state_diff_size: 6,
+            calldata_length: 5,
+            ..TransactionResources::default()
+        };
+        let fee_costs = StarknetVersion::V0_13_1.versioned_constants().fee_costs;
+        // 1200 steps at 0.0025 gas and 5 calldata felts at 0.128 gas.
+        assert_eq!(
+            resources.gas_vector(&fee_costs, &[]).0,
+            GasVector {
+                l1_gas: 4,
+                l1_data_gas: 6 * DATA_GAS_PER_FELT as u128,
+                l2_gas: 0,
+            }
+        );
+
+        // The pedersen builtin becomes the bottleneck.
+        resources
+            .computation
+            .builtin_instance_counter
+            .insert("pedersen_builtin".to_string(), 1_000);
+        let fee_costs = StarknetVersion::V0_13_0.versioned_constants().fee_costs;
+        let (gas, da_gas) = resources.gas_vector(&fee_costs, &[]);
+        assert_eq!(gas.l1_data_gas, 0);
+        assert_eq!(da_gas.l1_gas, resources.calldata_da_gas() as u128);
+        assert_eq!(gas.l1_gas, 80 + da_gas.l1_gas);
+    }
+
+    #[test]
+    fn test_state_changes_count() {
+        let fee_token = Felt252::from(0x1);
+        let account = Felt252::from(0x100);
+        let state_diff = StateDiff {
+            storage_diffs: vec![ContractStorageDiff {
+                address: fee_token,
+                storage_entries: vec![StorageEntry {
+                    key: fee_balance_key(account),
+                    value: Felt252::from(5),
+                }],
+            }],
+            ..StateDiff::default()
+        };
+        // The fee balance of the account is already in the diff.
+        let state_changes = StateChangesCount::new(&state_diff, Some((fee_token, account)));
+        assert_eq!(state_changes.n_storage_updates, 1);
+        assert_eq!(state_changes.n_modified_contracts, 1);
+
+        let state_changes = StateChangesCount::new(&state_diff, Some((fee_token, fee_token)));
+        assert_eq!(state_changes.n_storage_updates, 2);
+    }
+}
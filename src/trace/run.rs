@@ -0,0 +1,482 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use cairo_lang_casm::instructions::Instruction;
+use cairo_lang_casm_2_point_6::hints::Hint;
+use cairo_lang_runner::{Arg, SierraCasmRunner};
+use cairo_lang_sierra::extensions::core::{CoreLibfunc, CoreType};
+use cairo_lang_sierra::extensions::enm::EnumType;
+use cairo_lang_sierra::extensions::NamedType;
+use cairo_lang_sierra::ids::ConcreteTypeId;
+use cairo_lang_sierra::program::{GenericArg, Program as SierraProgram};
+use cairo_lang_sierra::program_registry::ProgramRegistry;
+use cairo_lang_sierra_to_casm::compiler::CairoProgram;
+use cairo_lang_sierra_to_casm::metadata::MetadataComputationConfig;
+use cairo_lang_sierra_type_size::get_type_size_map;
+use cairo_vm::serde::deserialize_program::{
+    ApTracking, FlowTrackingData, HintParams, ReferenceManager,
+};
+use cairo_vm::types::builtin_name::BuiltinName;
+use cairo_vm::types::program::Program;
+use cairo_vm::types::relocatable::MaybeRelocatable;
+use cairo_vm::vm::errors::vm_errors::VirtualMachineError;
+use cairo_vm::vm::runners::cairo_runner::{
+    CairoRunner, ExecutionResources as VmExecutionResources,
+};
+use cairo_vm::vm::trace::trace_entry::RelocatedTraceEntry;
+use cairo_vm::vm::vm_core::VirtualMachine;
+use serde::{Deserialize, Serialize};
+use starknet_types_core::felt::Felt as Felt252;
+
+use super::cairo_runner::ExecutionStatus;
+use super::config::RunConfig;
+use super::hint_processor::{PrintOutput, TracingHintProcessor};
+use super::short_string::as_short_string;
+use super::sierra_trace::{SierraDebugInfo, SierraTraceEntry};
+use super::syscalls::SyscallHandler;
+use crate::compiler::compile::CompilationResult;
+use crate::state::State;
+
+/// Hints by their offset in the program.
+type Hints = Vec<(usize, Vec<Hint>)>;
+
+/// An argument of the executed function: a felt or an array of felts, in hex or decimal.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum RunArg {
+    Single(String),
+    Array(Vec<String>),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProgramExecutionResult {
+    /// `succeeded`, `reverted` if the function panicked, or `step_limit_exceeded` if it did not
+    /// return within `max_steps`.
+    pub status: ExecutionStatus,
+    /// The cells of the value returned by the function, as hex.
+    pub return_values: Vec<String>,
+    /// The panic data of the function, if it panicked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub panic_data: Option<Vec<String>>,
+    /// The panic data decoded as short strings, where possible.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub panic_reason: Option<Vec<String>>,
+    /// What the function printed with `print`/`println!`.
+    pub prints: Vec<PrintOutput>,
+    pub n_steps: usize,
+    pub n_memory_holes: usize,
+    pub builtin_instance_counter: HashMap<String, usize>,
+    pub trace: Vec<RelocatedTraceEntry>,
+    pub sierra_trace: Vec<SierraTraceEntry>,
+}
+
+/// Parses a felt given in hex (`0x` prefixed) or decimal.
+pub fn parse_felt(value: &str) -> Result<Felt252> {
+    let felt = if value.starts_with("0x") {
+        Felt252::from_hex(value)
+    } else {
+        Felt252::from_dec_str(value)
+    };
+    felt.map_err(|_| anyhow::anyhow!("Invalid felt `{}`.", value))
+}
+
+/// A finished run of a function of a Sierra program.
+pub struct FunctionRun {
+    pub status: ExecutionStatus,
+    pub return_values: Vec<String>,
+    /// The panic data of a function that panicked.
+    pub panic_data: Option<Vec<Felt252>>,
+    pub hint_processor: TracingHintProcessor,
+    pub execution_resources: VmExecutionResources,
+    pub trace: Vec<RelocatedTraceEntry>,
+    /// The CASM the function's program was compiled to.
+    pub casm_program: CairoProgram,
+    /// The offset of the compiled program in the program segment, after the entry code.
+    pub code_offset: usize,
+}
+
+/// Runs a function of a compiled Cairo program, `main` if no function is given.
+///
+/// The function is called with `initial_gas` and stopped after `max_steps`. Gas is charged with
+/// the builtin costs of the compiler, `builtin_costs` only applies to contracts.
+pub fn run_program(
+    compilation_result: &CompilationResult,
+    function: Option<&str>,
+    args: &[RunArg],
+    config: &RunConfig,
+) -> Result<ProgramExecutionResult> {
+    let func_args = args
+        .iter()
+        .map(|arg| {
+            Ok(match arg {
+                RunArg::Single(value) => Arg::Value(parse_felt(value)?),
+                RunArg::Array(values) => Arg::Array(
+                    values
+                        .iter()
+                        .map(|value| Ok(Arg::Value(parse_felt(value)?)))
+                        .collect::<Result<_>>()?,
+                ),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let function = format!("::{}", function.unwrap_or("main"));
+    let FunctionRun {
+        status,
+        return_values,
+        panic_data,
+        mut hint_processor,
+        execution_resources,
+        trace,
+        code_offset,
+        ..
+    } = run_function(
+        &compilation_result.cairo_sierra.program,
+        &function,
+        &func_args,
+        config,
+    )?;
+
+    let debug_info = SierraDebugInfo::from_program(compilation_result);
+    let sierra_trace = debug_info.sierra_trace(&trace, code_offset);
+    // Prints are located by their offset in the compiled program, after the entry code.
+    for print in hint_processor.prints.iter_mut() {
+        print.pc = print.pc.saturating_sub(code_offset);
+    }
+    let prints = hint_processor.take_prints(Some(&debug_info));
+
+    Ok(ProgramExecutionResult {
+        status,
+        return_values,
+        panic_data: panic_data
+            .as_ref()
+            .map(|data| data.iter().map(Felt252::to_hex_string).collect()),
+        panic_reason: panic_data.map(|data| {
+            data.iter()
+                .filter_map(|value| as_short_string(&value.to_biguint()))
+                .collect()
+        }),
+        prints,
+        n_steps: execution_resources.n_steps,
+        n_memory_holes: execution_resources.n_memory_holes,
+        builtin_instance_counter: execution_resources
+            .builtin_instance_counter
+            .iter()
+            .map(|(builtin, count)| (builtin.to_str().to_string(), *count))
+            .collect(),
+        trace,
+        sierra_trace,
+    })
+}
+
+/// Runs the function whose name ends with `function` on a fresh VM, with a traced run of the
+/// program's CASM behind the runner's entry code.
+pub fn run_function(
+    sierra_program: &SierraProgram,
+    function: &str,
+    args: &[Arg],
+    config: &RunConfig,
+) -> Result<FunctionRun> {
+    let runner = SierraCasmRunner::new(
+        sierra_program.clone(),
+        Some(MetadataComputationConfig::default()),
+        Default::default(),
+        None,
+    )
+    .with_context(|| "Failed setting up runner.")?;
+    let func = runner
+        .find_function(function)
+        .map_err(|_| anyhow::anyhow!("Function `{}` not found.", function))?;
+
+    let initial_gas = runner
+        .get_initial_available_gas(func, Some(config.initial_gas as usize))
+        .with_context(|| "The initial gas does not cover the function's upfront cost.")?;
+    let (entry_code, builtins) = runner
+        .create_entry_code(func, args, initial_gas)
+        .with_context(|| "Failed to create the entry code.")?;
+
+    // The runner's own compilation of the program, which the entry code calls into.
+    let casm_program = runner.get_casm_program().clone();
+
+    let code_offset: usize = entry_code.iter().map(|instr| instr.body.op_size()).sum();
+    let footer = SierraCasmRunner::create_code_footer();
+    let (data, hints) = assemble(
+        entry_code
+            .iter()
+            .chain(&casm_program.instructions)
+            .chain(&footer),
+    )?;
+
+    let hint_params = hints
+        .iter()
+        .map(|(pc, _)| {
+            let params = HintParams {
+                code: pc.to_string(),
+                accessible_scopes: vec![],
+                flow_tracking_data: FlowTrackingData {
+                    ap_tracking: ApTracking::default(),
+                    reference_ids: HashMap::new(),
+                },
+            };
+            (*pc, vec![params])
+        })
+        .collect();
+    let builtins = builtins
+        .iter()
+        .map(|builtin| {
+            BuiltinName::from_str(builtin.to_str())
+                .with_context(|| format!("Invalid builtin name {}.", builtin.to_str()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let program = Program::new(
+        builtins,
+        data,
+        Some(0),
+        hint_params,
+        ReferenceManager { references: vec![] },
+        HashMap::new(),
+        vec![],
+        None,
+    )
+    .map_err(|e| anyhow::anyhow!("Invalid program: {}", e))?;
+
+    let mut hint_processor = TracingHintProcessor::new(
+        &hints,
+        SyscallHandler::new(config, State::default(), Felt252::ZERO, Felt252::ZERO),
+    );
+    let mut cairo_runner = CairoRunner::new(&program, config.layout()?, false)
+        .map_err(|e| anyhow::anyhow!("Failed to create the runner: {}", e))?;
+    let mut vm = VirtualMachine::new(true);
+    let end = cairo_runner
+        .initialize(&mut vm, false)
+        .map_err(|e| anyhow::anyhow!("Failed to initialize the runner: {}", e))?;
+
+    let mut status = match cairo_runner.run_until_pc(end, &mut vm, &mut hint_processor) {
+        Ok(()) => {
+            cairo_runner.end_run(true, false, &mut vm, &mut hint_processor)?;
+            ExecutionStatus::Succeeded
+        }
+        Err(VirtualMachineError::UnfinishedExecution) => ExecutionStatus::StepLimitExceeded {
+            max_steps: config.max_steps.unwrap_or_default(),
+        },
+        Err(e) => return Err(e.into()),
+    };
+    cairo_runner
+        .relocate(&mut vm, true)
+        .map_err(|e| anyhow::anyhow!("Failed to relocate the run: {}", e))?;
+
+    let mut return_values = vec![];
+    let mut panic_data = None;
+    if status == ExecutionStatus::Succeeded {
+        let registry = ProgramRegistry::<CoreType, CoreLibfunc>::new(sierra_program)?;
+        let type_sizes =
+            get_type_size_map(sierra_program, &registry).context("Invalid type sizes.")?;
+        let type_size = |ty: &ConcreteTypeId| type_sizes.get(ty).map_or(0, |size| *size as usize);
+        // The returned value comes last, after the implicit arguments.
+        let return_type = func.signature.ret_types.last();
+        let mut values = vm.get_return_values(return_type.map_or(0, type_size))?;
+        // A function that can panic returns a `PanicResult`: its variant, then the value, or the
+        // span of the panic data, last.
+        if let Some(value_type) =
+            return_type.and_then(|ty| panic_result_value_type(sierra_program, ty))
+        {
+            if values[0] == MaybeRelocatable::from(Felt252::ZERO) {
+                values.drain(..values.len() - type_size(&value_type));
+            } else {
+                let data_start = values[values.len() - 2]
+                    .get_relocatable()
+                    .with_context(|| "Invalid panic data.")?;
+                let data_end = values[values.len() - 1]
+                    .get_relocatable()
+                    .with_context(|| "Invalid panic data.")?;
+                panic_data = Some(
+                    vm.get_integer_range(data_start, (data_end - data_start)?)?
+                        .into_iter()
+                        .map(|value| value.into_owned())
+                        .collect(),
+                );
+                values.clear();
+                status = ExecutionStatus::Reverted;
+            }
+        }
+        return_values = values
+            .iter()
+            .map(|value| match value {
+                MaybeRelocatable::Int(value) => value.to_hex_string(),
+                MaybeRelocatable::RelocatableValue(value) => value.to_string(),
+            })
+            .collect();
+    }
+
+    let execution_resources = cairo_runner.get_execution_resources(&vm)?;
+    let trace = cairo_runner
+        .relocated_trace
+        .clone()
+        .with_context(|| "Missing relocated trace.")?;
+
+    Ok(FunctionRun {
+        status,
+        return_values,
+        panic_data,
+        hint_processor,
+        execution_resources,
+        trace,
+        casm_program,
+        code_offset,
+    })
+}
+
+/// The type of the value returned by a function that can panic, if `ty` is its `PanicResult`.
+fn panic_result_value_type(
+    sierra_program: &SierraProgram,
+    ty: &ConcreteTypeId,
+) -> Option<ConcreteTypeId> {
+    let long_id = &sierra_program
+        .type_declarations
+        .iter()
+        .find(|declaration| declaration.id == *ty)?
+        .long_id;
+    if long_id.generic_id != EnumType::ID {
+        return None;
+    }
+    match long_id.generic_args.as_slice() {
+        [GenericArg::UserType(user_type), GenericArg::Type(value_type), ..]
+            if user_type
+                .debug_name
+                .as_ref()
+                .is_some_and(|name| name.starts_with("core::panics::PanicResult::")) =>
+        {
+            Some(value_type.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Assembles instructions into the program data and the hints by offset.
+///
+/// The hint processor runs the hints of the Cairo version cairo-vm is built with, so the hints
+/// are converted to it through their serialized form, which both versions share.
+fn assemble<'a>(
+    instructions: impl Iterator<Item = &'a Instruction>,
+) -> Result<(Vec<MaybeRelocatable>, Hints)> {
+    let mut data = vec![];
+    let mut hints = vec![];
+    for instruction in instructions {
+        if !instruction.hints.is_empty() {
+            hints.push((data.len(), instruction.hints.clone()));
+        }
+        data.extend(
+            instruction
+                .assemble()
+                .encode()
+                .into_iter()
+                .map(|word| MaybeRelocatable::from(Felt252::from(word))),
+        );
+    }
+    let hints = serde_json::from_value(serde_json::to_value(&hints)?)
+        .with_context(|| "Unsupported hints.")?;
+    Ok((data, hints))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::compile::compile;
+
+    #[test]
+    fn test_run_named_function() {
+        let code = r#"fn main() -> felt252 {
+            double(5)
+        }
+
+        #[inline(never)]
+        fn double(x: felt252) -> felt252 {
+            x * 2
+        }
+        "#;
+        let compilation_result = compile(code, "double").unwrap();
+        let result = run_program(
+            &compilation_result,
+            Some("double"),
+            &[RunArg::Single("21".to_string())],
+            &RunConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(result.status, ExecutionStatus::Succeeded);
+        assert_eq!(result.return_values, vec!["0x2a"]);
+        assert!(result.n_steps > 0);
+        assert!(!result.trace.is_empty());
+    }
+
+    #[test]
+    fn test_run_step_limit_and_prints() {
+        let code = r#"fn main() {
+            println!("looping");
+            loop {}
+        }
+        "#;
+        let compilation_result = compile(code, "looping").unwrap();
+        let config = RunConfig {
+            max_steps: Some(1_000),
+            ..RunConfig::default()
+        };
+        let result = run_program(&compilation_result, None, &[], &config).unwrap();
+        assert_eq!(
+            result.status,
+            ExecutionStatus::StepLimitExceeded { max_steps: 1_000 }
+        );
+        assert!(result.n_steps <= 1_000);
+        assert_eq!(result.prints.len(), 1);
+        assert_eq!(result.prints[0].text, "looping\n");
+        assert!(result.prints[0].location.is_some());
+    }
+
+    #[test]
+    fn test_run_panicking_function() {
+        let code = r#"fn main() -> u32 {
+            checked_div(6, 3)
+        }
+
+        fn checked_div(a: u32, b: u32) -> u32 {
+            if b == 0 {
+                panic!("division by zero");
+            }
+            a / b
+        }
+
+        fn fail(x: felt252) -> felt252 {
+            assert(x == 0, 'not zero');
+            x
+        }
+        "#;
+        let compilation_result = compile(code, "panicking").unwrap();
+        let result = run_program(&compilation_result, None, &[], &RunConfig::default()).unwrap();
+        assert_eq!(result.status, ExecutionStatus::Succeeded);
+        assert_eq!(result.return_values, vec!["0x2"]);
+        assert!(result.panic_data.is_none());
+
+        let result = run_program(
+            &compilation_result,
+            Some("fail"),
+            &[RunArg::Single("1".to_string())],
+            &RunConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(result.status, ExecutionStatus::Reverted);
+        assert!(result.return_values.is_empty());
+        assert_eq!(
+            result.panic_data,
+            Some(vec![
+                Felt252::from_bytes_be_slice(b"not zero").to_hex_string()
+            ])
+        );
+        assert_eq!(result.panic_reason, Some(vec!["not zero".to_string()]));
+    }
+
+    #[test]
+    fn test_parse_felt() {
+        assert_eq!(parse_felt("0x10").unwrap(), Felt252::from(16));
+        assert_eq!(parse_felt("16").unwrap(), Felt252::from(16));
+        assert!(parse_felt("0xzz").is_err());
+    }
+}
@@ -0,0 +1,32 @@
+use num_bigint::BigUint;
+
+/// Decodes a felt holding a Cairo short string (e.g. `'Out of gas'`).
+///
+/// Returns `None` if the value is zero or contains non printable characters.
+pub fn as_short_string(value: &BigUint) -> Option<String> {
+    let bytes = value.to_bytes_be();
+    if bytes.iter().all(|byte| *byte == 0) {
+        return None;
+    }
+    if bytes
+        .iter()
+        .all(|byte| byte.is_ascii_graphic() || *byte == b' ')
+    {
+        Some(String::from_utf8(bytes).ok()?)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_as_short_string() {
+        let value = BigUint::from_bytes_be(b"Out of gas");
+        assert_eq!(as_short_string(&value), Some("Out of gas".to_string()));
+        assert_eq!(as_short_string(&BigUint::from(0u32)), None);
+        assert_eq!(as_short_string(&BigUint::from(1u32)), None);
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use cairo_lang_sierra::program::{Program, Statement, StatementIdx};
+use cairo_vm::vm::trace::trace_entry::RelocatedTraceEntry;
+use serde::{Deserialize, Serialize};
+
+use crate::cairo_sierra::cairo_helper::{CairoLocation, SierraCairoInfoMapping};
+use crate::casm_sierra::cairo::CasmSierraMappingInstruction;
+use crate::compiler::compile::CompilationResult;
+use crate::compiler::compile_contract::ContractCompilationResult;
+
+/// The relocated address of the first word of the program segment.
+pub const PROGRAM_BASE: usize = 1;
+
+/// A single executed Sierra statement, derived from the CASM-level trace.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SierraTraceEntry {
+    /// Index of the statement in the Sierra program.
+    pub statement_idx: u64,
+    /// The statement as it appears in the Sierra program text.
+    pub statement: String,
+    /// The libfunc invoked by the statement, `None` for `return`.
+    pub libfunc: Option<String>,
+    /// The branch taken, only set for statements with more than one branch.
+    pub branch: Option<usize>,
+    /// The Cairo function the statement was generated from.
+    pub function: Option<String>,
+    /// The relocated pc of the first CASM instruction of the statement.
+    pub pc: usize,
+}
+
+/// Debug information needed to map CASM execution back to Sierra and Cairo.
+pub struct SierraDebugInfo {
+    program: Program,
+    /// The Sierra statements starting at each bytecode offset.
+    statements_by_offset: BTreeMap<usize, Vec<u64>>,
+    sierra_cairo_info_mapping: SierraCairoInfoMapping,
+}
+
+impl SierraDebugInfo {
+    pub fn new(
+        program: Program,
+        casm_sierra_mapping_instruction: &CasmSierraMappingInstruction,
+        sierra_cairo_info_mapping: SierraCairoInfoMapping,
+    ) -> Self {
+        // Every CASM instruction starts at the first of its encoded words.
+        let mut instruction_offsets = BTreeMap::new();
+        for (offset, instruction) in casm_sierra_mapping_instruction
+            .casm_instructions
+            .iter()
+            .enumerate()
+        {
+            if instruction.instruction_representation.is_some() {
+                instruction_offsets.insert(instruction.instruction_index as u64, offset);
+            }
+        }
+
+        let statements_by_offset = casm_sierra_mapping_instruction
+            .casm_sierra_mapping
+            .iter()
+            .filter_map(|(instruction_idx, statements)| {
+                instruction_offsets
+                    .get(instruction_idx)
+                    .map(|offset| (*offset, statements.clone()))
+            })
+            .collect();
+
+        Self {
+            program,
+            statements_by_offset,
+            sierra_cairo_info_mapping,
+        }
+    }
+
+    /// Builds the debug info of a compiled contract.
+    pub fn from_contract(compilation_result: &ContractCompilationResult) -> anyhow::Result<Self> {
+        let sierra_contract_class = &compilation_result.cairo_sierra.sierra_contract_class;
+        let mut program = sierra_contract_class
+            .extract_sierra_program()
+            .with_context(|| "Failed to extract the Sierra program from the contract class.")?;
+        if let Some(debug_info) = &sierra_contract_class.sierra_program_debug_info {
+            debug_info.clone().populate(&mut program);
+        }
+
+        Ok(Self::new(
+            program,
+            &compilation_result
+                .casm_sierra
+                .casm_sierra_mapping_instruction,
+            compilation_result
+                .cairo_sierra
+                .sierra_cairo_info_mapping
+                .clone(),
+        ))
+    }
+
+    /// Builds the debug info of a compiled Cairo program.
+    pub fn from_program(compilation_result: &CompilationResult) -> Self {
+        Self::new(
+            compilation_result.cairo_sierra.program.clone(),
+            &compilation_result
+                .casm_sierra
+                .casm_sierra_mapping_instruction,
+            compilation_result
+                .cairo_sierra
+                .sierra_cairo_info_mapping
+                .clone(),
+        )
+    }
+
+    /// Returns the Sierra statement whose code contains the given bytecode offset.
+    pub fn statement_at(&self, offset: usize) -> Option<u64> {
+        self.statements_by_offset
+            .range(..=offset)
+            .next_back()
+            .and_then(|(_, statements)| statements.last().copied())
+    }
+
+    /// Returns the Cairo locations the given Sierra statement was generated from.
+    pub fn cairo_locations(&self, statement_idx: u64) -> Option<&Vec<CairoLocation>> {
+        self.sierra_cairo_info_mapping
+            .get(&statement_idx)
+            .and_then(|info| info.cairo_locations.as_ref())
+    }
+
+    /// Returns the name of the function the given Sierra statement belongs to.
+    pub fn function_name(&self, statement_idx: u64) -> Option<String> {
+        if let Some(info) = self.sierra_cairo_info_mapping.get(&statement_idx) {
+            return Some(info.fn_name.clone());
+        }
+        self.sierra_function_name(statement_idx)
+    }
+
+    /// Returns the name of the Sierra function containing the given statement.
+    fn sierra_function_name(&self, statement_idx: u64) -> Option<String> {
+        self.program
+            .funcs
+            .iter()
+            .filter(|function| function.entry_point.0 as u64 <= statement_idx)
+            .max_by_key(|function| function.entry_point.0)
+            .map(|function| function.id.to_string())
+    }
+
+    /// Returns the number of bytecode words every Sierra function compiled to.
+    pub fn bytecode_size_by_function(&self, bytecode_len: usize) -> BTreeMap<String, usize> {
+        let offsets: Vec<_> = self.statements_by_offset.iter().collect();
+        let mut sizes = BTreeMap::new();
+        for (i, (offset, statements)) in offsets.iter().enumerate() {
+            let end = offsets
+                .get(i + 1)
+                .map_or(bytecode_len, |(next_offset, _)| **next_offset);
+            let Some(function) = statements
+                .first()
+                .and_then(|statement_idx| self.sierra_function_name(*statement_idx))
+            else {
+                continue;
+            };
+            *sizes.entry(function).or_default() += end.saturating_sub(**offset);
+        }
+        sizes
+    }
+
+    /// Derives the executed Sierra statements from a relocated CASM trace.
+    ///
+    /// `code_offset` is the bytecode offset of the compiled Sierra program inside the program
+    /// segment, which is non zero when the runner prepends its own entry code.
+    pub fn sierra_trace(
+        &self,
+        trace: &[RelocatedTraceEntry],
+        code_offset: usize,
+    ) -> Vec<SierraTraceEntry> {
+        let mut entries: Vec<SierraTraceEntry> = vec![];
+        for trace_entry in trace {
+            let Some(offset) = trace_entry.pc.checked_sub(PROGRAM_BASE + code_offset) else {
+                continue;
+            };
+            let Some(statements) = self.statements_by_offset.get(&offset) else {
+                continue;
+            };
+            for statement_idx in statements {
+                let Some(statement) = self.program.statements.get(*statement_idx as usize) else {
+                    continue;
+                };
+                // The branch of the previous statement is decided by where execution continued.
+                if let Some(previous) = entries.last_mut() {
+                    previous.branch = self.branch_taken(previous.statement_idx, *statement_idx);
+                }
+                entries.push(SierraTraceEntry {
+                    statement_idx: *statement_idx,
+                    statement: statement.to_string(),
+                    libfunc: match statement {
+                        Statement::Invocation(invocation) => {
+                            Some(self.libfunc_name(&invocation.libfunc_id))
+                        }
+                        Statement::Return(_) => None,
+                    },
+                    branch: None,
+                    function: self.function_name(*statement_idx),
+                    pc: trace_entry.pc,
+                });
+            }
+        }
+        entries
+    }
+
+    /// Returns the branch of `statement_idx` that leads to `next_statement_idx`, if the statement
+    /// has more than one branch.
+    fn branch_taken(&self, statement_idx: u64, next_statement_idx: u64) -> Option<usize> {
+        let Some(Statement::Invocation(invocation)) =
+            self.program.statements.get(statement_idx as usize)
+        else {
+            return None;
+        };
+        if invocation.branches.len() < 2 {
+            return None;
+        }
+        invocation.branches.iter().position(|branch| {
+            StatementIdx(statement_idx as usize).next(&branch.target).0 as u64 == next_statement_idx
+        })
+    }
+
+    fn libfunc_name(&self, libfunc_id: &cairo_lang_sierra::ids::ConcreteLibfuncId) -> String {
+        if let Some(debug_name) = &libfunc_id.debug_name {
+            return debug_name.to_string();
+        }
+        self.program
+            .libfunc_declarations
+            .iter()
+            .find(|declaration| declaration.id == *libfunc_id)
+            .map(|declaration| declaration.long_id.to_string())
+            .unwrap_or_else(|| libfunc_id.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::compile::compile;
+    use crate::trace::config::RunConfig;
+    use crate::trace::run::{run_program, RunArg};
+
+    #[test]
+    fn test_statement_at_program_start() {
+        let code = r#"fn main() -> felt252 {
+            let x = 5;
+            x * 2
+        }
+        "#;
+        let compilation_result = compile(code, "double").unwrap();
+        let debug_info = SierraDebugInfo::from_program(&compilation_result);
+        assert!(debug_info.statement_at(0).is_some());
+        assert!(debug_info.function_name(0).is_some());
+    }
+
+    #[test]
+    fn test_sierra_trace_branches() {
+        let code = r#"fn main(x: felt252) -> felt252 {
+            if x == 0 {
+                1
+            } else {
+                2
+            }
+        }
+        "#;
+        let compilation_result = compile(code, "branch").unwrap();
+        let program = &compilation_result.cairo_sierra.program;
+        let trace = |x: &str| {
+            run_program(
+                &compilation_result,
+                None,
+                &[RunArg::Single(x.to_string())],
+                &RunConfig::default(),
+            )
+            .unwrap()
+            .sierra_trace
+        };
+
+        for (x, expected_branch) in [("0", 0), ("5", 1)] {
+            let trace = trace(x);
+            assert!(matches!(
+                program.statements[trace.last().unwrap().statement_idx as usize],
+                Statement::Return(_)
+            ));
+            let branch_idx = trace
+                .iter()
+                .position(|entry| entry.libfunc.as_deref() == Some("felt252_is_zero"))
+                .unwrap();
+            let entry = &trace[branch_idx];
+            assert_eq!(entry.branch, Some(expected_branch));
+            assert!(entry.statement.starts_with("felt252_is_zero"));
+            assert!(entry.function.as_deref().unwrap().ends_with("main"));
+
+            // Execution continues at the target of the branch taken.
+            let Statement::Invocation(invocation) =
+                &program.statements[entry.statement_idx as usize]
+            else {
+                panic!("`felt252_is_zero` is an invocation.");
+            };
+            let target = StatementIdx(entry.statement_idx as usize)
+                .next(&invocation.branches[expected_branch].target);
+            assert_eq!(trace[branch_idx + 1].statement_idx, target.0 as u64);
+            // Only the statements with several branches record the one taken.
+            for entry in &trace[..trace.len() - 1] {
+                let branches = match &program.statements[entry.statement_idx as usize] {
+                    Statement::Invocation(invocation) => invocation.branches.len(),
+                    Statement::Return(_) => 0,
+                };
+                assert_eq!(entry.branch.is_some(), branches > 1);
+            }
+        }
+
+        // Both runs execute the same statements up to the branch, then diverge.
+        let (zero, non_zero) = (trace("0"), trace("5"));
+        let statements = |trace: &[SierraTraceEntry]| -> Vec<u64> {
+            trace.iter().map(|entry| entry.statement_idx).collect()
+        };
+        let branch_idx = zero
+            .iter()
+            .position(|entry| entry.branch.is_some())
+            .unwrap();
+        assert_eq!(
+            statements(&zero[..=branch_idx]),
+            statements(&non_zero[..=branch_idx])
+        );
+        assert_ne!(
+            zero[branch_idx + 1].statement_idx,
+            non_zero[branch_idx + 1].statement_idx
+        );
+    }
+}
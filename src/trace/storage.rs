@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+
+use cairo_vm::types::{builtin_name::BuiltinName, relocatable::Relocatable};
+use cairo_vm::vm::vm_core::VirtualMachine;
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use starknet_types_core::felt::Felt as Felt252;
+use starknet_types_core::hash::{Pedersen, StarkHash};
+
+use super::abi_decoder::split_tuple;
+use crate::cairo_sierra::storage::StorageVariable;
+
+/// Values spanning several felts (e.g. `u256`) are stored at consecutive addresses from the
+/// address of their variable; keys further than this from it are not resolved.
+const MAX_VALUE_OFFSET: u64 = 16;
+
+/// The bound of storage addresses, `2**251 - 256`: map keys are hashed modulo this bound.
+const ADDR_BOUND: &str = "0x7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff00";
+
+/// The storage variable a storage key belongs to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageVariableRef {
+    /// The variable and its map keys, e.g. `balances[0x123]` or `allowances[(0x1, 0x2)]`.
+    pub name: String,
+    /// The offset of the key from the address of the variable, for values of several felts.
+    pub offset: u64,
+}
+
+/// Resolves storage keys to the storage variables of a contract.
+///
+/// Plain variables live at the Starknet keccak of their name. The keys of a map entry are hashed
+/// into the address with Pedersen, so they are recovered from the hashes computed by the run.
+pub struct StorageResolver<'a> {
+    variables: HashMap<Felt252, &'a StorageVariable>,
+    preimages: HashMap<Felt252, (Felt252, Felt252)>,
+}
+
+impl<'a> StorageResolver<'a> {
+    pub fn new(variables: &'a [StorageVariable]) -> Self {
+        Self {
+            variables: variables
+                .iter()
+                .map(|variable| (variable.base_address, variable))
+                .collect(),
+            preimages: HashMap::new(),
+        }
+    }
+
+    /// Records the inputs of the Pedersen hashes computed by a run.
+    pub fn add_pedersen_preimages(&mut self, vm: &VirtualMachine) {
+        let addr_bound = Felt252::from_hex_unchecked(ADDR_BOUND);
+        for builtin in vm.get_builtin_runners() {
+            if builtin.name() != BuiltinName::pedersen {
+                continue;
+            }
+            let segment_index = builtin.base() as isize;
+            let cell = |offset: usize| {
+                vm.get_maybe(&Relocatable::from((segment_index, offset)))
+                    .and_then(|value| value.get_int())
+            };
+            let mut offset = 0;
+            while let (Some(x), Some(y)) = (cell(offset), cell(offset + 1)) {
+                let hash = Pedersen::hash(&x, &y);
+                self.preimages.insert(hash, (x, y));
+                if hash >= addr_bound {
+                    self.preimages.insert(hash - addr_bound, (x, y));
+                }
+                offset += 3;
+            }
+        }
+    }
+
+    pub fn resolve(&self, key: Felt252) -> Option<StorageVariableRef> {
+        (0..MAX_VALUE_OFFSET).find_map(|offset| {
+            let name = self.resolve_address(key - Felt252::from(offset))?;
+            Some(StorageVariableRef { name, offset })
+        })
+    }
+
+    /// Renders the variable and map keys whose entry lives at `address`.
+    fn resolve_address(&self, address: Felt252) -> Option<String> {
+        let mut keys = vec![];
+        let mut address = address;
+        let variable = loop {
+            if let Some(variable) = self.variables.get(&address) {
+                break variable;
+            }
+            let (x, y) = self.preimages.get(&address)?;
+            keys.push(*y);
+            address = *x;
+        };
+        keys.reverse();
+        Some(render_variable(variable, &keys))
+    }
+}
+
+/// Renders a variable and its map keys, e.g. `allowances[(0x1, 0x2)]`.
+///
+/// The keys are grouped by the key types of the (possibly nested) maps of the variable, and are
+/// listed as felts if they do not match them.
+fn render_variable(variable: &StorageVariable, keys: &[Felt252]) -> String {
+    let mut name = variable.name.clone();
+    let key_types = variable
+        .map
+        .as_ref()
+        .map(|map| map.key_types.as_slice())
+        .unwrap_or_default();
+    let mut remaining = keys;
+    for key_type in key_types {
+        let Some((key, len)) = render_key(key_type, remaining) else {
+            break;
+        };
+        name.push_str(&format!("[{}]", key));
+        remaining = &remaining[len..];
+    }
+    if !remaining.is_empty() {
+        let felts: Vec<String> = remaining.iter().map(|key| key.to_hex_string()).collect();
+        name.push_str(&format!("[{}]", felts.join(", ")));
+    }
+    name
+}
+
+/// Renders a map key of the given type, returning it with the number of felts it spans.
+fn render_key(ty: &str, keys: &[Felt252]) -> Option<(String, usize)> {
+    if ty == "core::integer::u256" {
+        let low = keys.first()?.to_biguint();
+        let high = keys.get(1)?.to_biguint();
+        let value: BigUint = (high << 128) + low;
+        return Some((format!("{:#x}", value), 2));
+    }
+    if let Some(members) = ty.strip_prefix('(').and_then(|ty| ty.strip_suffix(')')) {
+        let mut items = vec![];
+        let mut len = 0;
+        for member in split_tuple(members) {
+            let (item, item_len) = render_key(member, &keys[len..])?;
+            items.push(item);
+            len += item_len;
+        }
+        return Some((format!("({})", items.join(", ")), len));
+    }
+    Some((keys.first()?.to_hex_string(), 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::State;
+    use crate::trace::abi_decoder::selector_from_name;
+    use crate::trace::cairo_runner::ExecutionStatus;
+    use crate::trace::config::RunConfig;
+    use crate::trace::execution::{
+        execute_call, CallEntryPoint, CallType, EntryPointType, StorageAccessKind,
+    };
+
+    #[test]
+    fn test_resolve_map_keys() {
+        let variables = vec![
+            StorageVariable::new(
+                "total_supply".to_string(),
+                "core::integer::u256".to_string(),
+                None,
+            ),
+            StorageVariable::new(
+                "allowances".to_string(),
+                "core::starknet::storage::map::LegacyMap::<(core::starknet::contract_address::ContractAddress, core::starknet::contract_address::ContractAddress), core::integer::u256>".to_string(),
+                None,
+            ),
+        ];
+        let mut resolver = StorageResolver::new(&variables);
+        let base = selector_from_name("allowances");
+        let owner = Felt252::from(0x123);
+        let spender = Felt252::from(0x456);
+        let inner = Pedersen::hash(&base, &owner);
+        let address = Pedersen::hash(&inner, &spender);
+        resolver.preimages.insert(inner, (base, owner));
+        resolver.preimages.insert(address, (inner, spender));
+
+        assert_eq!(
+            resolver.resolve(address + Felt252::ONE).unwrap(),
+            StorageVariableRef {
+                name: "allowances[(0x123, 0x456)]".to_string(),
+                offset: 1,
+            }
+        );
+        assert_eq!(
+            resolver
+                .resolve(selector_from_name("total_supply"))
+                .unwrap()
+                .name,
+            "total_supply"
+        );
+        assert!(resolver.resolve(Felt252::from(42)).is_none());
+    }
+
+    #[test]
+    fn test_resolve_keys_hashed_by_a_run() {
+        let code = r#"#[starknet::contract]
+        mod Token {
+            use starknet::ContractAddress;
+
+            #[storage]
+            struct Storage {
+                allowances: LegacyMap::<(ContractAddress, ContractAddress), u256>,
+            }
+
+            #[external(v0)]
+            fn approve(
+                ref self: ContractState,
+                owner: ContractAddress,
+                spender: ContractAddress,
+                amount: u256
+            ) {
+                self.allowances.write((owner, spender), amount);
+            }
+        }
+        "#;
+        let mut state = State::default();
+        state.deploy_code(code, Felt252::ONE);
+        let config = RunConfig::default();
+        let call = CallEntryPoint {
+            call_type: CallType::Call,
+            caller_address: Felt252::ZERO,
+            contract_address: Felt252::ONE,
+            class_hash: None,
+            entry_point_type: EntryPointType::External,
+            entry_point_selector: selector_from_name("approve"),
+            calldata: vec![
+                Felt252::from(0x123),
+                Felt252::from(0x456),
+                Felt252::from(7),
+                Felt252::ZERO,
+            ],
+            initial_gas: config.initial_gas,
+        };
+        let call_info = execute_call(&mut state, &mut 0, &call, &config).unwrap();
+        assert_eq!(call_info.status, ExecutionStatus::Succeeded);
+
+        // The address of the entry is only known from the Pedersen hashes of the run.
+        let writes: Vec<_> = call_info
+            .storage_accesses
+            .iter()
+            .filter(|access| access.kind == StorageAccessKind::Write)
+            .map(|access| (access.value, access.variable.clone()))
+            .collect();
+        let variable = |offset| {
+            Some(StorageVariableRef {
+                name: "allowances[(0x123, 0x456)]".to_string(),
+                offset,
+            })
+        };
+        assert_eq!(
+            writes,
+            vec![
+                (Felt252::from(7), variable(0)),
+                (Felt252::ZERO, variable(1))
+            ]
+        );
+    }
+}
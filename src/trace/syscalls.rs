@@ -0,0 +1,618 @@
+use std::collections::BTreeMap;
+
+use cairo_vm::{
+    types::relocatable::{MaybeRelocatable, Relocatable},
+    vm::{errors::hint_errors::HintError, vm_core::VirtualMachine},
+};
+use num_traits::ToPrimitive;
+use starknet_types_core::felt::Felt as Felt252;
+
+use super::abi_decoder::selector_from_name;
+use super::cairo_runner::ExecutionStatus;
+use super::config::{ExecutionMode, RunConfig};
+use super::events::EmittedEvent;
+use super::execution::{
+    execute_call, CallEntryPoint, CallInfo, CallType, EntryPointType, MessageToL1, StorageAccess,
+    StorageAccessKind,
+};
+use super::short_string::as_short_string;
+use super::versioned_constants::{SyscallGasCosts, SYSCALL_BASE_GAS_COST};
+use crate::rpc::transaction_hash::calculate_contract_address;
+use crate::state::State;
+
+/// The revert reason of a syscall that ran out of gas, `'Out of gas'` as a short string.
+const OUT_OF_GAS_ERROR: &[u8] = b"Out of gas";
+
+/// Appended to the retdata of a failed inner call to form the revert reason of the syscall.
+const ENTRYPOINT_FAILED_ERROR: &[u8] = b"ENTRYPOINT_FAILED";
+
+/// The revert reason of a deploy syscall to an address a contract is already deployed at.
+const CONTRACT_ADDRESS_UNAVAILABLE_ERROR: &[u8] = b"CONTRACT_ADDRESS_UNAVAILABLE";
+
+/// The revert reason of a storage syscall on an address domain other than 0.
+const UNSUPPORTED_ADDRESS_DOMAIN_ERROR: &[u8] = b"Unsupported address domain";
+
+/// The block number and timestamp seen in validate mode are rounded down to these multiples, so
+/// that the validation of a transaction does not depend on the block it lands in.
+const VALIDATE_BLOCK_NUMBER_ROUNDING: u64 = 100;
+const VALIDATE_TIMESTAMP_ROUNDING: u64 = 3600;
+
+/// The response of a syscall: its fields, or the revert reason if it failed.
+type SyscallResponse = Result<Vec<MaybeRelocatable>, Vec<Felt252>>;
+
+/// Executes the Starknet syscalls of an entrypoint run against the state.
+///
+/// The handler owns the state during the run; inner calls borrow it in turn.
+pub struct SyscallHandler {
+    pub config: RunConfig,
+    pub gas_costs: SyscallGasCosts,
+    pub state: State,
+    pub caller_address: Felt252,
+    /// The address of the contract whose storage is used.
+    pub contract_address: Felt252,
+    pub entry_point_selector: Felt252,
+    /// The number of events emitted in the whole execution, including by other calls.
+    pub n_emitted_events: usize,
+    pub events: Vec<EmittedEvent>,
+    pub storage_accesses: Vec<StorageAccess>,
+    pub l2_to_l1_messages: Vec<MessageToL1>,
+    pub inner_calls: Vec<CallInfo>,
+    /// The number of calls of each syscall, by selector.
+    pub syscall_counter: BTreeMap<String, usize>,
+    /// The steps left to the run when it made the current syscall, shared with its inner calls.
+    pub remaining_steps: Option<usize>,
+}
+
+impl SyscallHandler {
+    pub fn new(
+        config: &RunConfig,
+        state: State,
+        caller_address: Felt252,
+        contract_address: Felt252,
+    ) -> Self {
+        Self {
+            config: config.clone(),
+            gas_costs: config.syscall_gas_costs(),
+            state,
+            caller_address,
+            contract_address,
+            entry_point_selector: Felt252::ZERO,
+            n_emitted_events: 0,
+            events: vec![],
+            storage_accesses: vec![],
+            l2_to_l1_messages: vec![],
+            inner_calls: vec![],
+            syscall_counter: BTreeMap::new(),
+            remaining_steps: config.max_steps,
+        }
+    }
+
+    /// Executes the syscall whose request starts at `syscall_ptr` and writes its response.
+    ///
+    /// A request is the syscall selector, the available gas and the syscall arguments. The
+    /// response, written right after it, is the remaining gas, a failure flag and either the
+    /// syscall results or the revert reason.
+    pub fn execute(
+        &mut self,
+        vm: &mut VirtualMachine,
+        syscall_ptr: Relocatable,
+    ) -> Result<(), HintError> {
+        let selector = vm.get_integer(syscall_ptr)?.into_owned();
+        let selector = as_short_string(&selector.to_biguint()).unwrap_or_default();
+        *self.syscall_counter.entry(selector.clone()).or_default() += 1;
+        match selector.as_str() {
+            "CallContract" => self.execute_syscall(
+                vm,
+                syscall_ptr,
+                4,
+                self.gas_costs.call_contract,
+                Self::call_contract,
+            ),
+            "Deploy" => {
+                self.execute_syscall(vm, syscall_ptr, 5, self.gas_costs.deploy, Self::deploy)
+            }
+            "EmitEvent" => self.execute_syscall(
+                vm,
+                syscall_ptr,
+                4,
+                self.gas_costs.emit_event,
+                Self::emit_event,
+            ),
+            "GetExecutionInfo" => self.execute_syscall(
+                vm,
+                syscall_ptr,
+                0,
+                self.gas_costs.get_execution_info,
+                Self::get_execution_info,
+            ),
+            "LibraryCall" => self.execute_syscall(
+                vm,
+                syscall_ptr,
+                4,
+                self.gas_costs.library_call,
+                Self::library_call,
+            ),
+            "ReplaceClass" => self.execute_syscall(
+                vm,
+                syscall_ptr,
+                1,
+                self.gas_costs.replace_class,
+                Self::replace_class,
+            ),
+            "SendMessageToL1" => self.execute_syscall(
+                vm,
+                syscall_ptr,
+                3,
+                self.gas_costs.send_message_to_l1,
+                Self::send_message_to_l1,
+            ),
+            "StorageRead" => self.execute_syscall(
+                vm,
+                syscall_ptr,
+                2,
+                self.gas_costs.storage_read,
+                Self::storage_read,
+            ),
+            "StorageWrite" => self.execute_syscall(
+                vm,
+                syscall_ptr,
+                3,
+                self.gas_costs.storage_write,
+                Self::storage_write,
+            ),
+            _ => Err(HintError::CustomHint(
+                format!("Unsupported syscall `{}`.", selector).into(),
+            )),
+        }
+    }
+
+    /// Charges the gas of a syscall, runs it and writes its response.
+    ///
+    /// The base cost of the syscall was already withdrawn by the Sierra code, so only the rest of
+    /// `gas_cost` is deducted from the gas of the request, like the sequencer does.
+    fn execute_syscall<F>(
+        &mut self,
+        vm: &mut VirtualMachine,
+        syscall_ptr: Relocatable,
+        request_size: usize,
+        gas_cost: u64,
+        syscall: F,
+    ) -> Result<(), HintError>
+    where
+        F: FnOnce(
+            &mut Self,
+            &mut VirtualMachine,
+            Relocatable,
+            &mut u64,
+        ) -> Result<SyscallResponse, HintError>,
+    {
+        let gas = vm
+            .get_integer((syscall_ptr + 1)?)?
+            .to_u64()
+            .unwrap_or(u64::MAX);
+        let request_ptr = (syscall_ptr + 2)?;
+        let response_ptr = (request_ptr + request_size)?;
+
+        let gas_cost = gas_cost.saturating_sub(SYSCALL_BASE_GAS_COST);
+        let (remaining_gas, response) = if gas < gas_cost {
+            (
+                gas,
+                Err(vec![Felt252::from_bytes_be_slice(OUT_OF_GAS_ERROR)]),
+            )
+        } else {
+            let mut remaining_gas = gas - gas_cost;
+            let response = syscall(self, vm, request_ptr, &mut remaining_gas)?;
+            (remaining_gas, response)
+        };
+
+        let mut values = vec![MaybeRelocatable::from(Felt252::from(remaining_gas))];
+        match response {
+            Ok(fields) => {
+                values.push(Felt252::ZERO.into());
+                values.extend(fields);
+            }
+            Err(revert_reason) => {
+                let revert_reason: Vec<MaybeRelocatable> =
+                    revert_reason.into_iter().map(Into::into).collect();
+                let (start, end) = allocate_segment(vm, &revert_reason)?;
+                values.extend([Felt252::ONE.into(), start.into(), end.into()]);
+            }
+        }
+        vm.load_data(response_ptr, &values)?;
+        Ok(())
+    }
+
+    fn emit_event(
+        &mut self,
+        vm: &mut VirtualMachine,
+        request_ptr: Relocatable,
+        _remaining_gas: &mut u64,
+    ) -> Result<SyscallResponse, HintError> {
+        let keys = read_felt_span(vm, request_ptr)?;
+        let data = read_felt_span(vm, (request_ptr + 2)?)?;
+        self.events.push(EmittedEvent {
+            order: self.n_emitted_events,
+            contract_address: self.contract_address,
+            keys,
+            data,
+            decoded: None,
+        });
+        self.n_emitted_events += 1;
+        Ok(Ok(vec![]))
+    }
+
+    /// Replaces the class of the current contract, taking effect from its next call.
+    fn replace_class(
+        &mut self,
+        vm: &mut VirtualMachine,
+        request_ptr: Relocatable,
+        _remaining_gas: &mut u64,
+    ) -> Result<SyscallResponse, HintError> {
+        let class_hash = vm.get_integer(request_ptr)?.into_owned();
+        self.state
+            .replace_class(self.contract_address, class_hash)
+            .map_err(|e| HintError::CustomHint(e.to_string().into()))?;
+        Ok(Ok(vec![]))
+    }
+
+    fn send_message_to_l1(
+        &mut self,
+        vm: &mut VirtualMachine,
+        request_ptr: Relocatable,
+        _remaining_gas: &mut u64,
+    ) -> Result<SyscallResponse, HintError> {
+        let to_address = vm.get_integer(request_ptr)?.into_owned();
+        let payload = read_felt_span(vm, (request_ptr + 1)?)?;
+        self.l2_to_l1_messages.push(MessageToL1 {
+            from_address: self.contract_address,
+            to_address,
+            payload,
+        });
+        Ok(Ok(vec![]))
+    }
+
+    /// Writes the execution info, block info and transaction info of the call to new segments.
+    fn get_execution_info(
+        &mut self,
+        vm: &mut VirtualMachine,
+        _request_ptr: Relocatable,
+        _remaining_gas: &mut u64,
+    ) -> Result<SyscallResponse, HintError> {
+        let context = &self.config.execution_context;
+        let block_info: Vec<MaybeRelocatable> = match self.config.execution_mode {
+            ExecutionMode::Execute => vec![
+                Felt252::from(context.block_number).into(),
+                Felt252::from(context.block_timestamp).into(),
+                context.sequencer_address.into(),
+            ],
+            ExecutionMode::Validate => vec![
+                Felt252::from(
+                    context.block_number / VALIDATE_BLOCK_NUMBER_ROUNDING
+                        * VALIDATE_BLOCK_NUMBER_ROUNDING,
+                )
+                .into(),
+                Felt252::from(
+                    context.block_timestamp / VALIDATE_TIMESTAMP_ROUNDING
+                        * VALIDATE_TIMESTAMP_ROUNDING,
+                )
+                .into(),
+                Felt252::ZERO.into(),
+            ],
+        };
+        let (block_info_ptr, _) = allocate_segment(vm, &block_info)?;
+
+        let (signature_start, signature_end) = allocate_felts(vm, &context.signature)?;
+        let resource_bounds: Vec<Felt252> = context
+            .resource_bounds
+            .iter()
+            .flat_map(|bounds| {
+                [
+                    Felt252::from_bytes_be_slice(bounds.resource.as_bytes()),
+                    Felt252::from(bounds.max_amount),
+                    Felt252::from(bounds.max_price_per_unit),
+                ]
+            })
+            .collect();
+        let (resource_bounds_start, resource_bounds_end) = allocate_felts(vm, &resource_bounds)?;
+        let (paymaster_data_start, paymaster_data_end) =
+            allocate_felts(vm, &context.paymaster_data)?;
+        let (account_deployment_data_start, account_deployment_data_end) =
+            allocate_felts(vm, &context.account_deployment_data)?;
+        let tx_info: Vec<MaybeRelocatable> = vec![
+            context.version.into(),
+            context.account_contract_address.into(),
+            Felt252::from(context.max_fee).into(),
+            signature_start.into(),
+            signature_end.into(),
+            context.transaction_hash.into(),
+            context.chain_id.into(),
+            context.nonce.into(),
+            resource_bounds_start.into(),
+            resource_bounds_end.into(),
+            Felt252::from(context.tip).into(),
+            paymaster_data_start.into(),
+            paymaster_data_end.into(),
+            Felt252::from(context.nonce_data_availability_mode).into(),
+            Felt252::from(context.fee_data_availability_mode).into(),
+            account_deployment_data_start.into(),
+            account_deployment_data_end.into(),
+        ];
+        let (tx_info_ptr, _) = allocate_segment(vm, &tx_info)?;
+
+        let execution_info: Vec<MaybeRelocatable> = vec![
+            block_info_ptr.into(),
+            tx_info_ptr.into(),
+            self.caller_address.into(),
+            self.contract_address.into(),
+            self.entry_point_selector.into(),
+        ];
+        let (execution_info_ptr, _) = allocate_segment(vm, &execution_info)?;
+        Ok(Ok(vec![execution_info_ptr.into()]))
+    }
+
+    fn storage_read(
+        &mut self,
+        vm: &mut VirtualMachine,
+        request_ptr: Relocatable,
+        _remaining_gas: &mut u64,
+    ) -> Result<SyscallResponse, HintError> {
+        if vm.get_integer(request_ptr)?.into_owned() != Felt252::ZERO {
+            return Ok(Err(vec![Felt252::from_bytes_be_slice(
+                UNSUPPORTED_ADDRESS_DOMAIN_ERROR,
+            )]));
+        }
+        let key = vm.get_integer((request_ptr + 1)?)?.into_owned();
+        let value = self
+            .state
+            .get_storage_at(self.contract_address, key)
+            .map_err(|e| HintError::CustomHint(e.to_string().into()))?;
+        self.storage_accesses.push(StorageAccess {
+            kind: StorageAccessKind::Read,
+            key,
+            value,
+            previous_value: None,
+            variable: None,
+        });
+        Ok(Ok(vec![value.into()]))
+    }
+
+    fn storage_write(
+        &mut self,
+        vm: &mut VirtualMachine,
+        request_ptr: Relocatable,
+        _remaining_gas: &mut u64,
+    ) -> Result<SyscallResponse, HintError> {
+        if vm.get_integer(request_ptr)?.into_owned() != Felt252::ZERO {
+            return Ok(Err(vec![Felt252::from_bytes_be_slice(
+                UNSUPPORTED_ADDRESS_DOMAIN_ERROR,
+            )]));
+        }
+        let key = vm.get_integer((request_ptr + 1)?)?.into_owned();
+        let value = vm.get_integer((request_ptr + 2)?)?.into_owned();
+        let previous_value = self
+            .state
+            .get_storage_at(self.contract_address, key)
+            .map_err(|e| HintError::CustomHint(e.to_string().into()))?;
+        self.state.set_storage_at(self.contract_address, key, value);
+        self.storage_accesses.push(StorageAccess {
+            kind: StorageAccessKind::Write,
+            key,
+            value,
+            previous_value: Some(previous_value),
+            variable: None,
+        });
+        Ok(Ok(vec![]))
+    }
+
+    fn call_contract(
+        &mut self,
+        vm: &mut VirtualMachine,
+        request_ptr: Relocatable,
+        remaining_gas: &mut u64,
+    ) -> Result<SyscallResponse, HintError> {
+        let contract_address = vm.get_integer(request_ptr)?.into_owned();
+        if self.config.execution_mode == ExecutionMode::Validate
+            && contract_address != self.contract_address
+        {
+            return Err(HintError::CustomHint(
+                "Unauthorized syscall call_contract in execution mode validate.".into(),
+            ));
+        }
+        let call = CallEntryPoint {
+            call_type: CallType::Call,
+            caller_address: self.contract_address,
+            contract_address,
+            class_hash: None,
+            entry_point_type: EntryPointType::External,
+            entry_point_selector: vm.get_integer((request_ptr + 1)?)?.into_owned(),
+            calldata: read_felt_span(vm, (request_ptr + 2)?)?,
+            initial_gas: *remaining_gas,
+        };
+        self.execute_inner_call(vm, call, remaining_gas)
+    }
+
+    fn library_call(
+        &mut self,
+        vm: &mut VirtualMachine,
+        request_ptr: Relocatable,
+        remaining_gas: &mut u64,
+    ) -> Result<SyscallResponse, HintError> {
+        // The library code runs in the context of the current contract.
+        let call = CallEntryPoint {
+            call_type: CallType::Delegate,
+            caller_address: self.caller_address,
+            contract_address: self.contract_address,
+            class_hash: Some(vm.get_integer(request_ptr)?.into_owned()),
+            entry_point_type: EntryPointType::External,
+            entry_point_selector: vm.get_integer((request_ptr + 1)?)?.into_owned(),
+            calldata: read_felt_span(vm, (request_ptr + 2)?)?,
+            initial_gas: *remaining_gas,
+        };
+        self.execute_inner_call(vm, call, remaining_gas)
+    }
+
+    /// Deploys a contract at the address given by the deployer, the salt, the class hash and the
+    /// constructor calldata, and runs its constructor. The deployer is zero if `deploy_from_zero`
+    /// is set, to deploy at the same address from any contract.
+    fn deploy(
+        &mut self,
+        vm: &mut VirtualMachine,
+        request_ptr: Relocatable,
+        remaining_gas: &mut u64,
+    ) -> Result<SyscallResponse, HintError> {
+        if self.config.execution_mode == ExecutionMode::Validate {
+            return Err(HintError::CustomHint(
+                "Unauthorized syscall deploy in execution mode validate.".into(),
+            ));
+        }
+        let class_hash = vm.get_integer(request_ptr)?.into_owned();
+        let salt = vm.get_integer((request_ptr + 1)?)?.into_owned();
+        let calldata = read_felt_span(vm, (request_ptr + 2)?)?;
+        let deploy_from_zero = vm.get_integer((request_ptr + 4)?)?.into_owned() != Felt252::ZERO;
+        let deployer_address = if deploy_from_zero {
+            Felt252::ZERO
+        } else {
+            self.contract_address
+        };
+        let contract_address =
+            calculate_contract_address(salt, class_hash, &calldata, deployer_address);
+        if self.state.get_class_hash_at(&contract_address).is_ok() {
+            return Ok(Err(vec![Felt252::from_bytes_be_slice(
+                CONTRACT_ADDRESS_UNAVAILABLE_ERROR,
+            )]));
+        }
+        let checkpoint = self.state.checkpoint();
+        if let Err(e) = self.state.deploy_contract(contract_address, class_hash) {
+            self.state.revert(checkpoint);
+            return Err(HintError::CustomHint(e.to_string().into()));
+        }
+
+        let call = CallEntryPoint {
+            call_type: CallType::Call,
+            caller_address: deployer_address,
+            contract_address,
+            class_hash: Some(class_hash),
+            entry_point_type: EntryPointType::Constructor,
+            entry_point_selector: selector_from_name("constructor"),
+            calldata,
+            initial_gas: *remaining_gas,
+        };
+        let response = self.execute_inner_call(vm, call, remaining_gas);
+        match response {
+            Ok(Ok(retdata)) => {
+                self.state.commit();
+                Ok(Ok([vec![contract_address.into()], retdata].concat()))
+            }
+            // The deployment is reverted with the constructor.
+            Ok(Err(revert_reason)) => {
+                self.state.revert(checkpoint);
+                Ok(Err(revert_reason))
+            }
+            Err(e) => {
+                self.state.revert(checkpoint);
+                Err(e)
+            }
+        }
+    }
+
+    /// Runs an inner call against the state, charging the gas it consumed.
+    fn execute_inner_call(
+        &mut self,
+        vm: &mut VirtualMachine,
+        call: CallEntryPoint,
+        remaining_gas: &mut u64,
+    ) -> Result<SyscallResponse, HintError> {
+        let mut config = self.config.clone();
+        config.max_steps = self.remaining_steps;
+        let call_info = execute_call(&mut self.state, &mut self.n_emitted_events, &call, &config)
+            .map_err(|e| HintError::CustomHint(e.to_string().into()))?;
+        // An inner call stopped by the step limit used up the steps of this run too, which stops
+        // right after the syscall.
+        if let Some(remaining_steps) = &mut self.remaining_steps {
+            *remaining_steps = remaining_steps.saturating_sub(call_info.total_steps());
+        }
+
+        *remaining_gas = remaining_gas.saturating_sub(call_info.gas_consumed.unwrap_or_default());
+        let response = if call_info.status == ExecutionStatus::Succeeded {
+            let (start, end) = allocate_felts(vm, &call_info.retdata)?;
+            Ok(vec![start.into(), end.into()])
+        } else {
+            let mut revert_reason = call_info.retdata.clone();
+            revert_reason.push(Felt252::from_bytes_be_slice(ENTRYPOINT_FAILED_ERROR));
+            Err(revert_reason)
+        };
+        self.inner_calls.push(call_info);
+        Ok(response)
+    }
+}
+
+/// Writes data to a new segment, returning its start and end pointers.
+fn allocate_segment(
+    vm: &mut VirtualMachine,
+    data: &[MaybeRelocatable],
+) -> Result<(Relocatable, Relocatable), HintError> {
+    let start = vm.add_memory_segment();
+    let end = vm.load_data(start, &data.to_vec())?;
+    Ok((start, end))
+}
+
+/// Writes felts to a new segment and returns the span of its start and end pointers.
+fn allocate_felts(
+    vm: &mut VirtualMachine,
+    data: &[Felt252],
+) -> Result<(Relocatable, Relocatable), HintError> {
+    let data: Vec<MaybeRelocatable> = data.iter().map(MaybeRelocatable::from).collect();
+    allocate_segment(vm, &data)
+}
+
+/// Reads a span of felts given by its start and end pointers.
+pub fn read_felt_span(vm: &VirtualMachine, ptr: Relocatable) -> Result<Vec<Felt252>, HintError> {
+    let start = vm.get_relocatable(ptr)?;
+    let end = vm.get_relocatable((ptr + 1)?)?;
+    Ok(vm
+        .get_integer_range(start, (end - start)?)?
+        .into_iter()
+        .map(|value| value.into_owned())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_storage_read_gas() {
+        let config = RunConfig::default();
+        let contract_address = Felt252::from(0x100);
+        let key = Felt252::from(0x10);
+        let mut state = State::default();
+        state.set_storage_at(contract_address, key, Felt252::from(7));
+        let mut handler = SyscallHandler::new(&config, state, Felt252::ZERO, contract_address);
+
+        let mut vm = VirtualMachine::new(false);
+        let syscall_ptr = vm.add_memory_segment();
+        vm.load_data(
+            syscall_ptr,
+            &vec![
+                Felt252::from_bytes_be_slice(b"StorageRead").into(),
+                Felt252::from(100_000).into(),
+                Felt252::ZERO.into(),
+                key.into(),
+            ],
+        )
+        .unwrap();
+        handler.execute(&mut vm, syscall_ptr).unwrap();
+
+        // The storage read costs 50 steps on top of the base cost withdrawn by the Sierra code.
+        let response: Vec<Felt252> = vm
+            .get_integer_range((syscall_ptr + 4_usize).unwrap(), 3)
+            .unwrap()
+            .into_iter()
+            .map(|value| value.into_owned())
+            .collect();
+        assert_eq!(
+            response,
+            vec![Felt252::from(95_000), Felt252::ZERO, Felt252::from(7)]
+        );
+    }
+}
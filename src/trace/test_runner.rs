@@ -0,0 +1,276 @@
+use anyhow::{Context, Result};
+use cairo_lang_runner::{RunResultValue, SierraCasmRunner, StarknetState};
+use cairo_lang_sierra_to_casm::metadata::MetadataComputationConfig;
+use cairo_lang_test_plugin::test_config::{PanicExpectation, TestExpectation};
+use cairo_lang_test_plugin::{TestCompilation, TestCompilationMetadata, TestConfig};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+
+use super::config::RunConfig;
+use super::run::run_function;
+use super::short_string::as_short_string;
+use super::sierra_trace::SierraDebugInfo;
+use crate::cairo_sierra::cairo_helper::{CairoLocation, FullProgram};
+use crate::casm_sierra::cairo::get_casm_sierra_mapping_instructions;
+use crate::compiler::compile_tests::{compile_tests, TestsCompilationResult};
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TestResult {
+    pub name: String,
+    pub status: TestStatus,
+    /// Whether the test is marked `#[should_panic]`.
+    pub should_panic: bool,
+    pub available_gas: Option<usize>,
+    pub gas_consumed: Option<u64>,
+    /// The panic data of the test, if it panicked.
+    pub panic_data: Option<Vec<String>>,
+    /// The panic data decoded as short strings, where possible.
+    pub panic_reason: Option<Vec<String>>,
+    /// Why the test failed, if it did.
+    pub failure: Option<String>,
+    /// The Cairo code the failing test stopped at.
+    pub location: Option<CairoLocation>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TestRunSummary {
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+    pub tests: Vec<TestResult>,
+}
+
+/// Compiles and runs all the tests in the given code.
+pub fn run_tests(code: &str, file_name: &str) -> Result<TestRunSummary> {
+    let TestsCompilationResult {
+        test_compilation:
+            TestCompilation {
+                sierra_program,
+                metadata:
+                    TestCompilationMetadata {
+                        named_tests,
+                        function_set_costs,
+                        contracts_info,
+                        ..
+                    },
+            },
+        debug_program,
+    } = compile_tests(code, file_name)?;
+
+    let runner = SierraCasmRunner::new(
+        sierra_program.program,
+        Some(MetadataComputationConfig {
+            function_set_costs,
+            linear_gas_solver: true,
+            linear_ap_change_solver: true,
+            skip_non_linear_solver_comparisons: false,
+            compute_runtime_costs: false,
+        }),
+        contracts_info,
+        None,
+    )
+    .with_context(|| "Failed setting up runner.")?;
+
+    let mut tests = vec![];
+    for (name, config) in named_tests {
+        let result = if config.ignored {
+            TestResult::new(name, &config, TestStatus::Ignored)
+        } else {
+            run_test(&runner, name, &config)
+        };
+        tests.push(result);
+    }
+
+    for test in tests.iter_mut() {
+        if test.status == TestStatus::Failed {
+            test.location = locate_failure(&debug_program, test, file_name);
+        }
+    }
+
+    Ok(TestRunSummary {
+        passed: count(&tests, TestStatus::Passed),
+        failed: count(&tests, TestStatus::Failed),
+        ignored: count(&tests, TestStatus::Ignored),
+        tests,
+    })
+}
+
+/// Runs a single test and checks its result against its expectation.
+fn run_test(runner: &SierraCasmRunner, name: String, config: &TestConfig) -> TestResult {
+    let mut result = TestResult::new(name, config, TestStatus::Failed);
+    let func = match runner.find_function(&result.name) {
+        Ok(func) => func,
+        Err(e) => {
+            result.failure = Some(e.to_string());
+            return result;
+        }
+    };
+    // Tests without `#[available_gas]` run with unlimited gas.
+    let available_gas = config.available_gas.or(Some(usize::MAX));
+    let run_result = match runner.run_function_with_starknet_context(
+        func,
+        &[],
+        available_gas,
+        StarknetState::default(),
+    ) {
+        Ok(run_result) => run_result,
+        Err(e) => {
+            result.failure = Some(e.to_string());
+            return result;
+        }
+    };
+
+    if let (Some(available_gas), Some(gas_counter)) = (config.available_gas, run_result.gas_counter)
+    {
+        let remaining_gas: u64 = gas_counter.to_biguint().try_into().unwrap_or_default();
+        result.gas_consumed = (available_gas as u64).checked_sub(remaining_gas);
+    }
+
+    let (status, failure) = match (&run_result.value, &config.expectation) {
+        (RunResultValue::Success(_), TestExpectation::Success) => (TestStatus::Passed, None),
+        (RunResultValue::Success(_), TestExpectation::Panics(_)) => (
+            TestStatus::Failed,
+            Some("Expected the test to panic.".to_string()),
+        ),
+        (RunResultValue::Panic(_), TestExpectation::Success) => {
+            (TestStatus::Failed, Some("The test panicked.".to_string()))
+        }
+        (RunResultValue::Panic(_), TestExpectation::Panics(PanicExpectation::Any)) => {
+            (TestStatus::Passed, None)
+        }
+        (
+            RunResultValue::Panic(data),
+            TestExpectation::Panics(PanicExpectation::Exact(expected)),
+        ) => {
+            if data == expected {
+                (TestStatus::Passed, None)
+            } else {
+                (
+                    TestStatus::Failed,
+                    Some("The test panicked with unexpected panic data.".to_string()),
+                )
+            }
+        }
+    };
+    result.status = status;
+    result.failure = failure;
+
+    if let RunResultValue::Panic(data) = &run_result.value {
+        let data: Vec<BigUint> = data.iter().map(|felt| felt.to_biguint()).collect();
+        result.panic_data = Some(data.iter().map(|value| format!("{:#x}", value)).collect());
+        result.panic_reason = Some(data.iter().filter_map(as_short_string).collect());
+    }
+    result
+}
+
+impl TestResult {
+    fn new(name: String, config: &TestConfig, status: TestStatus) -> Self {
+        Self {
+            name,
+            status,
+            should_panic: matches!(config.expectation, TestExpectation::Panics(_)),
+            available_gas: config.available_gas,
+            gas_consumed: None,
+            panic_data: None,
+            panic_reason: None,
+            failure: None,
+            location: None,
+        }
+    }
+}
+
+fn count(tests: &[TestResult], status: TestStatus) -> usize {
+    tests.iter().filter(|test| test.status == status).count()
+}
+
+/// Locates the code a failed test stopped at: the test runs again with a trace, and the last
+/// executed statement generated from the tested file is resolved to its Cairo location.
+///
+/// No contract is declared in that run, so a test failing after a syscall may stop earlier.
+fn locate_failure(
+    debug_program: &FullProgram,
+    test: &TestResult,
+    file_name: &str,
+) -> Option<CairoLocation> {
+    let config = RunConfig {
+        initial_gas: test.available_gas.map_or(u64::MAX, |gas| gas as u64),
+        ..RunConfig::default()
+    };
+    let run = run_function(&debug_program.program, &test.name, &[], &config).ok()?;
+    let casm_sierra_mapping_instruction =
+        get_casm_sierra_mapping_instructions(run.casm_program).ok()?;
+    let debug_info = SierraDebugInfo::new(
+        debug_program.program.clone(),
+        &casm_sierra_mapping_instruction,
+        debug_program.sierra_cairo_info_mapping.clone(),
+    );
+
+    let source_file = format!("{}.cairo", file_name);
+    debug_info
+        .sierra_trace(&run.trace, run.code_offset)
+        .iter()
+        .rev()
+        .find_map(|entry| {
+            let location = debug_info
+                .cairo_locations(entry.statement_idx)?
+                .iter()
+                .find(|location| location.file_name.ends_with(&source_file))?;
+            Some(CairoLocation {
+                file_name: file_name.to_string(),
+                ..location.clone()
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_tests() {
+        let code = r#"fn add(a: felt252, b: felt252) -> felt252 {
+            a + b
+        }
+
+        #[cfg(test)]
+        mod tests {
+            #[test]
+            fn test_add() {
+                assert(super::add(1, 2) == 3, 'wrong sum');
+            }
+
+            #[test]
+            #[should_panic(expected: ('wrong sum',))]
+            fn test_add_panics() {
+                assert(super::add(1, 2) == 4, 'wrong sum');
+            }
+
+            #[test]
+            #[available_gas(100000)]
+            fn test_add_fails() {
+                assert(super::add(1, 2) == 4, 'wrong sum');
+            }
+        }
+        "#;
+        let summary = run_tests(code, "add").unwrap();
+        assert_eq!(summary.passed, 2);
+        assert_eq!(summary.failed, 1);
+
+        let failed = summary
+            .tests
+            .iter()
+            .find(|test| test.status == TestStatus::Failed)
+            .unwrap();
+        assert_eq!(failed.panic_reason, Some(vec!["wrong sum".to_string()]));
+        // The failing assert, not the declaration of the test.
+        assert_eq!(failed.location.as_ref().unwrap().start.line, 20);
+    }
+}
@@ -0,0 +1,358 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::resources::ExecutionResources;
+use crate::rpc::invoke::TransactionType;
+
+/// The gas cost of a single use of each builtin that is charged through the builtin costs table.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BuiltinCosts {
+    pub pedersen: u64,
+    pub bitwise: u64,
+    pub ec_op: u64,
+    pub poseidon: u64,
+    pub add_mod: u64,
+    pub mul_mod: u64,
+}
+
+impl BuiltinCosts {
+    /// Returns the costs in the order the compiled code expects them in the builtin costs segment.
+    pub fn to_vec(&self) -> Vec<u64> {
+        vec![
+            self.pedersen,
+            self.bitwise,
+            self.ec_op,
+            self.poseidon,
+            self.add_mod,
+            self.mul_mod,
+        ]
+    }
+}
+
+/// Gas charged by the syscalls, on top of the gas of any inner call they make.
+///
+/// Expressed in gas, at 100 gas per Cairo step.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyscallGasCosts {
+    pub call_contract: u64,
+    pub deploy: u64,
+    pub emit_event: u64,
+    pub get_block_hash: u64,
+    pub get_execution_info: u64,
+    pub keccak: u64,
+    pub keccak_round: u64,
+    pub library_call: u64,
+    pub replace_class: u64,
+    pub send_message_to_l1: u64,
+    pub storage_read: u64,
+    pub storage_write: u64,
+}
+
+/// Gas per Cairo step.
+const STEP_GAS_COST: u64 = 100;
+
+/// Gas charged by every syscall. The Sierra code withdraws it before the syscall, so the handler
+/// only charges the rest of the syscall cost.
+pub const SYSCALL_BASE_GAS_COST: u64 = 100 * STEP_GAS_COST;
+
+/// Gas charged for entering a called entrypoint.
+const ENTRY_POINT_GAS_COST: u64 = 10_000 + 500 * STEP_GAS_COST;
+
+impl Default for SyscallGasCosts {
+    fn default() -> Self {
+        Self {
+            call_contract: SYSCALL_BASE_GAS_COST + 10 * STEP_GAS_COST + ENTRY_POINT_GAS_COST,
+            deploy: SYSCALL_BASE_GAS_COST + 200 * STEP_GAS_COST + ENTRY_POINT_GAS_COST,
+            emit_event: SYSCALL_BASE_GAS_COST + 10 * STEP_GAS_COST,
+            get_block_hash: SYSCALL_BASE_GAS_COST + 50 * STEP_GAS_COST,
+            get_execution_info: SYSCALL_BASE_GAS_COST + 10 * STEP_GAS_COST,
+            keccak: SYSCALL_BASE_GAS_COST,
+            keccak_round: 180_000,
+            library_call: SYSCALL_BASE_GAS_COST + 10 * STEP_GAS_COST + ENTRY_POINT_GAS_COST,
+            replace_class: SYSCALL_BASE_GAS_COST + 50 * STEP_GAS_COST,
+            send_message_to_l1: SYSCALL_BASE_GAS_COST + 50 * STEP_GAS_COST,
+            storage_read: SYSCALL_BASE_GAS_COST + 50 * STEP_GAS_COST,
+            storage_write: SYSCALL_BASE_GAS_COST + 50 * STEP_GAS_COST,
+        }
+    }
+}
+
+/// The L1 gas cost of the resources of a transaction, in units of 10^-4 L1 gas.
+///
+/// The VM resources are charged by their bottleneck: the most expensive of the steps and of each
+/// builtin. Calldata, signatures and events are charged per felt.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeCosts {
+    pub n_steps: u64,
+    /// By builtin name with its `_builtin` suffix, e.g. `pedersen_builtin`.
+    pub builtins: BTreeMap<String, u64>,
+    pub gas_per_data_felt: u64,
+    /// How many data felts an event key costs.
+    pub event_key_factor: u64,
+    /// Whether the state diff is published in blobs, charged in L1 data gas, rather than in
+    /// calldata, charged in L1 gas.
+    pub use_kzg_da: bool,
+}
+
+impl FeeCosts {
+    fn new(gas_per_data_felt: u64, use_kzg_da: bool) -> Self {
+        let builtins = [
+            ("output_builtin", 0),
+            ("pedersen_builtin", 800),
+            ("range_check_builtin", 400),
+            ("ecdsa_builtin", 51_200),
+            ("bitwise_builtin", 1_600),
+            ("ec_op_builtin", 25_600),
+            ("keccak_builtin", 51_200),
+            ("poseidon_builtin", 800),
+            ("range_check96_builtin", 400),
+            ("add_mod_builtin", 400),
+            ("mul_mod_builtin", 400),
+        ];
+        Self {
+            n_steps: 25,
+            builtins: builtins
+                .into_iter()
+                .map(|(name, cost)| (name.to_string(), cost))
+                .collect(),
+            gas_per_data_felt,
+            event_key_factor: 2,
+            use_kzg_da,
+        }
+    }
+}
+
+/// The VM resources the Starknet OS spends around the calls of a transaction, on top of their own
+/// resources.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OsResources {
+    /// The resources of each syscall, by name, e.g. `StorageRead`.
+    pub execute_syscalls: BTreeMap<String, ExecutionResources>,
+    pub invoke: TransactionOsResources,
+    pub declare: TransactionOsResources,
+    pub deploy_account: TransactionOsResources,
+}
+
+/// The resources of running a transaction of a given type in the OS.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionOsResources {
+    pub constant: ExecutionResources,
+    /// The resources per felt of calldata.
+    pub calldata_factor: ExecutionResources,
+}
+
+impl OsResources {
+    /// The resources of a transaction of the given type with `calldata_length` felts of calldata.
+    pub fn transaction_resources(
+        &self,
+        transaction_type: TransactionType,
+        calldata_length: usize,
+    ) -> ExecutionResources {
+        let resources = match transaction_type {
+            TransactionType::Invoke => &self.invoke,
+            TransactionType::Declare => &self.declare,
+            TransactionType::DeployAccount => &self.deploy_account,
+        };
+        let mut total = resources.constant.clone();
+        total.add(&resources.calldata_factor.multiply(calldata_length));
+        total
+    }
+
+    /// The resources of the syscalls counted in `syscall_counter`.
+    pub fn syscall_resources(
+        &self,
+        syscall_counter: &BTreeMap<String, usize>,
+    ) -> ExecutionResources {
+        let mut total = ExecutionResources::default();
+        for (syscall, count) in syscall_counter {
+            if let Some(resources) = self.execute_syscalls.get(syscall) {
+                total.add(&resources.multiply(*count));
+            }
+        }
+        total
+    }
+}
+
+impl Default for OsResources {
+    fn default() -> Self {
+        let execute_syscalls = [
+            (
+                "CallContract",
+                resources(827, &[("range_check_builtin", 15)]),
+            ),
+            (
+                "Deploy",
+                resources(
+                    1097,
+                    &[("pedersen_builtin", 7), ("range_check_builtin", 18)],
+                ),
+            ),
+            ("EmitEvent", resources(61, &[("range_check_builtin", 1)])),
+            ("GetBlockHash", resources(74, &[("range_check_builtin", 2)])),
+            (
+                "GetExecutionInfo",
+                resources(64, &[("range_check_builtin", 1)]),
+            ),
+            (
+                "Keccak",
+                resources(
+                    381,
+                    &[
+                        ("bitwise_builtin", 6),
+                        ("keccak_builtin", 1),
+                        ("range_check_builtin", 56),
+                    ],
+                ),
+            ),
+            (
+                "LibraryCall",
+                resources(818, &[("range_check_builtin", 15)]),
+            ),
+            ("ReplaceClass", resources(98, &[("range_check_builtin", 1)])),
+            (
+                "SendMessageToL1",
+                resources(139, &[("range_check_builtin", 1)]),
+            ),
+            ("StorageRead", resources(87, &[("range_check_builtin", 1)])),
+            ("StorageWrite", resources(89, &[("range_check_builtin", 1)])),
+        ];
+        Self {
+            execute_syscalls: execute_syscalls
+                .into_iter()
+                .map(|(name, resources)| (name.to_string(), resources))
+                .collect(),
+            invoke: TransactionOsResources {
+                constant: resources(
+                    3382,
+                    &[("pedersen_builtin", 16), ("range_check_builtin", 80)],
+                ),
+                calldata_factor: resources(8, &[("pedersen_builtin", 4)]),
+            },
+            declare: TransactionOsResources {
+                constant: resources(
+                    2711,
+                    &[("pedersen_builtin", 15), ("range_check_builtin", 63)],
+                ),
+                calldata_factor: ExecutionResources::default(),
+            },
+            deploy_account: TransactionOsResources {
+                constant: resources(
+                    3628,
+                    &[("pedersen_builtin", 23), ("range_check_builtin", 83)],
+                ),
+                calldata_factor: resources(21, &[("pedersen_builtin", 2)]),
+            },
+        }
+    }
+}
+
+fn resources(n_steps: usize, builtins: &[(&str, usize)]) -> ExecutionResources {
+    ExecutionResources {
+        n_steps,
+        builtin_instance_counter: builtins
+            .iter()
+            .map(|(name, count)| (name.to_string(), *count))
+            .collect(),
+        ..ExecutionResources::default()
+    }
+}
+
+/// A Starknet protocol version with its own set of versioned constants.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StarknetVersion {
+    #[serde(rename = "0.13.0")]
+    V0_13_0,
+    #[serde(rename = "0.13.1")]
+    V0_13_1,
+    #[serde(rename = "0.13.2")]
+    V0_13_2,
+    #[serde(rename = "0.13.3")]
+    V0_13_3,
+    #[default]
+    #[serde(rename = "0.13.4")]
+    V0_13_4,
+}
+
+/// The constants of the Starknet OS that affect execution, for a given protocol version.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionedConstants {
+    pub builtin_costs: BuiltinCosts,
+    pub syscall_gas_costs: SyscallGasCosts,
+    pub fee_costs: FeeCosts,
+    pub os_resources: OsResources,
+}
+
+impl StarknetVersion {
+    pub fn versioned_constants(self) -> VersionedConstants {
+        let builtin_costs = match self {
+            // Until Sierra gas was introduced the OS only charged the bitwise builtin.
+            Self::V0_13_0 | Self::V0_13_1 | Self::V0_13_2 | Self::V0_13_3 => BuiltinCosts {
+                pedersen: 0,
+                bitwise: 594,
+                ec_op: 0,
+                poseidon: 0,
+                add_mod: 0,
+                mul_mod: 0,
+            },
+            Self::V0_13_4 => BuiltinCosts {
+                pedersen: 4050,
+                bitwise: 583,
+                ec_op: 4085,
+                poseidon: 491,
+                add_mod: 230,
+                mul_mod: 604,
+            },
+        };
+        let fee_costs = match self {
+            // Calldata and events were free and the state diff was published in calldata until
+            // 0.13.1.
+            Self::V0_13_0 => FeeCosts::new(0, false),
+            Self::V0_13_1 | Self::V0_13_2 | Self::V0_13_3 | Self::V0_13_4 => {
+                FeeCosts::new(1_280, true)
+            }
+        };
+        VersionedConstants {
+            builtin_costs,
+            // The syscall costs did not change across the supported versions.
+            syscall_gas_costs: SyscallGasCosts::default(),
+            fee_costs,
+            // The OS resources are priced with those of 0.13.1 in all the supported versions.
+            os_resources: OsResources::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_starknet_version_serde() {
+        let version: StarknetVersion = serde_json::from_str(r#""0.13.1""#).unwrap();
+        assert_eq!(version, StarknetVersion::V0_13_1);
+        assert_eq!(
+            version.versioned_constants().builtin_costs.to_vec().len(),
+            6
+        );
+    }
+
+    #[test]
+    fn test_os_resources() {
+        let os_resources = OsResources::default();
+        let syscall_resources =
+            os_resources.syscall_resources(&BTreeMap::from([("StorageRead".to_string(), 2)]));
+        assert_eq!(syscall_resources.n_steps, 2 * 87);
+        assert_eq!(
+            syscall_resources.builtin_instance_counter["range_check_builtin"],
+            2
+        );
+        let invoke_resources = os_resources.transaction_resources(TransactionType::Invoke, 3);
+        assert_eq!(invoke_resources.n_steps, 3382 + 3 * 8);
+        assert_eq!(
+            invoke_resources.builtin_instance_counter["pedersen_builtin"],
+            16 + 3 * 4
+        );
+    }
+}